@@ -21,7 +21,12 @@ try {
         npm run build
         tauri build
     } else {
-        # Build and run development version
+        # Build and run development version. vite needs port 1420; report who holds it
+        # instead of letting `tauri dev` fail halfway (nothing is stopped for you)
+        cargo run --quiet --manifest-path src-tauri/Cargo.toml --bin search-cli -- dev-port 1420
+        if ($LASTEXITCODE -ne 0) {
+            exit 1
+        }
         npm run build
         tauri dev
     }
@@ -0,0 +1,542 @@
+// api_server.rs
+// Optional local HTTP/JSON API, so editors and scripts can run searches in the running app
+// and read the results back. Disabled by default (see ApiSettings). It only listens on
+// 127.0.0.1 and every request must carry the token from the settings:
+//
+//   Authorization: Bearer <token>
+//
+//   GET  /api/status                          app version and running searches
+//   POST /api/searches                        body: search options as JSON -> {"search_id"}
+//   GET  /api/searches/{id}                   status; stats and warnings once finished
+//   POST /api/searches/{id}/cancel            -> {"cancelled": bool}
+//   GET  /api/searches/{id}/results           ?offset&limit&sortBy&descending&groupBy&directory
+//   GET  /api/searches/{id}/matches           ?path&offset&limit
+//   GET  /api/searches/{id}/file              ?path&offset&count, lines of a result file
+//   GET  /api/searches/{id}/hex               ?path&offset&rows, hex dump of a binary result file
+//   GET  /api/searches/{id}/timeline          ?offset&limit, matches of all files by log timestamp
+//   GET  /api/searches/{id}/context           ?path&line&before&after, lines around a match
+//
+// The search options and result pages have the same JSON shape as for the Tauri commands.
+// The routes call an ApiHandler, which main.rs implements on the app's search manager.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::SocketAddr;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::binary_view::HexChunk;
+use crate::logging;
+use crate::result_store::{MatchesPage, PageRequest, ResultsPage, TimelinePage};
+use crate::search::SearchOptions;
+use crate::settings::ApiSettings;
+
+// Search options are small; anything bigger is not a request we want to parse
+const MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+// Defaults for paging parameters the caller leaves out
+const DEFAULT_PAGE_SIZE: usize = 100;
+const DEFAULT_CONTEXT_LINES: usize = 5;
+
+// Threads serving authorized requests; more than one, so a slow page or file read never
+// holds up a cancel
+const WORKER_THREADS: usize = 4;
+
+/// What the API can do; implemented by the app on top of its search manager and sessions.
+/// Errors are answered with 400 unless made with `ApiError::not_found`.
+pub trait ApiHandler: Send + Sync + 'static {
+    fn status(&self) -> Value;
+    /// Start a background search and return its ID
+    fn start_search(&self, options: SearchOptions) -> Result<String, ApiError>;
+    /// {"status": "running"} while it runs, the search summary once it has finished
+    fn search_status(&self, search_id: &str) -> Result<Value, ApiError>;
+    fn cancel_search(&self, search_id: &str) -> bool;
+    fn results_page(&self, search_id: &str, request: &PageRequest) -> Result<ResultsPage, ApiError>;
+    fn file_matches(&self, search_id: &str, path: &str, offset: usize, limit: usize) -> Result<MatchesPage, ApiError>;
+    /// Lines `offset..offset + count` of a file, with its path relative to the search root
+    fn read_chunk(&self, search_id: &str, path: &str, offset: usize, count: Option<usize>) -> Result<Value, ApiError>;
+    /// `rows` rows of 16 bytes from the row containing byte `offset`
+    fn read_hex_chunk(&self, search_id: &str, path: &str, offset: u64, rows: Option<usize>) -> Result<HexChunk, ApiError>;
+    fn timeline(&self, search_id: &str, offset: usize, limit: usize) -> Result<TimelinePage, ApiError>;
+    /// `before` and `after` lines around line `line` (1-based), each with its timestamp and level
+    fn read_context(&self, search_id: &str, path: &str, line: usize, before: usize, after: usize) -> Result<Value, ApiError>;
+}
+
+/// A running server; stops when dropped
+pub struct ApiServer {
+    server: Arc<Server>,
+    threads: Vec<JoinHandle<()>>, // the listener first, then the workers
+    port: u16,
+}
+
+impl ApiServer {
+    /// Listen on 127.0.0.1:`settings.port`. Fails if the port is taken or no token is set.
+    pub fn start(settings: &ApiSettings, handler: Arc<dyn ApiHandler>) -> Result<Self, String> {
+        if settings.token.trim().is_empty() {
+            return Err("The API needs a token".to_string());
+        }
+        let server = Server::http(("127.0.0.1", settings.port))
+            .map_err(|e| format!("Failed to listen on 127.0.0.1:{}: {}", settings.port, e))?;
+        let server = Arc::new(server);
+        let token: Arc<str> = Arc::from(settings.token.trim());
+
+        // The listener checks the token itself, so unauthorized requests are answered
+        // right away and never take up a worker
+        let (queue, jobs) = mpsc::channel::<Request>();
+        let listener = Arc::clone(&server);
+        let mut threads = vec![std::thread::spawn(move || {
+            for request in listener.incoming_requests() {
+                match check_request(&request, &token) {
+                    Ok(()) => {
+                        if let Err(mpsc::SendError(request)) = queue.send(request) {
+                            respond(request, Err(ApiError::new(503, "The API is shutting down")));
+                        }
+                    }
+                    Err(e) => respond(request, Err(e)),
+                }
+            }
+            // Dropping `queue` here lets the workers finish
+        })];
+
+        let jobs = Arc::new(Mutex::new(jobs));
+        for _ in 0..WORKER_THREADS {
+            let jobs = Arc::clone(&jobs);
+            let handler = Arc::clone(&handler);
+            threads.push(std::thread::spawn(move || loop {
+                let next = jobs.lock().unwrap().recv();
+                match next {
+                    Ok(request) => handle(request, handler.as_ref()),
+                    Err(_) => break,
+                }
+            }));
+        }
+        logging::info("API server listening", &[("address", &format!("127.0.0.1:{}", settings.port))]);
+        Ok(Self { server, threads, port: settings.port })
+    }
+}
+
+impl Drop for ApiServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+        logging::info("API server stopped", &[("port", &self.port)]);
+    }
+}
+
+/// A fresh random token: 128 bits from the OS random number generator, as hex
+pub fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Failed to generate an API token: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+// -----------------------------------------------------------
+// Requests
+// -----------------------------------------------------------
+
+/// An error answer: HTTP status and message
+#[derive(Debug)]
+pub struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self { status, message: message.into() }
+    }
+
+    /// 404: an unknown search ID, or a file that is not one of the search's results
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(404, message)
+    }
+}
+
+// Other handler errors are about the request (bad options, lines out of range)
+impl From<String> for ApiError {
+    fn from(message: String) -> Self {
+        Self::new(400, message)
+    }
+}
+
+// Run an authorized request
+fn handle(mut request: Request, handler: &dyn ApiHandler) {
+    let method = request.method().clone();
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+
+    let result = read_body(&mut request).and_then(|body| route(handler, &method, path, &Query::parse(query), &body));
+    respond(request, result);
+}
+
+fn respond(request: Request, result: Result<Value, ApiError>) {
+    let method = request.method().clone();
+    let url = request.url().to_string();
+    let path = url.split_once('?').map_or(url.as_str(), |(path, _)| path);
+    let (status, body) = match result {
+        Ok(value) => (200, value),
+        Err(e) => {
+            logging::warn("API request failed", &[
+                ("method", &method),
+                ("route", &path),
+                ("status", &e.status),
+                ("error", &e.message),
+            ]);
+            (e.status, json!({ "error": e.message }))
+        }
+    };
+    logging::debug("API request", &[("method", &method), ("route", &path), ("status", &status)]);
+
+    let response = Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Content-Type", "application/json").expect("static header"));
+    let _ = request.respond(response);
+}
+
+// Loopback peers with the right token only
+fn check_request(request: &Request, token: &str) -> Result<(), ApiError> {
+    if !request.remote_addr().map(SocketAddr::ip).is_some_and(|ip| ip.is_loopback()) {
+        return Err(ApiError::new(403, "Only local connections are accepted"));
+    }
+    let given = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+        .map(str::trim)
+        .unwrap_or("");
+    if !constant_time_eq(given.as_bytes(), token.as_bytes()) {
+        return Err(ApiError::new(401, "Missing or wrong API token"));
+    }
+    Ok(())
+}
+
+fn read_body(request: &mut Request) -> Result<Vec<u8>, ApiError> {
+    if request.body_length().is_some_and(|len| len as u64 > MAX_BODY_BYTES) {
+        return Err(ApiError::new(413, "Request body too large"));
+    }
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| ApiError::new(400, format!("Failed to read request body: {}", e)))?;
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return Err(ApiError::new(413, "Request body too large"));
+    }
+    Ok(body)
+}
+
+fn route(handler: &dyn ApiHandler, method: &Method, path: &str, query: &Query, body: &[u8]) -> Result<Value, ApiError> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (method, segments.as_slice()) {
+        (Method::Get, ["api", "status"]) => Ok(handler.status()),
+        (Method::Post, ["api", "searches"]) => {
+            let options: SearchOptions = serde_json::from_slice(body)
+                .map_err(|e| ApiError::new(400, format!("Invalid search options: {}", e)))?;
+            Ok(json!({ "search_id": handler.start_search(options)? }))
+        }
+        (Method::Get, ["api", "searches", id]) => Ok(handler.search_status(id)?),
+        (Method::Post, ["api", "searches", id, "cancel"]) => Ok(json!({ "cancelled": handler.cancel_search(id) })),
+        (Method::Get, ["api", "searches", id, "results"]) => {
+            let request = PageRequest {
+                offset: query.get("offset")?.unwrap_or(0),
+                limit: query.get("limit")?.unwrap_or(DEFAULT_PAGE_SIZE),
+                sort_by: query.get("sortBy")?.unwrap_or_default(),
+                descending: query.get("descending")?.unwrap_or(false),
+                group_by: query.get("groupBy")?.unwrap_or_default(),
+                directory: query.raw("directory").map(str::to_string),
+            };
+            to_json(&handler.results_page(id, &request)?)
+        }
+        (Method::Get, ["api", "searches", id, "matches"]) => {
+            let page = handler.file_matches(
+                id,
+                query.required("path")?,
+                query.get("offset")?.unwrap_or(0),
+                query.get("limit")?.unwrap_or(DEFAULT_PAGE_SIZE),
+            )?;
+            to_json(&page)
+        }
+        (Method::Get, ["api", "searches", id, "file"]) => {
+            Ok(handler.read_chunk(id, query.required("path")?, query.get("offset")?.unwrap_or(0), query.get("count")?)?)
+        }
+        (Method::Get, ["api", "searches", id, "hex"]) => {
+            to_json(&handler.read_hex_chunk(id, query.required("path")?, query.get("offset")?.unwrap_or(0), query.get("rows")?)?)
+        }
+        (Method::Get, ["api", "searches", id, "timeline"]) => {
+            to_json(&handler.timeline(id, query.get("offset")?.unwrap_or(0), query.get("limit")?.unwrap_or(DEFAULT_PAGE_SIZE))?)
+        }
+        (Method::Get, ["api", "searches", id, "context"]) => Ok(handler.read_context(
+            id,
+            query.required("path")?,
+            query.get("line")?.unwrap_or(1),
+            query.get("before")?.unwrap_or(DEFAULT_CONTEXT_LINES),
+            query.get("after")?.unwrap_or(DEFAULT_CONTEXT_LINES),
+        )?),
+        (_, ["api", "status" | "searches"])
+        | (_, ["api", "searches", _])
+        | (_, ["api", "searches", _, "cancel" | "results" | "matches" | "file" | "hex" | "timeline" | "context"]) => Err(ApiError::new(405, format!("{} not allowed here", method))),
+        _ => Err(ApiError::new(404, format!("No such route: {}", path))),
+    }
+}
+
+fn to_json(value: &impl Serialize) -> Result<Value, ApiError> {
+    serde_json::to_value(value).map_err(|e| ApiError::new(500, e.to_string()))
+}
+
+// -----------------------------------------------------------
+// Helpers
+// -----------------------------------------------------------
+
+/// Decoded query string parameters
+struct Query(HashMap<String, String>);
+
+impl Query {
+    fn parse(query: &str) -> Self {
+        Self(
+            query
+                .split('&')
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| {
+                    let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                    (percent_decode(key), percent_decode(value))
+                })
+                .collect(),
+        )
+    }
+
+    fn raw(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    fn required(&self, name: &str) -> Result<&str, ApiError> {
+        self.raw(name)
+            .ok_or_else(|| ApiError::new(400, format!("Missing query parameter '{}'", name)))
+    }
+
+    // Numbers and booleans as JSON literals, names of enum values as plain strings
+    fn get<T: DeserializeOwned>(&self, name: &str) -> Result<Option<T>, ApiError> {
+        let Some(value) = self.raw(name) else {
+            return Ok(None);
+        };
+        serde_json::from_str(value)
+            .or_else(|_| serde_json::from_value(Value::String(value.to_string())))
+            .map(Some)
+            .map_err(|_| ApiError::new(400, format!("Invalid value for '{}': {}", name, value)))
+    }
+}
+
+// "%2F" -> "/", "+" -> " "; invalid escapes are kept as they are
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                // from_str_radix alone would take "+1" as a number
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok().filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()));
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiny_http::TestRequest;
+
+    // Search "s1" exists and found "./a.log"; anything else is unknown
+    struct FakeHandler;
+
+    impl FakeHandler {
+        fn check(&self, search_id: &str, path: &str) -> Result<(), ApiError> {
+            if search_id != "s1" {
+                return Err(ApiError::not_found(format!("Unknown search ID: {}", search_id)));
+            }
+            if crate::session::result_relative(path).map_err(ApiError::not_found)? != "a.log" {
+                return Err(ApiError::not_found(format!("Not a result: {}", path)));
+            }
+            Ok(())
+        }
+    }
+
+    impl ApiHandler for FakeHandler {
+        fn status(&self) -> Value {
+            json!({ "running": [] })
+        }
+        fn start_search(&self, options: SearchOptions) -> Result<String, ApiError> {
+            if options.query.is_empty() {
+                return Err("Search query is empty".to_string().into());
+            }
+            Ok("s1".to_string())
+        }
+        fn search_status(&self, search_id: &str) -> Result<Value, ApiError> {
+            self.check(search_id, "a.log")?;
+            Ok(json!({ "search_id": search_id, "status": "completed" }))
+        }
+        fn cancel_search(&self, search_id: &str) -> bool {
+            search_id == "s1"
+        }
+        fn results_page(&self, search_id: &str, request: &PageRequest) -> Result<ResultsPage, ApiError> {
+            self.check(search_id, "a.log")?;
+            Ok(ResultsPage { total_groups: request.limit, offset: request.offset, groups: Vec::new() })
+        }
+        fn file_matches(&self, search_id: &str, path: &str, offset: usize, limit: usize) -> Result<MatchesPage, ApiError> {
+            self.check(search_id, path)?;
+            Ok(MatchesPage { path: path.to_string(), total: limit, offset, matches: Vec::new() })
+        }
+        fn read_chunk(&self, search_id: &str, path: &str, offset: usize, count: Option<usize>) -> Result<Value, ApiError> {
+            self.check(search_id, path)?;
+            Ok(json!({ "offset": offset, "count": count }))
+        }
+        fn read_hex_chunk(&self, search_id: &str, path: &str, offset: u64, rows: Option<usize>) -> Result<HexChunk, ApiError> {
+            self.check(search_id, path)?;
+            Ok(HexChunk { rows: Vec::new(), start: offset, offset, total_bytes: rows.unwrap_or(0) as u64, has_more: false })
+        }
+        fn timeline(&self, search_id: &str, offset: usize, limit: usize) -> Result<TimelinePage, ApiError> {
+            self.check(search_id, "a.log")?;
+            Ok(TimelinePage { total: limit, offset, sources: 0, matches: Vec::new() })
+        }
+        fn read_context(&self, search_id: &str, path: &str, line: usize, before: usize, after: usize) -> Result<Value, ApiError> {
+            self.check(search_id, path)?;
+            Ok(json!([line, before, after]))
+        }
+    }
+
+    fn call(method: Method, url: &str, body: &str) -> Result<Value, u16> {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        route(&FakeHandler, &method, path, &Query::parse(query), body.as_bytes()).map_err(|e| e.status)
+    }
+
+    fn request(remote: &str, authorization: Option<&str>) -> Request {
+        let mut test = TestRequest::new().with_remote_addr(remote.parse().unwrap());
+        if let Some(value) = authorization {
+            test = test.with_header(Header::from_bytes("Authorization", value).unwrap());
+        }
+        test.into()
+    }
+
+    #[test]
+    fn percent_decoding() {
+        assert_eq!(percent_decode("a%2Fb+c%20d"), "a/b c d");
+        assert_eq!(percent_decode("caf%C3%A9"), "café");
+        // Bad escapes stay as they are
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%4"), "%4");
+        assert_eq!(percent_decode("%zz%41"), "%zzA");
+        assert_eq!(percent_decode("%+1"), "% 1");
+        assert_eq!(percent_decode("%-1"), "%-1");
+        // Bytes that are not UTF-8 are replaced, not dropped
+        assert_eq!(percent_decode("%FFa"), "\u{FFFD}a");
+    }
+
+    #[test]
+    fn query_values_are_json_literals_or_strings() {
+        let query = Query::parse("offset=20&descending=true&sortBy=matchCount&path=.%2Fa+b.log&flag&limit=ten");
+        assert_eq!(query.get::<usize>("offset").ok().flatten(), Some(20));
+        assert_eq!(query.get::<bool>("descending").ok().flatten(), Some(true));
+        assert_eq!(query.raw("path"), Some("./a b.log"));
+        assert_eq!(query.raw("flag"), Some(""));
+        assert_eq!(query.get::<usize>("missing").ok().flatten(), None);
+        assert_eq!(query.get::<usize>("limit").unwrap_err().status, 400);
+        assert_eq!(query.required("missing").unwrap_err().status, 400);
+        assert_eq!(query.get("sortBy").ok().flatten(), Some(crate::result_store::SortKey::MatchCount));
+    }
+
+    #[test]
+    fn routes_dispatch_to_the_handler() {
+        assert_eq!(call(Method::Get, "/api/status", ""), Ok(json!({ "running": [] })));
+        assert_eq!(call(Method::Post, "/api/searches", r#"{"query":"error","path":"/srv"}"#), Ok(json!({ "search_id": "s1" })));
+        assert_eq!(call(Method::Get, "/api/searches/s1/", "").unwrap()["status"], "completed");
+        assert_eq!(call(Method::Post, "/api/searches/s1/cancel", ""), Ok(json!({ "cancelled": true })));
+        assert_eq!(call(Method::Post, "/api/searches/s9/cancel", ""), Ok(json!({ "cancelled": false })));
+
+        let page = call(Method::Get, "/api/searches/s1/results?offset=5", "").unwrap();
+        assert_eq!((page["offset"].as_u64(), page["total_groups"].as_u64()), (Some(5), Some(DEFAULT_PAGE_SIZE as u64)));
+        let chunk = call(Method::Get, "/api/searches/s1/file?path=.%2Fa.log&count=10", "").unwrap();
+        assert_eq!(chunk, json!({ "offset": 0, "count": 10 }));
+        let context = call(Method::Get, "/api/searches/s1/context?path=a.log&line=7", "").unwrap();
+        assert_eq!(context, json!([7, DEFAULT_CONTEXT_LINES, DEFAULT_CONTEXT_LINES]));
+        assert_eq!(call(Method::Get, "/api/searches/s1/hex?path=a.log&rows=2", "").unwrap()["total_bytes"], 2);
+        assert_eq!(call(Method::Get, "/api/searches/s1/timeline?limit=3", "").unwrap()["total"], 3);
+        assert_eq!(call(Method::Get, "/api/searches/s1/matches?path=a.log", "").unwrap()["path"], "a.log");
+    }
+
+    #[test]
+    fn bad_requests_and_unknown_things() {
+        // Bad input is 400
+        assert_eq!(call(Method::Post, "/api/searches", "{"), Err(400));
+        assert_eq!(call(Method::Post, "/api/searches", r#"{"query":"","path":"/srv"}"#), Err(400));
+        assert_eq!(call(Method::Get, "/api/searches/s1/file", ""), Err(400));
+        assert_eq!(call(Method::Get, "/api/searches/s1/file?path=a.log&offset=-1", ""), Err(400));
+        // Unknown searches and files outside the results are 404
+        assert_eq!(call(Method::Get, "/api/searches/s9", ""), Err(404));
+        assert_eq!(call(Method::Get, "/api/searches/s9/results", ""), Err(404));
+        assert_eq!(call(Method::Get, "/api/searches/s1/file?path=..%2Fetc%2Fpasswd", ""), Err(404));
+        assert_eq!(call(Method::Get, "/api/searches/s1/context?path=%2Fetc%2Fpasswd", ""), Err(404));
+        assert_eq!(call(Method::Get, "/api/searches/s1/hex?path=b.log", ""), Err(404));
+        // Unknown routes are 404, known ones with the wrong method 405
+        assert_eq!(call(Method::Get, "/api/nothing", ""), Err(404));
+        assert_eq!(call(Method::Get, "/", ""), Err(404));
+        assert_eq!(call(Method::Get, "/api/searches/s1/unknown", ""), Err(404));
+        assert_eq!(call(Method::Get, "/api/searches", ""), Err(405));
+        assert_eq!(call(Method::Delete, "/api/status", ""), Err(405));
+        assert_eq!(call(Method::Get, "/api/searches/s1/cancel", ""), Err(405));
+    }
+
+    #[test]
+    fn tokens_compare_in_full() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(!constant_time_eq(b"", b"secret"));
+        assert!(constant_time_eq(b"", b""));
+    }
+
+    #[test]
+    fn only_loopback_peers_with_the_token_get_in() {
+        let token = "0123abcd";
+        assert!(check_request(&request("127.0.0.1:5000", Some("Bearer 0123abcd")), token).is_ok());
+        assert!(check_request(&request("[::1]:5000", Some("Bearer  0123abcd ")), token).is_ok());
+        let status = |remote, auth| check_request(&request(remote, auth), token).unwrap_err().status;
+        assert_eq!(status("192.168.1.20:5000", Some("Bearer 0123abcd")), 403);
+        assert_eq!(status("127.0.0.1:5000", None), 401);
+        assert_eq!(status("127.0.0.1:5000", Some("Bearer wrong")), 401);
+        assert_eq!(status("127.0.0.1:5000", Some("0123abcd")), 401);
+        assert_eq!(status("127.0.0.1:5000", Some("Basic 0123abcd")), 401);
+    }
+
+    #[test]
+    fn large_bodies_are_refused() {
+        let mut small: Request = TestRequest::new().with_body(r#"{"query":"x"}"#).into();
+        assert_eq!(read_body(&mut small).unwrap(), br#"{"query":"x"}"#);
+
+        let big: &'static str = Box::leak("x".repeat(MAX_BODY_BYTES as usize + 1).into_boxed_str());
+        let mut request: Request = TestRequest::new().with_body(big).into();
+        assert_eq!(read_body(&mut request).unwrap_err().status, 413);
+    }
+}
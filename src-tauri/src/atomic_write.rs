@@ -0,0 +1,142 @@
+// atomic_write.rs
+// Replace a file's contents without ever leaving it half-written:
+// write a temp file in the same directory, flush it to disk, then rename it over the target.
+// Several files can be replaced together (write_all_atomic): every temp file is written
+// before any is renamed, so a failed write leaves all of them untouched.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Makes temp names unique within the process; the pid makes them unique across processes
+static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+
+/// Atomically replace `path` with `bytes`, keeping the original file's permissions
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    Staged::write(path, bytes)?.commit()
+}
+
+/// Where `write_all_atomic` stopped
+#[derive(Debug)]
+pub struct BatchError {
+    pub index: usize,     // the file that failed
+    pub committed: usize, // files already replaced; 0 when a temp file could not be written
+    pub error: io::Error,
+}
+
+/// Atomically replace several files. All temp files are written first; if one fails,
+/// none of the files change. Only a failed rename (rare: same directory) can leave the
+/// first `committed` files replaced and the rest untouched.
+pub fn write_all_atomic(files: &[(&Path, &[u8])]) -> Result<(), BatchError> {
+    let mut staged = Vec::with_capacity(files.len());
+    for (index, (path, bytes)) in files.iter().enumerate() {
+        match Staged::write(path, bytes) {
+            Ok(file) => staged.push(file),
+            Err(error) => return Err(BatchError { index, committed: 0, error }),
+        }
+    }
+    // On a failed rename the files not renamed yet drop their temp files
+    for (committed, file) in staged.into_iter().enumerate() {
+        if let Err(error) = file.commit() {
+            return Err(BatchError { index: committed, committed, error });
+        }
+    }
+    Ok(())
+}
+
+// A temp file written and synced next to its target, not yet renamed over it.
+// Dropping it without committing deletes the temp file.
+struct Staged {
+    tmp_path: PathBuf,
+    path: PathBuf,
+    committed: bool,
+}
+
+impl Staged {
+    fn write(path: &Path, bytes: &[u8]) -> io::Result<Self> {
+        let staged = Self { tmp_path: temp_path_for(path), path: path.to_path_buf(), committed: false };
+        let mut tmp = File::create(&staged.tmp_path)?;
+        tmp.write_all(bytes)?;
+        tmp.sync_all()?;
+        drop(tmp);
+
+        if let Ok(meta) = fs::metadata(path) {
+            fs::set_permissions(&staged.tmp_path, meta.permissions())?;
+        }
+        Ok(staged)
+    }
+
+    fn commit(mut self) -> io::Result<()> {
+        fs::rename(&self.tmp_path, &self.path)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for Staged {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.tmp_path);
+        }
+    }
+}
+
+// ".name.<pid>.<n>.search-tool.tmp" next to the target, so the rename never crosses file
+// systems and two writers of the same file never share a temp file
+fn temp_path_for(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let n = NEXT_TEMP.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{}.{}.{}.search-tool.tmp", name, std::process::id(), n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("search-tool-atomic-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn temp_names_are_unique() {
+        let path = Path::new("/srv/app.log");
+        assert_ne!(temp_path_for(path), temp_path_for(path));
+        assert_eq!(temp_path_for(path).parent(), path.parent());
+    }
+
+    #[test]
+    fn write_replaces_the_file_and_leaves_no_temp_file() {
+        let dir = temp_dir("single");
+        let path = dir.join("a.txt");
+        fs::write(&path, "old").unwrap();
+        write_atomic(&path, b"new").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_failed_write_changes_none_of_the_files() {
+        let dir = temp_dir("batch");
+        let a = dir.join("a.txt");
+        let b = dir.join("b.txt");
+        fs::write(&a, "a").unwrap();
+        fs::write(&b, "b").unwrap();
+        let missing = dir.join("no-such-dir").join("c.txt");
+
+        let err = write_all_atomic(&[(&a, b"A"), (&b, b"B"), (&missing, b"C")]).unwrap_err();
+        assert_eq!((err.index, err.committed), (2, 0));
+        assert_eq!(fs::read(&a).unwrap(), b"a");
+        assert_eq!(fs::read(&b).unwrap(), b"b");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2); // temp files removed
+
+        write_all_atomic(&[(&a, b"A"), (&b, b"B")]).unwrap();
+        assert_eq!(fs::read(&a).unwrap(), b"A");
+        assert_eq!(fs::read(&b).unwrap(), b"B");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,553 @@
+// search_cli.rs
+// Headless front end to the search backend in search_tool_lib. Runs the same content and
+// file-name searches as the app (same settings file, walker rules, content indexes and
+// limits) and prints the results for scripts and CI jobs:
+//
+//   search-cli search "TODO" ./src --glob "*.rs" --format json
+//   search-cli files "src/**/*Controller.cs" ./repo --mode glob
+//   search-cli search "order 1234" ./logs --from 2h --level warn --level error
+//   search-cli dev-port                (is the `tauri dev` port free? who holds it?)
+//
+// Exit status follows grep: 0 when something matched, 1 when nothing did, 2 on errors.
+// For dev-port: 0 when the port is free, 1 when it is taken.
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use search_tool_lib::dev_port::{self, DEV_SERVER_PORT};
+use search_tool_lib::export::{self, ExportFormat};
+use search_tool_lib::file_search::{self, FileHit, FileSearchOptions, FileSearchStats, NameMatchMode};
+use search_tool_lib::log_format::{LogFilter, Severity};
+use search_tool_lib::metadata_filter::MetadataFilter;
+use search_tool_lib::result_store::StoredResults;
+use search_tool_lib::search::{self, SearchControl, SearchOptions, SearchOutcome, SearchStats, SearchWarning, WarningKind};
+use search_tool_lib::session::absolute_path;
+use search_tool_lib::settings;
+
+#[derive(Parser)]
+#[command(name = "search-cli", version, about = "Search file contents and names from the command line")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Search file contents with ripgrep
+    Search(SearchArgs),
+    /// Find files by name or path
+    Files(FilesArgs),
+    /// Check that the dev server port is free, and report the process holding it if not
+    DevPort(DevPortArgs),
+}
+
+#[derive(Args)]
+struct SearchArgs {
+    /// Text to search for; a regular expression with --regex
+    query: String,
+    /// Directory or file to search
+    #[arg(default_value = ".")]
+    path: String,
+    /// Only search files matching this glob, e.g. "*.rs"
+    #[arg(short, long)]
+    glob: Option<String>,
+    /// Match case exactly (default from settings)
+    #[arg(short = 's', long, conflicts_with = "ignore_case")]
+    case_sensitive: bool,
+    /// Ignore case (default from settings)
+    #[arg(short, long)]
+    ignore_case: bool,
+    /// Treat the query as literal text (default from settings)
+    #[arg(short = 'F', long, conflicts_with = "regex")]
+    fixed_strings: bool,
+    /// Treat the query as a regular expression (default from settings)
+    #[arg(short = 'e', long)]
+    regex: bool,
+    /// Only match whole words
+    #[arg(short, long)]
+    word_regexp: bool,
+    #[command(flatten)]
+    metadata: MetadataArgs,
+    #[command(flatten)]
+    log: LogArgs,
+    #[arg(long, value_enum, default_value_t = SearchFormat::Text)]
+    format: SearchFormat,
+    /// Write the results here instead of standard output
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Print search statistics to standard error
+    #[arg(long)]
+    stats: bool,
+}
+
+#[derive(Args)]
+struct FilesArgs {
+    /// Fuzzy text, glob or regex; patterns with a '/' match the path below the root
+    pattern: String,
+    /// Directory to search
+    #[arg(default_value = ".")]
+    path: String,
+    #[arg(short, long, value_enum, default_value_t = NameMode::Fuzzy)]
+    mode: NameMode,
+    /// Only list files matching this glob, as for content searches
+    #[arg(short, long)]
+    glob: Option<String>,
+    /// Match case exactly
+    #[arg(short = 's', long)]
+    case_sensitive: bool,
+    #[command(flatten)]
+    metadata: MetadataArgs,
+    #[arg(long, value_enum, default_value_t = FilesFormat::Text)]
+    format: FilesFormat,
+    /// Write the results here instead of standard output
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Print search statistics to standard error
+    #[arg(long)]
+    stats: bool,
+}
+
+#[derive(Args)]
+struct DevPortArgs {
+    #[arg(default_value_t = DEV_SERVER_PORT)]
+    port: u16,
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+/// Same filters as the app's "File filters" panel
+#[derive(Args)]
+struct MetadataArgs {
+    /// Modified at or after this time: 2h, 3d, 2025-01-31 or 2025-01-31 14:00
+    #[arg(long)]
+    modified_after: Option<String>,
+    /// Modified at or before this time
+    #[arg(long)]
+    modified_before: Option<String>,
+    /// Minimum file size in bytes
+    #[arg(long)]
+    min_size: Option<u64>,
+    /// Maximum file size in bytes
+    #[arg(long)]
+    max_size: Option<u64>,
+    /// Owning user name or uid (not supported on Windows)
+    #[arg(long)]
+    owner: Option<String>,
+}
+
+/// Log mode: parse timestamps and levels, list matches chronologically across files
+#[derive(Args)]
+struct LogArgs {
+    /// Treat the files as logs even without --from, --to or --level
+    #[arg(long)]
+    log: bool,
+    /// Only lines logged at or after this time: 2h, 3d, 2025-01-31 or 2025-01-31 14:00
+    #[arg(long)]
+    from: Option<String>,
+    /// Only lines logged at or before this time
+    #[arg(long)]
+    to: Option<String>,
+    /// Only lines at this level (trace, debug, info, warn, error, fatal); repeatable
+    #[arg(long, value_parser = parse_level)]
+    level: Vec<Severity>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SearchFormat {
+    Text,     // file:line:text, like grep
+    Json,     // one document with stats, warnings and matches
+    Jsonl,    // one JSON object per match
+    Csv,
+    Markdown, // report with stats and a section per file
+}
+
+impl SearchFormat {
+    // The formats shared with the app's export; None for Json
+    fn export_format(self) -> Option<ExportFormat> {
+        match self {
+            SearchFormat::Text => Some(ExportFormat::Grep),
+            SearchFormat::Json => None,
+            SearchFormat::Jsonl => Some(ExportFormat::Jsonl),
+            SearchFormat::Csv => Some(ExportFormat::Csv),
+            SearchFormat::Markdown => Some(ExportFormat::Markdown),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FilesFormat {
+    Text, // one path per line
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum NameMode {
+    Fuzzy,
+    Glob,
+    Regex,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    // Searches read their defaults and limits from the app's settings file
+    let settings_report = settings::init();
+    if settings_report.invalid {
+        eprintln!("search-cli: {} ({})", settings_report.message, settings_report.path.display());
+    }
+
+    let result = match cli.command {
+        Command::Search(args) => run_search(args),
+        Command::Files(args) => run_files(args),
+        Command::DevPort(args) => run_dev_port(args),
+    };
+    if let Err(e) = &result {
+        eprintln!("search-cli: {}", e);
+    }
+    ExitCode::from(exit_status(&result))
+}
+
+// 0 when something matched (or the port is free), 1 when not, 2 on errors
+fn exit_status(result: &Result<bool, String>) -> u8 {
+    match result {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(_) => 2,
+    }
+}
+
+// -----------------------------------------------------------
+// Content search
+// -----------------------------------------------------------
+
+#[derive(Serialize)]
+struct SearchReport<'a> {
+    root: String,
+    #[serde(flatten)]
+    outcome: &'a SearchOutcome,
+}
+
+// Ok(true) if anything matched
+fn run_search(args: SearchArgs) -> Result<bool, String> {
+    let options = SearchOptions {
+        query: args.query,
+        path: args.path,
+        file_filter: args.glob,
+        case_sensitive: flag(args.case_sensitive, args.ignore_case),
+        whole_phrase: flag(args.fixed_strings, args.regex),
+        whole_words: args.word_regexp.then_some(true),
+        metadata_filter: args.metadata.into_filter(),
+        log_filter: args.log.into_filter(),
+    };
+    options.validate()?;
+
+    let settings = settings::current();
+    let outcome = search::run_search(&options, &settings, &SearchControl::default(), |_| {})?;
+    let root = absolute_path(Path::new(&options.path));
+    let stored = StoredResults::new(root, options, outcome);
+
+    write_output(args.output.as_deref(), |mut out| match args.format.export_format() {
+        Some(format) => export::write_export(&stored, format, &mut out).map(|_| ()),
+        None => {
+            let report = SearchReport {
+                root: stored.root.display().to_string(),
+                outcome: &stored.outcome,
+            };
+            serde_json::to_writer_pretty(&mut out, &report)?;
+            writeln!(out)
+        }
+    })?;
+
+    let outcome = &stored.outcome;
+    // ripgrep rejecting the search itself (bad regex, ...) is an error, not "no matches"
+    if outcome.matches.is_empty() {
+        if let Some(warning) = outcome.warnings.iter().find(|w| w.kind == WarningKind::Other) {
+            return Err(warning.message.clone());
+        }
+    }
+    print_warnings(&outcome.warnings);
+    if outcome.truncated {
+        eprintln!("search-cli: stopped at the result limit ({} matches)", settings.limits.max_results);
+    }
+    if args.stats {
+        print_search_stats(&outcome.stats);
+    }
+    Ok(!outcome.matches.is_empty())
+}
+
+fn print_search_stats(stats: &SearchStats) {
+    eprintln!(
+        "{} matches in {} lines, {} of {} files ({} bytes) in {:.0} ms",
+        stats.total_matches,
+        stats.matched_lines,
+        stats.files_with_matches,
+        stats.files_searched,
+        stats.bytes_searched,
+        stats.total_time_ms,
+    );
+    let skipped = &stats.skipped;
+    if skipped.total() > 0 {
+        eprintln!(
+            "skipped {} files: {} binary, {} symlinks or special files, {} too large, {} errors, {} by modified time, {} by size, {} by owner",
+            skipped.total(),
+            skipped.binary,
+            skipped.symlinks_or_special,
+            skipped.too_large,
+            skipped.error,
+            skipped.modified_range,
+            skipped.size_range,
+            skipped.owner,
+        );
+    }
+    if stats.files_excluded_by_index > 0 {
+        eprintln!("content index ruled out {} files", stats.files_excluded_by_index);
+    }
+    if stats.lines_excluded_by_log_filter > 0 {
+        eprintln!("log filter dropped {} matched lines", stats.lines_excluded_by_log_filter);
+    }
+}
+
+// -----------------------------------------------------------
+// File-name search
+// -----------------------------------------------------------
+
+#[derive(Serialize)]
+struct FilesReport<'a> {
+    root: String,
+    files: &'a [FileHit],
+    stats: &'a FileSearchStats,
+    warnings: &'a [SearchWarning],
+    truncated: bool,
+}
+
+fn run_files(args: FilesArgs) -> Result<bool, String> {
+    let options = FileSearchOptions {
+        pattern: args.pattern,
+        path: args.path,
+        mode: match args.mode {
+            NameMode::Fuzzy => NameMatchMode::Fuzzy,
+            NameMode::Glob => NameMatchMode::Glob,
+            NameMode::Regex => NameMatchMode::Regex,
+        },
+        file_filter: args.glob,
+        case_sensitive: Some(args.case_sensitive),
+        metadata_filter: args.metadata.into_filter(),
+    };
+    options.validate()?;
+
+    let settings = settings::current();
+    let mut hits = Vec::new();
+    let outcome = file_search::run_file_search(&options, &settings, &SearchControl::default(), |batch| {
+        hits.extend(batch)
+    })?;
+    // Best fuzzy matches first; stable, so equal scores keep the walk order
+    if options.mode == NameMatchMode::Fuzzy {
+        hits.sort_by_key(|hit| std::cmp::Reverse(hit.score));
+    }
+
+    write_output(args.output.as_deref(), |mut out| match args.format {
+        FilesFormat::Text => hits.iter().try_for_each(|hit| writeln!(out, "{}", hit.path)),
+        FilesFormat::Json => {
+            let report = FilesReport {
+                root: absolute_path(Path::new(&options.path)).display().to_string(),
+                files: &hits,
+                stats: &outcome.stats,
+                warnings: &outcome.warnings,
+                truncated: outcome.truncated,
+            };
+            serde_json::to_writer_pretty(&mut out, &report)?;
+            writeln!(out)
+        }
+    })?;
+
+    print_warnings(&outcome.warnings);
+    if outcome.truncated {
+        eprintln!("search-cli: stopped at the result limit ({} files)", settings.limits.max_results);
+    }
+    if args.stats {
+        let stats = &outcome.stats;
+        eprintln!(
+            "{} of {} files matched in {:.0} ms, {} skipped",
+            stats.files_matched,
+            stats.files_visited,
+            stats.total_time_ms,
+            stats.skipped.total(),
+        );
+    }
+    Ok(!hits.is_empty())
+}
+
+// -----------------------------------------------------------
+// Dev-server port
+// -----------------------------------------------------------
+
+// Ok(true) if the port is free. Only reports; never stops the process holding it.
+fn run_dev_port(args: DevPortArgs) -> Result<bool, String> {
+    let report = dev_port::check(args.port);
+    if args.json {
+        let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+        println!("{}", json);
+    } else if report.in_use {
+        eprintln!("search-cli: {}", report.message());
+    } else {
+        println!("{}", report.message());
+    }
+    Ok(!report.in_use)
+}
+
+// -----------------------------------------------------------
+// Helpers
+// -----------------------------------------------------------
+
+impl MetadataArgs {
+    fn into_filter(self) -> Option<MetadataFilter> {
+        let filter = MetadataFilter {
+            modified_after: self.modified_after,
+            modified_before: self.modified_before,
+            min_size: self.min_size,
+            max_size: self.max_size,
+            owner: self.owner,
+        };
+        let empty = filter.modified_after.is_none()
+            && filter.modified_before.is_none()
+            && filter.min_size.is_none()
+            && filter.max_size.is_none()
+            && filter.owner.is_none();
+        (!empty).then_some(filter)
+    }
+}
+
+impl LogArgs {
+    fn into_filter(self) -> Option<LogFilter> {
+        let on = self.log || self.from.is_some() || self.to.is_some() || !self.level.is_empty();
+        on.then_some(LogFilter {
+            from: self.from,
+            to: self.to,
+            levels: self.level,
+        })
+    }
+}
+
+fn parse_level(value: &str) -> Result<Severity, String> {
+    Severity::from_word(value).ok_or_else(|| format!("unknown level '{}'", value))
+}
+
+// Some(true) / Some(false) for an explicit flag, None to use the settings default
+fn flag(on: bool, off: bool) -> Option<bool> {
+    match (on, off) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+// Run `write` against the output file or a locked, buffered stdout
+fn write_output(path: Option<&Path>, write: impl FnOnce(&mut dyn Write) -> io::Result<()>) -> Result<(), String> {
+    let result = match path {
+        Some(path) => {
+            let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+            let mut out = BufWriter::new(file);
+            write(&mut out).and_then(|_| out.flush())
+        }
+        None => {
+            let mut out = BufWriter::new(io::stdout().lock());
+            write(&mut out).and_then(|_| out.flush())
+        }
+    };
+    match result {
+        // `search-cli ... | head` closes the pipe early; that is not an error
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        other => other.map_err(|e| format!("Failed to write results: {}", e)),
+    }
+}
+
+fn print_warnings(warnings: &[SearchWarning]) {
+    for warning in warnings {
+        match &warning.path {
+            Some(path) => eprintln!("search-cli: {}: {}", path, warning.message),
+            None => eprintln!("search-cli: {}", warning.message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, clap::Error> {
+        Cli::try_parse_from(["search-cli"].iter().chain(args)).map(|cli| cli.command)
+    }
+
+    fn search_args(args: &[&str]) -> SearchArgs {
+        match parse(&[&["search"], args].concat()) {
+            Ok(Command::Search(args)) => args,
+            _ => panic!("not a search: {:?}", args),
+        }
+    }
+
+    #[test]
+    fn explicit_flags_override_the_settings() {
+        assert_eq!(flag(true, false), Some(true));
+        assert_eq!(flag(false, true), Some(false));
+        assert_eq!(flag(false, false), None);
+
+        let args = search_args(&["x", "-s", "-e"]);
+        assert_eq!(flag(args.case_sensitive, args.ignore_case), Some(true));
+        assert_eq!(flag(args.fixed_strings, args.regex), Some(false));
+        let args = search_args(&["x", "--ignore-case", "--fixed-strings"]);
+        assert_eq!(flag(args.case_sensitive, args.ignore_case), Some(false));
+        assert_eq!(flag(args.fixed_strings, args.regex), Some(true));
+    }
+
+    #[test]
+    fn contradicting_flags_are_refused() {
+        for args in [&["search", "x", "-s", "-i"], &["search", "x", "-F", "-e"]] {
+            let err = parse(args).err().unwrap_or_else(|| panic!("{:?} parsed", args));
+            assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+        }
+        assert!(parse(&["search", "x", "--level", "loud"]).is_err());
+    }
+
+    #[test]
+    fn filters_are_only_sent_when_set() {
+        let args = search_args(&["x"]);
+        assert!(args.metadata.into_filter().is_none());
+        assert!(args.log.into_filter().is_none());
+
+        let args = search_args(&["x", "--min-size", "10", "--log"]);
+        assert_eq!(args.metadata.into_filter().unwrap().min_size, Some(10));
+        let log = args.log.into_filter().unwrap();
+        assert!(log.from.is_none() && log.levels.is_empty());
+
+        let args = search_args(&["x", "--owner", "jo", "--level", "warn", "--level", "error"]);
+        assert_eq!(args.metadata.into_filter().unwrap().owner.as_deref(), Some("jo"));
+        assert_eq!(args.log.into_filter().unwrap().levels.len(), 2);
+    }
+
+    #[test]
+    fn exit_status_follows_grep() {
+        assert_eq!(exit_status(&Ok(true)), 0);
+        assert_eq!(exit_status(&Ok(false)), 1);
+        assert_eq!(exit_status(&Err("bad regex".to_string())), 2);
+
+        // A file-name search needs no ripgrep, so it can run here
+        let dir = std::env::temp_dir().join(format!("search-tool-cli-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+        let output = dir.join("out.txt");
+        let files = |pattern: &str, path: &Path| match parse(&["files", pattern, path.to_str().unwrap(), "-o", output.to_str().unwrap()]) {
+            Ok(Command::Files(args)) => exit_status(&run_files(args)),
+            _ => panic!("not a file search"),
+        };
+        assert_eq!(files("main", &dir), 0);
+        assert_eq!(std::fs::read_to_string(&output).unwrap().trim(), "./main.rs");
+        assert_eq!(files("nothing-like-it", &dir), 1);
+        assert_eq!(files("main", &dir.join("missing")), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,159 @@
+// binary_view.rs
+// Binary files in the viewer: detection, and a hex + ASCII dump that pages by byte
+// offset instead of by line (a binary file has no useful lines, only megabyte-long runs
+// between stray '\n' bytes).
+
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Bytes looked at to decide whether a file is binary
+pub const SNIFF_LEN: usize = 8 * 1024;
+
+/// Bytes shown per row of the dump
+pub const BYTES_PER_ROW: usize = 16;
+
+// Rows one request may ask for; keeps a bad `rows` argument from reading a whole file
+const MAX_ROWS: usize = 16 * 1024;
+
+/// True for bytes that look like binary data: any NUL, or more than a third of the
+/// bytes being control characters. Bytes >= 0x80 count as text, since Windows-1252 and
+/// UTF-8 files are full of them. Files with a UTF-16 BOM are text (see text_codec.rs).
+pub fn looks_binary(bytes: &[u8]) -> bool {
+    if bytes.starts_with(b"\xFF\xFE") || bytes.starts_with(b"\xFE\xFF") {
+        return false;
+    }
+    let sample = &bytes[..bytes.len().min(SNIFF_LEN)];
+    if sample.contains(&0) {
+        return true;
+    }
+    let control = sample.iter().filter(|&&b| is_control(b)).count();
+    control * 3 > sample.len()
+}
+
+// Tab, newlines, form feed and escape (ANSI colours in logs) are normal in text
+fn is_control(byte: u8) -> bool {
+    (byte < 0x20 && !matches!(byte, b'\t' | b'\n' | b'\r' | 0x0C | 0x1B)) || byte == 0x7F
+}
+
+/// One row of the dump: "00000010  48 65 6c 6c 6f ...  Hello..."
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct HexRow {
+    pub offset: u64,
+    pub hex: String,   // "48 65 6c 6c 6f", space separated
+    pub ascii: String, // printable ASCII as is, anything else as '.'
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct HexChunk {
+    pub rows: Vec<HexRow>,
+    pub start: u64,  // offset of the first row, `offset` rounded down to a row
+    pub offset: u64, // where the next chunk starts
+    pub total_bytes: u64,
+    pub has_more: bool,
+}
+
+/// `rows` rows of the dump of `path`, starting at the row that contains byte `offset`
+pub fn read_hex_chunk(path: &Path, offset: u64, rows: usize) -> Result<HexChunk, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let total_bytes = file
+        .metadata()
+        .map_err(|e| format!("Failed to read metadata of {}: {}", path.display(), e))?
+        .len();
+
+    let start = align_to_row(offset.min(total_bytes));
+    let wanted = rows.clamp(1, MAX_ROWS) * BYTES_PER_ROW;
+    let mut bytes = Vec::with_capacity(wanted);
+    file.seek(SeekFrom::Start(start))
+        .and_then(|_| file.by_ref().take(wanted as u64).read_to_end(&mut bytes))
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let next = start + bytes.len() as u64;
+    Ok(HexChunk {
+        rows: hex_rows(&bytes, start),
+        start,
+        offset: next,
+        total_bytes,
+        has_more: next < total_bytes,
+    })
+}
+
+/// Format `bytes` as dump rows; `start` is the file offset of the first byte
+pub fn hex_rows(bytes: &[u8], start: u64) -> Vec<HexRow> {
+    bytes
+        .chunks(BYTES_PER_ROW)
+        .enumerate()
+        .map(|(i, row)| HexRow {
+            offset: start + (i * BYTES_PER_ROW) as u64,
+            hex: row.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" "),
+            ascii: row
+                .iter()
+                .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Offset of the row containing byte `offset`
+pub fn align_to_row(offset: u64) -> u64 {
+    offset - offset % BYTES_PER_ROW as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nul_bytes_and_control_characters_mean_binary() {
+        assert!(!looks_binary(b"plain text\twith tabs\r\n"));
+        assert!(!looks_binary(b"\x1b[31mred\x1b[0m caf\xe9 \xe2\x82\xac"));
+        assert!(looks_binary(b"text with one\0nul"));
+        // A third of the bytes being control characters is still text; more is not
+        assert!(!looks_binary(b"ab\x01"));
+        assert!(looks_binary(b"a\x01\x02"));
+        assert!(looks_binary(b"abc\x7f\x7f"));
+        // Only the sniffed start counts
+        let mut late = vec![b'a'; SNIFF_LEN];
+        late.push(0);
+        assert!(!looks_binary(&late));
+    }
+
+    #[test]
+    fn utf16_with_a_bom_is_text() {
+        assert!(!looks_binary(b"\xFF\xFEh\0i\0"));
+        assert!(!looks_binary(b"\xFE\xFF\0h\0i"));
+        assert!(looks_binary(b"h\0i\0")); // no BOM, no way to tell
+    }
+
+    #[test]
+    fn rows_are_aligned_and_clamped() {
+        let path = std::env::temp_dir().join(format!("search-tool-hex-{}.bin", std::process::id()));
+        let bytes: Vec<u8> = (0..=255u8).cycle().take(MAX_ROWS * BYTES_PER_ROW + 40).collect();
+        std::fs::write(&path, &bytes).unwrap();
+        let total = bytes.len() as u64;
+
+        let chunk = read_hex_chunk(&path, 20, 2).unwrap();
+        assert_eq!((chunk.start, chunk.offset, chunk.total_bytes, chunk.has_more), (16, 48, total, true));
+        assert_eq!(chunk.rows[0].offset, 16);
+        assert!(chunk.rows[0].hex.starts_with("10 11 12"));
+
+        // At most MAX_ROWS rows, and at least one
+        let chunk = read_hex_chunk(&path, 0, usize::MAX).unwrap();
+        assert_eq!(chunk.rows.len(), MAX_ROWS);
+        assert_eq!(read_hex_chunk(&path, 0, 0).unwrap().rows.len(), 1);
+
+        // Offsets past the end start at the last (partial) row and read nothing more
+        let chunk = read_hex_chunk(&path, u64::MAX, 4).unwrap();
+        assert_eq!((chunk.start, chunk.offset, chunk.has_more), (align_to_row(total), total, false));
+        assert_eq!(chunk.rows.len(), 1);
+        assert_eq!(chunk.rows[0].hex.split(' ').count(), (total % BYTES_PER_ROW as u64) as usize);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn ascii_column_shows_printable_bytes_only() {
+        let rows = hex_rows(b"Hi \x00\x7f\xe9~", 32);
+        assert_eq!(rows, [HexRow { offset: 32, hex: "48 69 20 00 7f e9 7e".to_string(), ascii: "Hi ...~".to_string() }]);
+    }
+}
@@ -0,0 +1,552 @@
+// content_index.rs
+// Optional on-disk index per search root, used to skip files that cannot match a query.
+// Every indexed file gets a trigram filter: a bitset with one bit set per (hashed) trigram
+// of its content, ASCII-lowercased. The literal parts of a query give trigrams every
+// match must contain; files whose filter lacks one of them are not handed to ripgrep.
+// Filters can give false positives (ripgrep still checks), never false negatives.
+//
+// Entries are trusted only while a file's size and mtime are what they were when it was
+// indexed, so a stale index makes searches slower, never wrong. Indexes are built on a
+// background thread and refreshed incrementally: unchanged files are not read again.
+// They live in <data dir>/indexes/<hash of root>/ (index.bin plus a small meta.json).
+
+use once_cell::sync::Lazy;
+use regex_syntax::hir::{Hir, HirKind};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, UNIX_EPOCH};
+
+use crate::atomic_write::write_atomic;
+use crate::logging;
+use crate::search::SearchOptions;
+use crate::settings::{self, Settings};
+use crate::walker::{self, WalkOptions, WalkProgress};
+
+// Bumped when the on-disk layout changes; older indexes are rebuilt
+const INDEX_FORMAT: u32 = 1;
+const INDEX_FILE: &str = "index.bin";
+const META_FILE: &str = "meta.json";
+
+// Larger files are not read; searches always hand them to ripgrep
+const MAX_INDEXED_FILE_SIZE: u64 = 16 * 1024 * 1024;
+
+// Filter size: at least this many bits per distinct trigram (rounded up to a power of
+// two), within these bounds. Small files get no narrower than MIN, and files with more
+// trigrams than MAX can hold end up with filters that match almost anything.
+const BITS_PER_TRIGRAM: usize = 4;
+const MIN_FILTER_BITS: usize = 512;
+const MAX_FILTER_BITS: usize = 1 << 16;
+
+// -----------------------------------------------------------
+// Index data
+// -----------------------------------------------------------
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    size: u64,
+    mtime_secs: u64,
+    mtime_nanos: u32,
+}
+
+impl FileStamp {
+    fn of(meta: &Metadata) -> Self {
+        let mtime = meta.modified().ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()).unwrap_or_default();
+        Self {
+            size: meta.len(),
+            mtime_secs: mtime.as_secs(),
+            mtime_nanos: mtime.subsec_nanos(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct IndexedFile {
+    stamp: FileStamp,
+    filter: Vec<u64>, // empty when the file was not read (too large, UTF-16, unreadable)
+}
+
+/// The index of one root, keyed by "./relative/path" as the walker yields it
+#[derive(Serialize, Deserialize)]
+pub struct ContentIndex {
+    format: u32,
+    root: PathBuf,
+    built: String,
+    files: HashMap<String, IndexedFile>,
+}
+
+// Written next to index.bin so status and listing do not have to load the index
+#[derive(Serialize, Deserialize)]
+struct IndexMeta {
+    format: u32,
+    root: PathBuf,
+    built: String,
+    files: usize,
+}
+
+/// What the index says about one file for one query
+pub enum IndexCheck {
+    Excluded,  // cannot match
+    Candidate, // may match
+    Stale,     // new or changed since the index was built; search it
+}
+
+impl ContentIndex {
+    pub fn check(&self, path: &Path, meta: &Metadata, query: &QueryTrigrams) -> IndexCheck {
+        let Some(entry) = self.files.get(path.to_string_lossy().as_ref()) else {
+            return IndexCheck::Stale;
+        };
+        if entry.stamp != FileStamp::of(meta) {
+            return IndexCheck::Stale;
+        }
+        if entry.filter.is_empty() || query.0.iter().all(|&t| filter_contains(&entry.filter, t)) {
+            IndexCheck::Candidate
+        } else {
+            IndexCheck::Excluded
+        }
+    }
+}
+
+// -----------------------------------------------------------
+// Trigrams
+// -----------------------------------------------------------
+
+fn trigram(bytes: &[u8]) -> u32 {
+    (bytes[0].to_ascii_lowercase() as u32) << 16 | (bytes[1].to_ascii_lowercase() as u32) << 8 | bytes[2].to_ascii_lowercase() as u32
+}
+
+fn filter_bit(trigram: u32, bits: usize) -> usize {
+    ((trigram as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32) as usize & (bits - 1)
+}
+
+fn filter_contains(filter: &[u64], trigram: u32) -> bool {
+    let bit = filter_bit(trigram, filter.len() * 64);
+    filter[bit / 64] & (1 << (bit % 64)) != 0
+}
+
+fn build_filter(bytes: &[u8]) -> Vec<u64> {
+    let mut trigrams: Vec<u32> = bytes.windows(3).map(trigram).collect();
+    trigrams.sort_unstable();
+    trigrams.dedup();
+    let bits = (trigrams.len() * BITS_PER_TRIGRAM).next_power_of_two().clamp(MIN_FILTER_BITS, MAX_FILTER_BITS);
+    let mut filter = vec![0u64; bits / 64];
+    for t in trigrams {
+        let bit = filter_bit(t, bits);
+        filter[bit / 64] |= 1 << (bit % 64);
+    }
+    filter
+}
+
+/// Trigrams every match of a query must contain
+pub struct QueryTrigrams(Vec<u32>);
+
+impl QueryTrigrams {
+    /// None when the query has no literal run of 3+ bytes to narrow the search with
+    pub fn new(options: &SearchOptions, settings: &Settings) -> Option<Self> {
+        let defaults = &settings.search;
+        let ignore_case = !options.case_sensitive.unwrap_or(defaults.case_sensitive);
+        let runs = if options.whole_phrase.unwrap_or(defaults.whole_phrase) {
+            vec![options.query.as_bytes().to_vec()]
+        } else {
+            let hir = regex_syntax::Parser::new().parse(&options.query).ok()?;
+            let mut runs = Vec::new();
+            let mut current = Vec::new();
+            required_literals(&hir, &mut current, &mut runs);
+            flush_run(&mut current, &mut runs);
+            runs
+        };
+
+        let mut trigrams: Vec<u32> = runs
+            .iter()
+            .flat_map(|run| run.windows(3))
+            // ripgrep's --ignore-case folds Unicode too ('k' also matches the Kelvin sign,
+            // 's' the long s), which an ASCII-lowercased index cannot see
+            .filter(|w| !ignore_case || w.iter().all(|b| b.is_ascii() && !matches!(b.to_ascii_lowercase(), b'k' | b's')))
+            .map(trigram)
+            .collect();
+        trigrams.sort_unstable();
+        trigrams.dedup();
+        (!trigrams.is_empty()).then_some(Self(trigrams))
+    }
+}
+
+// Collect runs of literal bytes every match contains. Anything that is not a plain
+// literal (classes, alternations, optional parts) ends the current run.
+fn required_literals(hir: &Hir, current: &mut Vec<u8>, runs: &mut Vec<Vec<u8>>) {
+    match hir.kind() {
+        HirKind::Literal(lit) => current.extend_from_slice(&lit.0),
+        HirKind::Empty | HirKind::Look(_) => {}
+        HirKind::Capture(cap) => required_literals(&cap.sub, current, runs),
+        HirKind::Concat(parts) => {
+            for part in parts {
+                required_literals(part, current, runs);
+            }
+        }
+        HirKind::Repetition(rep) if rep.min >= 1 => {
+            flush_run(current, runs);
+            let mut inner = Vec::new();
+            required_literals(&rep.sub, &mut inner, runs);
+            flush_run(&mut inner, runs);
+        }
+        _ => flush_run(current, runs),
+    }
+}
+
+fn flush_run(current: &mut Vec<u8>, runs: &mut Vec<Vec<u8>>) {
+    if current.len() >= 3 {
+        runs.push(std::mem::take(current));
+    }
+    current.clear();
+}
+
+// -----------------------------------------------------------
+// Registry of loaded indexes and running builds
+// -----------------------------------------------------------
+
+#[derive(Default)]
+struct BuildProgress {
+    files: AtomicUsize,
+    cancel: AtomicBool,
+}
+
+#[derive(Default)]
+struct RootIndex {
+    loaded: Option<Arc<ContentIndex>>,
+    building: Option<Arc<BuildProgress>>,
+    error: Option<String>, // last build or load failure
+}
+
+static REGISTRY: Lazy<Mutex<HashMap<PathBuf, RootIndex>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexState {
+    Missing,
+    Building,
+    Ready,
+    Failed,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct IndexStatus {
+    pub root: String,
+    pub state: IndexState,
+    pub files: usize,           // files in the last completed index
+    pub size_bytes: u64,        // of index.bin
+    pub built: Option<String>,  // when the last completed index was written
+    pub files_processed: usize, // so far, while a build runs
+    pub error: Option<String>,
+}
+
+// Indexes are keyed by the canonical root so "C:\src" and "c:/src/" share one
+fn root_key(root: &Path) -> Result<PathBuf, String> {
+    std::fs::canonicalize(root).map_err(|e| format!("Cannot index {}: {}", root.display(), e))
+}
+
+fn index_dir(key: &Path) -> PathBuf {
+    let hash = format!("{:x}", Sha256::digest(key.to_string_lossy().as_bytes()));
+    indexes_dir().join(&hash[..16])
+}
+
+fn indexes_dir() -> PathBuf {
+    settings::data_dir().join("indexes")
+}
+
+/// The index of `root` if one has been built, loading it from disk on first use
+pub fn for_root(root: &Path) -> Option<Arc<ContentIndex>> {
+    let key = root_key(root).ok()?;
+    let mut registry = REGISTRY.lock().unwrap();
+    let entry = registry.entry(key.clone()).or_default();
+    if entry.loaded.is_none() && entry.error.is_none() && index_dir(&key).join(INDEX_FILE).exists() {
+        match load(&key) {
+            Ok(index) => entry.loaded = Some(Arc::new(index)),
+            Err(e) => {
+                logging::warn("Content index could not be loaded", &[("root", &key.display()), ("error", &e)]);
+                entry.error = Some(e);
+            }
+        }
+    }
+    entry.loaded.clone()
+}
+
+pub fn status(root: &Path) -> Result<IndexStatus, String> {
+    let key = root_key(root)?;
+    Ok(status_of(&key))
+}
+
+fn status_of(key: &Path) -> IndexStatus {
+    let dir = index_dir(key);
+    let meta: Option<IndexMeta> = std::fs::read(dir.join(META_FILE))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .filter(|m: &IndexMeta| m.format == INDEX_FORMAT);
+    let size_bytes = std::fs::metadata(dir.join(INDEX_FILE)).map(|m| m.len()).unwrap_or(0);
+
+    let registry = REGISTRY.lock().unwrap();
+    let entry = registry.get(key);
+    let building = entry.and_then(|e| e.building.as_ref());
+    let error = entry.and_then(|e| e.error.clone());
+    let state = match (building, &meta, &error) {
+        (Some(_), _, _) => IndexState::Building,
+        (None, _, Some(_)) => IndexState::Failed,
+        (None, Some(_), None) => IndexState::Ready,
+        (None, None, None) => IndexState::Missing,
+    };
+    IndexStatus {
+        root: key.to_string_lossy().to_string(),
+        state,
+        files: meta.as_ref().map(|m| m.files).unwrap_or(0),
+        size_bytes,
+        built: meta.map(|m| m.built),
+        files_processed: building.map(|b| b.files.load(Ordering::Relaxed)).unwrap_or(0),
+        error,
+    }
+}
+
+/// Status of every index on disk
+pub fn list() -> Vec<IndexStatus> {
+    let Ok(entries) = std::fs::read_dir(indexes_dir()) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| std::fs::read(entry.path().join(META_FILE)).ok())
+        .filter_map(|bytes| serde_json::from_slice::<IndexMeta>(&bytes).ok())
+        .map(|meta| status_of(&meta.root))
+        .collect()
+}
+
+/// Build or refresh the index of `root` in the background. With `full`, every file is read
+/// again; otherwise files whose size and mtime did not change keep their entries.
+/// Returns right away (with the current status if a build is already running) and calls
+/// `on_finished` when the build is done.
+pub fn start_build(
+    root: &Path,
+    full: bool,
+    on_finished: impl FnOnce(IndexStatus) + Send + 'static,
+) -> Result<IndexStatus, String> {
+    let key = root_key(root)?;
+    if !key.is_dir() {
+        return Err(format!("Not a directory: {}", key.display()));
+    }
+    let progress = Arc::new(BuildProgress::default());
+    let previous = {
+        let mut registry = REGISTRY.lock().unwrap();
+        let entry = registry.entry(key.clone()).or_default();
+        if entry.building.is_some() {
+            drop(registry);
+            return Ok(status_of(&key));
+        }
+        entry.building = Some(progress.clone());
+        entry.error = None;
+        if full { None } else { entry.loaded.clone() }
+    };
+
+    let thread_key = key.clone();
+    std::thread::spawn(move || {
+        let key = thread_key;
+        let started = Instant::now();
+        let previous = previous.or_else(|| if full { None } else { load(&key).ok().map(Arc::new) });
+        let result = build(&key, previous.as_deref(), &progress).and_then(|index| save(&index).map(|_| index));
+
+        {
+            let mut registry = REGISTRY.lock().unwrap();
+            let entry = registry.entry(key.clone()).or_default();
+            entry.building = None;
+            match result {
+                Ok(index) => {
+                    logging::info("Content index built", &[
+                        ("root", &key.display()),
+                        ("files", &index.files.len()),
+                        ("full", &full),
+                        ("duration_ms", &started.elapsed().as_millis()),
+                    ]);
+                    entry.loaded = Some(Arc::new(index));
+                }
+                Err(e) => {
+                    logging::error("Content index build failed", &[("root", &key.display()), ("error", &e)]);
+                    entry.error = Some(e);
+                }
+            }
+        }
+        on_finished(status_of(&key));
+    });
+    Ok(status_of(&key))
+}
+
+/// Remove the index of `root` from disk and memory, cancelling a running build
+pub fn delete(root: &Path) -> Result<(), String> {
+    let key = root_key(root)?;
+    if let Some(entry) = REGISTRY.lock().unwrap().remove(&key) {
+        if let Some(building) = entry.building {
+            building.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+    let dir = index_dir(&key);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).map_err(|e| format!("Failed to delete index {}: {}", dir.display(), e))?;
+    }
+    logging::info("Content index deleted", &[("root", &key.display())]);
+    Ok(())
+}
+
+// -----------------------------------------------------------
+// Building, loading and saving
+// -----------------------------------------------------------
+
+fn build(root: &Path, previous: Option<&ContentIndex>, progress: &BuildProgress) -> Result<ContentIndex, String> {
+    // Everything a search might look at, whatever its hidden/ignore flags
+    let options = WalkOptions {
+        root: root.to_path_buf(),
+        include_hidden: true,
+        respect_ignore: false,
+        file_filter: None,
+        excluded_dirs: settings::current().excluded_dirs,
+        max_file_size: 0,
+        skip_binary: false,
+        metadata: None,
+    };
+    let mut files = HashMap::new();
+    walker::walk(&options, &WalkProgress::default(), |path, meta| {
+        let key = path.to_string_lossy().to_string();
+        let stamp = FileStamp::of(meta);
+        let entry = match previous.and_then(|p| p.files.get(&key)).filter(|e| e.stamp == stamp) {
+            Some(entry) => entry.clone(),
+            None => IndexedFile { stamp, filter: read_filter(&root.join(path), stamp.size) },
+        };
+        files.insert(key, entry);
+        progress.files.fetch_add(1, Ordering::Relaxed);
+        !progress.cancel.load(Ordering::Relaxed)
+    })?;
+    if progress.cancel.load(Ordering::Relaxed) {
+        return Err("Index build cancelled".to_string());
+    }
+
+    Ok(ContentIndex {
+        format: INDEX_FORMAT,
+        root: root.to_path_buf(),
+        built: chrono::Local::now().to_rfc3339(),
+        files,
+    })
+}
+
+// Files ripgrep would transcode (UTF-16 with a BOM) or that are too large to read get an
+// empty filter, which matches every query
+fn read_filter(path: &Path, size: u64) -> Vec<u64> {
+    if size > MAX_INDEXED_FILE_SIZE {
+        return Vec::new();
+    }
+    match std::fs::read(path) {
+        Ok(bytes) if bytes.starts_with(&[0xFF, 0xFE]) || bytes.starts_with(&[0xFE, 0xFF]) => Vec::new(),
+        Ok(bytes) => build_filter(&bytes),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn load(key: &Path) -> Result<ContentIndex, String> {
+    let path = index_dir(key).join(INDEX_FILE);
+    let bytes = std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let index: ContentIndex = bincode::deserialize(&bytes).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    if index.format != INDEX_FORMAT || index.root != key {
+        return Err(format!("{} is from another version or root; rebuild it", path.display()));
+    }
+    Ok(index)
+}
+
+fn save(index: &ContentIndex) -> Result<(), String> {
+    let dir = index_dir(&index.root);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let bytes = bincode::serialize(index).map_err(|e| format!("Failed to serialize index: {}", e))?;
+    write_atomic(&dir.join(INDEX_FILE), &bytes).map_err(|e| format!("Failed to write index: {}", e))?;
+
+    let meta = IndexMeta {
+        format: index.format,
+        root: index.root.clone(),
+        built: index.built.clone(),
+        files: index.files.len(),
+    };
+    let json = serde_json::to_vec_pretty(&meta).map_err(|e| e.to_string())?;
+    write_atomic(&dir.join(META_FILE), &json).map_err(|e| format!("Failed to write index metadata: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(query: &str, case_sensitive: bool, whole_phrase: bool) -> Option<QueryTrigrams> {
+        let options: SearchOptions = serde_json::from_value(serde_json::json!({
+            "query": query,
+            "path": ".",
+            "caseSensitive": case_sensitive,
+            "wholePhrase": whole_phrase,
+        }))
+        .unwrap();
+        QueryTrigrams::new(&options, &Settings::default())
+    }
+
+    fn trigrams(words: &[&str]) -> Vec<u32> {
+        let mut trigrams: Vec<u32> = words.iter().map(|w| trigram(w.as_bytes())).collect();
+        trigrams.sort_unstable();
+        trigrams
+    }
+
+    #[test]
+    fn filters_hold_every_trigram_in_any_case() {
+        let filter = build_filter(b"Connection Timeout");
+        for t in ["con", "CON", "ion", "n t", "out", "TiM"] {
+            assert!(filter_contains(&filter, trigram(t.as_bytes())), "{}", t);
+        }
+        assert!(!filter_contains(&filter, trigram(b"xyz")));
+        assert_eq!(filter.len() * 64, MIN_FILTER_BITS);
+    }
+
+    #[test]
+    fn literal_queries_give_their_trigrams() {
+        assert_eq!(query("Error", true, true).unwrap().0, trigrams(&["err", "rro", "ror"]));
+        // Case-insensitive queries look the same, except around k and s (Unicode folding)
+        assert_eq!(query("ERROR", false, true).unwrap().0, trigrams(&["err", "rro", "ror"]));
+        assert_eq!(query("kelvin", false, true).unwrap().0, trigrams(&["elv", "lvi", "vin"]));
+        assert_eq!(query("kelvin", true, true).unwrap().0, trigrams(&["kel", "elv", "lvi", "vin"]));
+    }
+
+    #[test]
+    fn regex_queries_keep_only_required_literals() {
+        assert_eq!(query("timeout.*db", true, false).unwrap().0, trigrams(&["tim", "ime", "meo", "eou", "out"]));
+        assert_eq!(query("(abcd)+", true, false).unwrap().0, trigrams(&["abc", "bcd"]));
+        // Neither side of an alternation is required
+        assert!(query("error|warning", true, false).is_none());
+        // An optional part ends the run before it
+        assert_eq!(query("colou?r", true, false).unwrap().0, trigrams(&["col", "olo"]));
+        // Too short to narrow anything
+        assert!(query("id", true, true).is_none());
+        assert!(query("[", true, false).is_none());
+    }
+
+    #[test]
+    fn check_excludes_only_files_that_are_indexed_and_unchanged() {
+        let path = std::env::temp_dir().join(format!("search-tool-index-{}.txt", std::process::id()));
+        std::fs::write(&path, "request failed: connection timeout\n").unwrap();
+        let meta = std::fs::metadata(&path).unwrap();
+        let mut files = HashMap::new();
+        files.insert("./app.log".to_string(), IndexedFile { stamp: FileStamp::of(&meta), filter: build_filter(b"request failed: connection timeout\n") });
+        files.insert("./big.log".to_string(), IndexedFile { stamp: FileStamp::of(&meta), filter: Vec::new() });
+        let index = ContentIndex { format: INDEX_FORMAT, root: PathBuf::from("/r"), built: String::new(), files };
+
+        let timeout = query("Timeout", false, true).unwrap();
+        let missing = query("missing", true, true).unwrap();
+        assert!(matches!(index.check(Path::new("./app.log"), &meta, &timeout), IndexCheck::Candidate));
+        assert!(matches!(index.check(Path::new("./app.log"), &meta, &missing), IndexCheck::Excluded));
+        // Files that were not read match everything
+        assert!(matches!(index.check(Path::new("./big.log"), &meta, &missing), IndexCheck::Candidate));
+        assert!(matches!(index.check(Path::new("./new.log"), &meta, &missing), IndexCheck::Stale));
+
+        std::fs::write(&path, "request failed: connection timeout, missing route\n").unwrap();
+        let changed = std::fs::metadata(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(matches!(index.check(Path::new("./app.log"), &changed, &missing), IndexCheck::Stale));
+    }
+}
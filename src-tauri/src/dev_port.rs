@@ -0,0 +1,329 @@
+// dev_port.rs
+// Dev-server port checks for `tauri dev`. vite runs with strictPort on the port in
+// tauri.conf.json's devPath, so a conflict makes it fail; this finds out who holds the
+// port and suggests a free one. It only reads: nothing is ever stopped or killed.
+
+use serde::Serialize;
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, Ipv6Addr, TcpListener};
+
+/// The port in build.devPath (tauri.conf.json) and server.port (vite.config.js)
+pub const DEV_SERVER_PORT: u16 = 1420;
+
+/// vite.config.js's HMR port when TAURI_DEV_HOST is set; never suggested for the dev server
+pub const HMR_PORT: u16 = 1421;
+
+// Ports tried after the wanted one when suggesting an alternative
+const SUGGESTION_ATTEMPTS: u16 = 100;
+
+/// A process listening on a port
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct PortOwner {
+    pub pid: u32,
+    pub name: Option<String>, // unknown when the process belongs to another user
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct PortReport {
+    pub port: u16,
+    pub in_use: bool,
+    pub owner: Option<PortOwner>,  // only looked up when the port is in use
+    pub suggestion: Option<u16>,   // a free port to move the dev server to
+}
+
+impl PortReport {
+    /// One-line explanation for a terminal
+    pub fn message(&self) -> String {
+        if !self.in_use {
+            return format!("Port {} is free", self.port);
+        }
+        let holder = match &self.owner {
+            Some(PortOwner { pid, name: Some(name) }) => format!("{} (pid {})", name, pid),
+            Some(PortOwner { pid, name: None }) => format!("pid {}", pid),
+            None => "another process".to_string(),
+        };
+        let mut message = format!("Port {} is in use by {}. Stop that process", self.port, holder);
+        match self.suggestion {
+            Some(free) => message.push_str(&format!(
+                ", or move the dev server to port {} (server.port in vite.config.js and build.devPath in tauri.conf.json)",
+                free
+            )),
+            None => message.push_str(", or move the dev server to another port"),
+        }
+        message.push('.');
+        message
+    }
+}
+
+/// Check `port` and, when it is taken, who holds it and which port is free instead
+pub fn check(port: u16) -> PortReport {
+    let in_use = is_in_use(port);
+    PortReport {
+        port,
+        in_use,
+        owner: if in_use { owner(port) } else { None },
+        suggestion: if in_use { find_free_port(port.saturating_add(1), SUGGESTION_ATTEMPTS) } else { None },
+    }
+}
+
+/// True if something listens on `port` on the IPv4 or IPv6 loopback. "localhost" may
+/// resolve to either, so both count.
+pub fn is_in_use(port: u16) -> bool {
+    let taken = |result: std::io::Result<TcpListener>| matches!(result, Err(e) if e.kind() == ErrorKind::AddrInUse);
+    taken(TcpListener::bind((Ipv4Addr::LOCALHOST, port))) || taken(TcpListener::bind((Ipv6Addr::LOCALHOST, port)))
+}
+
+/// First free port in `start..start + attempts`, leaving out HMR_PORT
+pub fn find_free_port(start: u16, attempts: u16) -> Option<u16> {
+    (0..attempts)
+        .filter_map(|offset| start.checked_add(offset))
+        .find(|&port| port != 0 && port != HMR_PORT && !is_in_use(port))
+}
+
+// -----------------------------------------------------------
+// Finding the owner, per platform
+// -----------------------------------------------------------
+
+/// The process listening on `port`, if the OS tells us
+#[cfg(target_os = "linux")]
+pub fn owner(port: u16) -> Option<PortOwner> {
+    let mut inodes = Vec::new();
+    for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        if let Ok(text) = std::fs::read_to_string(table) {
+            inodes.extend(parse_proc_net_tcp(&text, port));
+        }
+    }
+    if inodes.is_empty() {
+        return None;
+    }
+    // The socket shows up as a "socket:[inode]" link among the owner's open files
+    let wanted: Vec<String> = inodes.iter().map(|inode| format!("socket:[{}]", inode)).collect();
+    for entry in std::fs::read_dir("/proc").ok()?.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            continue;
+        };
+        let Ok(fds) = std::fs::read_dir(entry.path().join("fd")) else {
+            continue; // another user's process
+        };
+        let holds_socket = fds
+            .flatten()
+            .filter_map(|fd| std::fs::read_link(fd.path()).ok())
+            .any(|target| wanted.iter().any(|w| target.as_os_str() == w.as_str()));
+        if holds_socket {
+            let name = std::fs::read_to_string(entry.path().join("comm")).ok().map(|n| n.trim().to_string());
+            return Some(PortOwner { pid, name });
+        }
+    }
+    None
+}
+
+#[cfg(windows)]
+pub fn owner(port: u16) -> Option<PortOwner> {
+    let output = hidden_command("netstat").args(["-ano"]).output().ok()?;
+    let pid = parse_netstat(&String::from_utf8_lossy(&output.stdout), port)?;
+    let name = hidden_command("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+        .output()
+        .ok()
+        .and_then(|output| parse_tasklist(&String::from_utf8_lossy(&output.stdout)));
+    Some(PortOwner { pid, name })
+}
+
+#[cfg(windows)]
+fn hidden_command(program: &str) -> std::process::Command {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    let mut cmd = std::process::Command::new(program);
+    cmd.creation_flags(CREATE_NO_WINDOW);
+    cmd
+}
+
+#[cfg(target_os = "macos")]
+pub fn owner(port: u16) -> Option<PortOwner> {
+    let output = std::process::Command::new("lsof")
+        .args(["-nP", &format!("-iTCP:{}", port), "-sTCP:LISTEN", "-Fpc"])
+        .output()
+        .ok()?;
+    parse_lsof(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn owner(_port: u16) -> Option<PortOwner> {
+    None
+}
+
+// -----------------------------------------------------------
+// Parsers (kept on every platform so they are tested everywhere)
+// -----------------------------------------------------------
+
+// Inodes of sockets listening on `port` in /proc/net/tcp or tcp6:
+//   sl  local_address rem_address   st tx_queue:rx_queue tr:tm->when retrnsmt   uid  timeout inode
+//    0: 0100007F:058C 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 123456
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_proc_net_tcp(text: &str, port: u16) -> Vec<u64> {
+    const LISTEN: &str = "0A";
+    text.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let local_port = fields.get(1)?.rsplit_once(':')?.1;
+            let listening = *fields.get(3)? == LISTEN;
+            (listening && u16::from_str_radix(local_port, 16).ok()? == port).then(|| fields.get(9)?.parse().ok())?
+        })
+        .filter(|&inode| inode != 0)
+        .collect()
+}
+
+// PID listening on `port` in `netstat -ano` output:
+//   TCP    127.0.0.1:1420         0.0.0.0:0              LISTENING       4242
+//   TCP    [::1]:1420             [::]:0                 LISTENING       4242
+// Only the local address counts; the remote one may use the same port number.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn parse_netstat(text: &str, port: u16) -> Option<u32> {
+    text.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 5 || !fields[0].eq_ignore_ascii_case("TCP") || fields[3] != "LISTENING" {
+            return None;
+        }
+        let local_port = fields[1].rsplit_once(':')?.1.parse::<u16>().ok()?;
+        (local_port == port).then(|| fields[4].parse().ok())?
+    })
+}
+
+// Image name from `tasklist /FO CSV /NH`: "node.exe","4242","Console","1","52,120 K"
+#[cfg_attr(not(windows), allow(dead_code))]
+fn parse_tasklist(text: &str) -> Option<String> {
+    let line = text.lines().find(|line| line.starts_with('"'))?;
+    let name = line.trim_start_matches('"').split('"').next()?;
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+// `lsof -Fpc` prints one field per line: "p4242" then "cnode"
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_lsof(text: &str) -> Option<PortOwner> {
+    let mut lines = text.lines();
+    let pid = lines.find_map(|line| line.strip_prefix('p'))?.parse().ok()?;
+    let name = lines.find_map(|line| line.strip_prefix('c')).map(str::to_string);
+    Some(PortOwner { pid, name })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A listener on a port the OS picked, so tests never collide with real servers
+    fn listener() -> (TcpListener, u16) {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = listener.local_addr().expect("local addr").port();
+        (listener, port)
+    }
+
+    #[test]
+    fn port_held_by_a_listener_is_in_use() {
+        let (_listener, port) = listener();
+        assert!(is_in_use(port));
+        let report = check(port);
+        assert!(report.in_use);
+        assert!(report.suggestion.is_some_and(|free| free != port && !is_in_use(free)));
+    }
+
+    #[test]
+    fn released_port_is_free() {
+        let (listener, port) = listener();
+        drop(listener);
+        let report = check(port);
+        assert_eq!(report, PortReport { port, in_use: false, owner: None, suggestion: None });
+        assert_eq!(report.message(), format!("Port {} is free", port));
+    }
+
+    #[test]
+    fn free_port_search_skips_taken_ports() {
+        let (_listener, port) = listener();
+        let free = find_free_port(port, 10).expect("a free port");
+        assert_ne!(free, port);
+        assert!(free > port && free < port.saturating_add(10));
+        assert_eq!(find_free_port(port, 1), None);
+    }
+
+    #[test]
+    fn free_port_search_never_suggests_the_hmr_port() {
+        assert_eq!(find_free_port(HMR_PORT, 1), None);
+        assert_ne!(find_free_port(DEV_SERVER_PORT + 1, 10), Some(HMR_PORT));
+    }
+
+    #[test]
+    fn free_port_search_stops_at_the_last_port() {
+        assert!(matches!(find_free_port(u16::MAX, 5), None | Some(u16::MAX)));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn owner_of_our_own_listener_is_this_process() {
+        let (_listener, port) = listener();
+        let owner = owner(port).expect("owner found");
+        assert_eq!(owner.pid, std::process::id());
+        assert!(owner.name.is_some());
+    }
+
+    #[test]
+    fn message_names_the_owner_and_the_suggestion() {
+        let report = PortReport {
+            port: 1420,
+            in_use: true,
+            owner: Some(PortOwner { pid: 4242, name: Some("node".to_string()) }),
+            suggestion: Some(1422),
+        };
+        let message = report.message();
+        assert!(message.starts_with("Port 1420 is in use by node (pid 4242). Stop that process"));
+        assert!(message.contains("port 1422 (server.port in vite.config.js and build.devPath in tauri.conf.json)"));
+
+        let unknown = PortReport { owner: None, suggestion: None, ..report };
+        assert_eq!(
+            unknown.message(),
+            "Port 1420 is in use by another process. Stop that process, or move the dev server to another port."
+        );
+    }
+
+    #[test]
+    fn proc_net_tcp_finds_listening_sockets_only() {
+        let text = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n\
+            \x20  0: 0100007F:058C 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 111 1 0000000000000000 100 0 0 10 0\n\
+            \x20  1: 0100007F:D431 0100007F:058C 01 00000000:00000000 00:00000000 00000000  1000        0 222 1 0000000000000000 20 4 30 10 -1\n\
+            \x20  2: 0100007F:058D 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 333 1 0000000000000000 100 0 0 10 0\n";
+        assert_eq!(parse_proc_net_tcp(text, 1420), vec![111]);
+        assert_eq!(parse_proc_net_tcp(text, 1421), vec![333]);
+        assert!(parse_proc_net_tcp(text, 54321).is_empty());
+    }
+
+    #[test]
+    fn netstat_matches_the_local_port_only() {
+        let text = "\n\
+            Active Connections\n\n\
+            \x20 Proto  Local Address          Foreign Address        State           PID\n\
+            \x20 TCP    127.0.0.1:51000        127.0.0.1:1420         ESTABLISHED     7000\n\
+            \x20 TCP    0.0.0.0:14200          0.0.0.0:0              LISTENING       8000\n\
+            \x20 TCP    [::1]:1420             [::]:0                 LISTENING       4242\n\
+            \x20 UDP    0.0.0.0:1420           *:*                                    9000\n";
+        assert_eq!(parse_netstat(text, 1420), Some(4242));
+        assert_eq!(parse_netstat(text, 14200), Some(8000));
+        assert_eq!(parse_netstat(text, 51000), None);
+    }
+
+    #[test]
+    fn tasklist_csv_gives_the_image_name() {
+        assert_eq!(
+            parse_tasklist("\"node.exe\",\"4242\",\"Console\",\"1\",\"52,120 K\"\r\n"),
+            Some("node.exe".to_string())
+        );
+        assert_eq!(parse_tasklist("INFO: No tasks are running which match the specified criteria.\r\n"), None);
+    }
+
+    #[test]
+    fn lsof_fields_give_pid_and_command() {
+        assert_eq!(
+            parse_lsof("p4242\ncnode\nf23\n"),
+            Some(PortOwner { pid: 4242, name: Some("node".to_string()) })
+        );
+        assert_eq!(parse_lsof(""), None);
+    }
+}
@@ -0,0 +1,224 @@
+// diagnostics.rs
+// A single JSON bundle describing the app's state, for attaching to bug reports
+// ("search returns nothing"): versions, OS, ripgrep, effective settings, recent
+// searches with their stderr, cache usage and the tail of the log.
+// With redaction on, queries, paths and error texts are replaced by short hashes
+// (see logging::redact), so equal values still line up across the bundle.
+
+use serde::Serialize;
+
+use crate::logging::{self, LogRecord};
+use crate::search_manager::SearchRecord;
+use crate::settings::Settings;
+
+#[derive(Serialize)]
+pub struct AppInfo {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Serialize)]
+pub struct OsInfo {
+    pub os: &'static str,
+    pub family: &'static str,
+    pub arch: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct EngineInfo {
+    pub path: Option<String>,
+    pub version: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct CacheStats {
+    pub indexed_files: usize,
+    pub index_memory_bytes: usize,
+    pub index_budget_bytes: usize,
+    pub stored_results: usize,
+    pub running_searches: usize,
+}
+
+#[derive(Serialize)]
+pub struct DiagnosticsBundle {
+    pub generated: String,
+    pub redacted: bool,
+    pub app: AppInfo,
+    pub os: OsInfo,
+    pub engine: EngineInfo,
+    pub settings: Settings,
+    pub log_dir: Option<String>,
+    pub searches: Vec<SearchRecord>, // newest first
+    pub cache: CacheStats,
+    pub logs: Vec<LogRecord>, // oldest first
+}
+
+impl OsInfo {
+    pub fn current() -> Self {
+        Self {
+            os: std::env::consts::OS,
+            family: std::env::consts::FAMILY,
+            arch: std::env::consts::ARCH,
+        }
+    }
+}
+
+/// The settings as they go into a bundle, redacted or not: the API token is a
+/// credential for the local API and never leaves the machine
+pub fn bundle_settings(settings: &Settings) -> Settings {
+    let mut settings = settings.clone();
+    if !settings.api.token.is_empty() {
+        settings.api.token = "<hidden>".to_string();
+    }
+    settings
+}
+
+impl DiagnosticsBundle {
+    /// Hide queries, paths and error texts
+    pub fn redact(&mut self) {
+        self.redacted = true;
+        redact_opt(&mut self.engine.path);
+        redact_opt(&mut self.engine.error);
+        redact_opt(&mut self.log_dir);
+        for dir in &mut self.settings.excluded_dirs {
+            *dir = logging::redact(dir);
+        }
+        self.settings.editor.custom_command = redact_paths(&self.settings.editor.custom_command);
+        for search in &mut self.searches {
+            search.root = logging::redact(&search.root);
+            search.options.query = logging::redact(&search.options.query);
+            search.options.path = logging::redact(&search.options.path);
+            redact_opt(&mut search.options.file_filter);
+            if let Some(filter) = &mut search.options.metadata_filter {
+                redact_opt(&mut filter.modified_after);
+                redact_opt(&mut filter.modified_before);
+                redact_opt(&mut filter.owner);
+            }
+            if let Some(filter) = &mut search.options.log_filter {
+                redact_opt(&mut filter.from);
+                redact_opt(&mut filter.to);
+            }
+            if !search.stderr.is_empty() {
+                search.stderr = logging::redact(&search.stderr);
+            }
+            redact_opt(&mut search.error);
+            // Directory names are parts of paths; extensions say what is being searched
+            if let Some(stats) = &mut search.stats {
+                for bucket in stats.by_directory.iter_mut().chain(&mut stats.by_extension) {
+                    bucket.key = logging::redact(&bucket.key);
+                }
+            }
+        }
+
+        // Messages and other fields should not carry user data, but some (settings file
+        // location, error texts...) still quote a path
+        for record in &mut self.logs {
+            for (key, value) in record.fields.iter_mut() {
+                if logging::is_sensitive(key) && !value.starts_with("<redacted:") {
+                    *value = logging::redact(value);
+                } else {
+                    *value = redact_paths(value);
+                }
+            }
+            record.message = redact_paths(&record.message);
+        }
+    }
+}
+
+/// Hash every word of `text` that looks like a path: one holding a separator
+/// ("/srv/app", "D:\work", "\\server\share", "./x") or starting with "~".
+/// A path with spaces is split into words, but each part is hashed on its own
+fn redact_paths(text: &str) -> String {
+    const OPENING: &[char] = &['"', '\'', '(', '[', '='];
+    const CLOSING: &[char] = &['"', '\'', ')', ']', ',', ';', ':', '.'];
+    let mut out = String::with_capacity(text.len());
+    for piece in text.split_inclusive(char::is_whitespace) {
+        let word = piece.trim_end_matches(char::is_whitespace);
+        let opened = word.trim_start_matches(OPENING);
+        let core = opened.trim_end_matches(CLOSING);
+        if core.contains(['/', '\\']) || (core.starts_with('~') && core.len() > 1) {
+            let at = word.len() - opened.len();
+            out.push_str(&word[..at]);
+            out.push_str(&logging::redact(core));
+            out.push_str(&piece[at + core.len()..]);
+        } else {
+            out.push_str(piece);
+        }
+    }
+    out
+}
+
+fn redact_opt(value: &mut Option<String>) {
+    if let Some(v) = value {
+        *v = logging::redact(v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_in_text_are_hashed() {
+        for path in ["/srv/app/data.log", "D:\\work\\repo", "\\\\server\\share\\x", "./src/main.rs", "~/notes"] {
+            let text = format!("Path does not exist: {} (skipped)", path);
+            let redacted = redact_paths(&text);
+            assert!(!redacted.contains(path), "{}", redacted);
+            assert_eq!(redacted, format!("Path does not exist: {} (skipped)", logging::redact(path)));
+        }
+        assert_eq!(redact_paths("Invalid settings file \"/etc/x.json\": EOF"), format!("Invalid settings file \"{}\": EOF", logging::redact("/etc/x.json")));
+        assert_eq!(redact_paths("Search finished in 12 ms"), "Search finished in 12 ms");
+    }
+
+    #[test]
+    fn a_redacted_bundle_hides_search_details() {
+        use crate::search::{SearchStats, StatsBucket};
+        let bucket = |key: &str| StatsBucket { key: key.to_string(), files_searched: 3, files_matched: 1, matches: 2 };
+        let options = serde_json::from_value(serde_json::json!({ "query": "password", "path": "/home/jo/secret" })).unwrap();
+        let stats = SearchStats {
+            by_directory: vec![bucket("clients"), bucket(".")],
+            by_extension: vec![bucket("kdbx")],
+            ..Default::default()
+        };
+        let mut bundle = DiagnosticsBundle {
+            generated: String::new(),
+            redacted: false,
+            app: AppInfo { name: "app".to_string(), version: "1.0.0".to_string() },
+            os: OsInfo::current(),
+            engine: EngineInfo { path: Some("/opt/rg".to_string()), version: None, error: None },
+            settings: Settings::default(),
+            log_dir: None,
+            searches: vec![SearchRecord {
+                search_id: "search-1".to_string(),
+                started: String::new(),
+                root: "/home/jo/secret".to_string(),
+                options,
+                status: "completed".to_string(),
+                stats: Some(stats),
+                truncated: false,
+                stderr: String::new(),
+                error: None,
+            }],
+            cache: CacheStats { indexed_files: 0, index_memory_bytes: 0, index_budget_bytes: 0, stored_results: 0, running_searches: 0 },
+            logs: Vec::new(),
+        };
+        bundle.redact();
+
+        let json = serde_json::to_string(&bundle).unwrap();
+        for secret in ["password", "/home/jo", "/opt/rg", "clients", "kdbx"] {
+            assert!(!json.contains(secret), "{} in {}", secret, json);
+        }
+        // Counts are kept, and equal keys still hash alike
+        let stats = bundle.searches[0].stats.as_ref().unwrap();
+        assert_eq!(stats.by_directory[0].key, logging::redact("clients"));
+        assert_eq!(stats.by_directory[0].matches, 2);
+        assert_eq!(stats.by_extension[0].key, logging::redact("kdbx"));
+    }
+
+    #[test]
+    fn parts_of_a_path_with_spaces_are_hashed() {
+        let redacted = redact_paths("C:\\Users\\Jo Ann\\repo");
+        assert!(!redacted.contains("Users") && !redacted.contains("repo"), "{}", redacted);
+    }
+}
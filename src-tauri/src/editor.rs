@@ -0,0 +1,218 @@
+// editor.rs
+// Opens a result in the user's external editor at its line and column.
+// Each preset is a command template; {file}, {line} and {column} are replaced per
+// argument after the template is split, so paths with spaces stay one argument and
+// nothing goes through a shell.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::settings::EditorSettings;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EditorPreset {
+    VsCode,
+    Vim, // gvim; a terminal vim has no window to open in
+    NotepadPlusPlus,
+    JetBrains, // the `idea` launcher; other IDEs take the same arguments through a custom command
+    Custom,
+}
+
+impl EditorPreset {
+    pub fn template(self) -> Option<&'static str> {
+        match self {
+            EditorPreset::VsCode => Some("code -g {file}:{line}:{column}"),
+            EditorPreset::Vim => Some("gvim \"+call cursor({line}, {column})\" {file}"),
+            EditorPreset::NotepadPlusPlus => Some("notepad++ -n{line} -c{column} {file}"),
+            EditorPreset::JetBrains => Some("idea --line {line} --column {column} {file}"),
+            EditorPreset::Custom => None,
+        }
+    }
+}
+
+/// Check a custom command template before it is saved
+pub fn validate_template(template: &str) -> Result<(), String> {
+    let args = split_template(template)?;
+    if args.is_empty() {
+        return Err("Editor command is empty".to_string());
+    }
+    if !args.iter().any(|arg| arg.contains("{file}")) {
+        return Err("Editor command must contain {file}".to_string());
+    }
+    Ok(())
+}
+
+/// Launch the configured editor on `file` (1-based line and column) and return
+/// without waiting for it
+pub fn open(settings: &EditorSettings, file: &Path, line: u64, column: usize) -> Result<(), String> {
+    let template = match settings.preset.template() {
+        Some(template) => template,
+        None => settings.custom_command.as_str(),
+    };
+    let mut args = editor_args(template, file, line, column)?.into_iter();
+    let program = args.next().ok_or_else(|| "Editor command is empty".to_string())?;
+
+    let mut cmd = Command::new(resolve_program(&program));
+    cmd.args(args).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    hide_console(&mut cmd);
+    let mut child = cmd.spawn().map_err(|e| format!("Failed to start editor '{}': {}", program, e))?;
+    // Wait on a thread of its own so the editor is reaped when it exits (no zombie on Unix)
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
+// The template's arguments with {file}, {line} and {column} filled in
+fn editor_args(template: &str, file: &Path, line: u64, column: usize) -> Result<Vec<String>, String> {
+    let file_arg = file.to_string_lossy();
+    let (line, column) = (line.max(1).to_string(), column.max(1).to_string());
+    let values = [("{file}", file_arg.as_ref()), ("{line}", line.as_str()), ("{column}", column.as_str())];
+    Ok(split_template(template)?.iter().map(|arg| fill_placeholders(arg, &values)).collect())
+}
+
+// One pass, so a file name that itself contains "{line}" is left alone
+fn fill_placeholders(arg: &str, values: &[(&str, &str)]) -> String {
+    let mut out = String::new();
+    let mut rest = arg;
+    while !rest.is_empty() {
+        match values.iter().find(|(name, _)| rest.starts_with(name)) {
+            Some((name, value)) => {
+                out.push_str(value);
+                rest = &rest[name.len()..];
+            }
+            None => {
+                let c = rest.chars().next().unwrap_or_default();
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    out
+}
+
+// Whitespace-separated arguments; double quotes group, backslash-quote is a literal quote
+fn split_template(template: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quoted = false;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+                in_arg = true;
+            }
+            '"' => {
+                quoted = !quoted;
+                in_arg = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if quoted {
+        return Err(format!("Unclosed quote in editor command: {}", template));
+    }
+    if in_arg {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+// Windows launchers such as `code` are .cmd scripts, which CreateProcess does not find
+// on its own; look the name up through PATH and PATHEXT like the shell would
+#[cfg(windows)]
+fn resolve_program(program: &str) -> PathBuf {
+    let path = Path::new(program);
+    if path.extension().is_some() || path.components().count() > 1 {
+        return path.to_path_buf();
+    }
+    let extensions = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+    std::env::var_os("PATH")
+        .iter()
+        .flat_map(std::env::split_paths)
+        .flat_map(|dir| {
+            extensions
+                .split(';')
+                .filter(|ext| !ext.is_empty())
+                .map(move |ext| dir.join(format!("{}{}", program, ext.to_lowercase())))
+        })
+        .find(|candidate| candidate.is_file())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+#[cfg(not(windows))]
+fn resolve_program(program: &str) -> PathBuf {
+    PathBuf::from(program)
+}
+
+// Script launchers would otherwise flash a console window
+fn hide_console(_cmd: &mut Command) {
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        _cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_template_groups_quotes() {
+        assert_eq!(split_template("code -g {file}").unwrap(), ["code", "-g", "{file}"]);
+        assert_eq!(split_template("  a   b\tc ").unwrap(), ["a", "b", "c"]);
+        assert_eq!(
+            split_template("\"C:\\Program Files\\ed.exe\" --at=\"{line} {column}\" {file}").unwrap(),
+            ["C:\\Program Files\\ed.exe", "--at={line} {column}", "{file}"]
+        );
+        assert_eq!(split_template("ed \"\" {file}").unwrap(), ["ed", "", "{file}"]);
+        assert_eq!(split_template("ed \\\"x\\\" {file}").unwrap(), ["ed", "\"x\"", "{file}"]);
+        assert!(split_template("ed \"{file}").is_err());
+        assert!(split_template("   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn placeholders_fill_whole_arguments() {
+        let file = Path::new("/home/me/my project/main file.rs");
+        let args = editor_args(EditorPreset::VsCode.template().unwrap(), file, 12, 5).unwrap();
+        assert_eq!(args, ["code", "-g", "/home/me/my project/main file.rs:12:5"]);
+
+        let args = editor_args(EditorPreset::Vim.template().unwrap(), file, 3, 7).unwrap();
+        assert_eq!(args, ["gvim", "+call cursor(3, 7)", "/home/me/my project/main file.rs"]);
+
+        // Line and column are 1-based; 0 means the start
+        let args = editor_args("ed +{line}:{column} {file}", Path::new("a.txt"), 0, 0).unwrap();
+        assert_eq!(args, ["ed", "+1:1", "a.txt"]);
+
+        // Placeholders inside the substituted path are not filled in again
+        let args = editor_args("ed {file}:{line}", Path::new("notes {line} é.md"), 4, 1).unwrap();
+        assert_eq!(args, ["ed", "notes {line} é.md:4"]);
+    }
+
+    #[test]
+    fn templates_are_checked_before_saving() {
+        assert!(validate_template("subl {file}:{line}").is_ok());
+        assert!(validate_template("subl").is_err());
+        assert!(validate_template("").is_err());
+        assert!(validate_template("subl \"{file}").is_err());
+        for preset in [EditorPreset::VsCode, EditorPreset::Vim, EditorPreset::NotepadPlusPlus, EditorPreset::JetBrains] {
+            assert!(validate_template(preset.template().unwrap()).is_ok());
+        }
+    }
+}
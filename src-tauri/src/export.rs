@@ -0,0 +1,271 @@
+// export.rs
+// Writes the stored results of a search to a file, for pasting into tickets and spreadsheets.
+// Every format is streamed match by match through a BufWriter, so very large
+// result sets never have to be built in memory.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::log_format::Severity;
+use crate::result_store::StoredResults;
+use crate::search::MatchRange;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,      // file,line,column,text
+    Jsonl,    // one JSON object per match
+    Markdown, // report with stats and a section per file
+    Grep,     // file:line:text
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Grep => "txt",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Jsonl => "JSON Lines",
+            ExportFormat::Markdown => "Markdown",
+            ExportFormat::Grep => "Text (file:line:text)",
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct ExportSummary {
+    pub path: String,
+    pub format: ExportFormat,
+    pub matches_written: usize,
+}
+
+#[derive(Serialize)]
+struct JsonlRecord<'a> {
+    file: &'a str,
+    line: u64,
+    column: usize,
+    text: &'a str,
+    ranges: &'a [MatchRange],
+    binary_offset: Option<u64>,
+    timestamp: Option<i64>,
+    level: Option<Severity>,
+}
+
+/// Export all matches of `stored` to `path` in `format`
+pub fn export_to_file(stored: &StoredResults, format: ExportFormat, path: &Path) -> Result<ExportSummary, String> {
+    let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut out = BufWriter::new(file);
+    let matches_written = write_export(stored, format, &mut out)
+        .and_then(|n| out.flush().map(|_| n))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    Ok(ExportSummary {
+        path: path.display().to_string(),
+        format,
+        matches_written,
+    })
+}
+
+/// Stream the export to any writer. Returns the number of matches written.
+pub fn write_export(stored: &StoredResults, format: ExportFormat, out: &mut impl Write) -> io::Result<usize> {
+    match format {
+        ExportFormat::Csv => write_csv(stored, out),
+        ExportFormat::Jsonl => write_jsonl(stored, out),
+        ExportFormat::Markdown => write_markdown(stored, out),
+        ExportFormat::Grep => write_grep(stored, out),
+    }
+}
+
+fn write_csv(stored: &StoredResults, out: &mut impl Write) -> io::Result<usize> {
+    writeln!(out, "file,line,column,text")?;
+    let mut count = 0;
+    for m in stored.matches_in_order() {
+        writeln!(
+            out,
+            "{},{},{},{}",
+            csv_field(display_path(&m.path)),
+            m.line,
+            m.column(),
+            csv_field(&m.text)
+        )?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn write_jsonl(stored: &StoredResults, out: &mut impl Write) -> io::Result<usize> {
+    let mut count = 0;
+    for m in stored.matches_in_order() {
+        let record = JsonlRecord {
+            file: display_path(&m.path),
+            line: m.line,
+            column: m.column(),
+            text: &m.text,
+            ranges: &m.ranges,
+            binary_offset: m.binary_offset,
+            timestamp: m.timestamp,
+            level: m.level,
+        };
+        serde_json::to_writer(&mut *out, &record)?;
+        writeln!(out)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn write_grep(stored: &StoredResults, out: &mut impl Write) -> io::Result<usize> {
+    let mut count = 0;
+    for m in stored.matches_in_order() {
+        writeln!(out, "{}:{}:{}", display_path(&m.path), m.line, m.text)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn write_markdown(stored: &StoredResults, out: &mut impl Write) -> io::Result<usize> {
+    let stats = &stored.outcome.stats;
+    writeln!(out, "# Search results")?;
+    writeln!(out)?;
+    writeln!(out, "| | |")?;
+    writeln!(out, "|---|---|")?;
+    writeln!(out, "| Query | `{}` |", md_cell(&stored.options.query))?;
+    writeln!(out, "| Folder | `{}` |", md_cell(&stored.root.display().to_string()))?;
+    if let Some(filter) = &stored.options.file_filter {
+        writeln!(out, "| File filter | `{}` |", md_cell(filter))?;
+    }
+    writeln!(out, "| Exported | {} |", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"))?;
+    writeln!(out, "| Files with matches | {} |", stored.file_count())?;
+    writeln!(out, "| Matched lines | {} |", stats.matched_lines)?;
+    writeln!(out, "| Total matches | {} |", stats.total_matches)?;
+    writeln!(out, "| Files searched | {} |", stats.files_searched)?;
+    writeln!(out, "| Bytes searched | {} |", stats.bytes_searched)?;
+    writeln!(out, "| Files skipped | {} |", stats.skipped.total())?;
+    writeln!(out, "| Search time | {:.1} ms |", stats.total_time_ms)?;
+    if stored.outcome.truncated {
+        writeln!(out, "| Note | results were truncated by the result limit |")?;
+    }
+
+    let mut count = 0;
+    let mut current_file: Option<&str> = None;
+    for m in stored.matches_by_file() {
+        if current_file != Some(m.path.as_str()) {
+            if current_file.is_some() {
+                writeln!(out, "```")?;
+            }
+            writeln!(out)?;
+            writeln!(out, "## {}", display_path(&m.path))?;
+            writeln!(out)?;
+            writeln!(out, "```")?;
+            current_file = Some(m.path.as_str());
+        }
+        writeln!(out, "{:>6}: {}", m.line, m.text.replace("```", "`\u{200b}``"))?;
+        count += 1;
+    }
+    if current_file.is_some() {
+        writeln!(out, "```")?;
+    }
+    Ok(count)
+}
+
+// "./src/main.rs" -> "src/main.rs"
+fn display_path(path: &str) -> &str {
+    path.strip_prefix("./").unwrap_or(path)
+}
+
+// Quote a CSV field when it contains a separator, quote or line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn md_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('`', "'")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{SearchMatch, SearchOptions, SearchOutcome};
+    use std::path::PathBuf;
+
+    fn found(path: &str, line: u64, text: &str, start: usize) -> SearchMatch {
+        SearchMatch {
+            path: path.to_string(),
+            line,
+            text: text.to_string(),
+            ranges: vec![MatchRange { start, end: start + 5 }],
+            binary_offset: None,
+            timestamp: None,
+            level: None,
+        }
+    }
+
+    fn export(format: ExportFormat) -> (usize, String) {
+        let options: SearchOptions = serde_json::from_value(serde_json::json!({ "query": "error", "path": "/srv" })).unwrap();
+        let outcome = SearchOutcome {
+            matches: vec![
+                found("./src/a.rs", 3, "let error = \"bad, \"\"quoted\"\"\";", 4),
+                found("./src/a.rs", 9, "error", 0),
+                found("./b.txt", 1, "an error with ``` fences", 3),
+            ],
+            ..Default::default()
+        };
+        let stored = StoredResults::new(PathBuf::from("/srv"), options, outcome);
+        let mut out = Vec::new();
+        let count = write_export(&stored, format, &mut out).unwrap();
+        (count, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn csv_quotes_fields_with_separators() {
+        let (count, text) = export(ExportFormat::Csv);
+        assert_eq!(count, 3);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "file,line,column,text");
+        assert_eq!(lines[1], r#"src/a.rs,3,5,"let error = ""bad, """"quoted"""""";""#);
+        assert_eq!(lines[2], "src/a.rs,9,1,error");
+        assert_eq!(lines[3], "b.txt,1,4,an error with ``` fences");
+    }
+
+    #[test]
+    fn jsonl_writes_one_object_per_match() {
+        let (count, text) = export(ExportFormat::Jsonl);
+        let records: Vec<serde_json::Value> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(records.len(), count);
+        assert_eq!(records[0]["file"], "src/a.rs");
+        assert_eq!(records[0]["column"], 5);
+        assert_eq!(records[0]["ranges"][0]["end"], 9);
+        assert_eq!(records[2]["timestamp"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn grep_format_is_file_line_text() {
+        let (_, text) = export(ExportFormat::Grep);
+        assert_eq!(text.lines().nth(1), Some("src/a.rs:9:error"));
+    }
+
+    #[test]
+    fn markdown_has_a_fenced_section_per_file() {
+        let (count, text) = export(ExportFormat::Markdown);
+        assert_eq!(count, 3);
+        assert!(text.starts_with("# Search results\n"));
+        assert!(text.contains("| Query | `error` |"));
+        assert!(text.contains("| Files with matches | 2 |"));
+        assert!(text.contains("## src/a.rs\n\n```\n     3: let error"));
+        assert!(text.contains("## b.txt\n\n```\n     1: an error with `\u{200b}`` fences\n```\n"));
+        // Every fence is closed, and fences in the text cannot close one early
+        assert_eq!(text.lines().filter(|l| *l == "```").count(), 4);
+    }
+}
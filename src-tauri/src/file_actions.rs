@@ -0,0 +1,164 @@
+// file_actions.rs
+// Actions on a result file outside the app: reveal it in the system file manager,
+// and format its path for the clipboard.
+
+use serde::Deserialize;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// Path formats offered by "Copy path"
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PathFormat {
+    Absolute,
+    Relative, // to the search root
+    FileLine, // absolute path and line, "C:\src\main.rs:42"
+}
+
+/// Format `abs_path` for copying. Paths outside `root` (or without one) stay absolute
+/// in the Relative format.
+pub fn format_path(abs_path: &Path, root: Option<&Path>, format: PathFormat, line: Option<u64>) -> String {
+    let abs_path = native_path(abs_path);
+    match format {
+        PathFormat::Absolute => abs_path.display().to_string(),
+        PathFormat::Relative => root
+            .map(native_path)
+            .and_then(|root| abs_path.strip_prefix(root).ok().map(Path::to_path_buf))
+            .unwrap_or_else(|| abs_path.clone())
+            .display()
+            .to_string(),
+        PathFormat::FileLine => match line {
+            Some(line) => format!("{}:{}", abs_path.display(), line),
+            None => abs_path.display().to_string(),
+        },
+    }
+}
+
+// Result paths use '/' and may start with "./"; rebuilding from the components gives
+// the platform's separators and drops the "." parts
+fn native_path(path: &Path) -> PathBuf {
+    path.components().filter(|c| *c != Component::CurDir).collect()
+}
+
+/// Open the folder containing `path` in the system file manager, with the file selected
+/// where the file manager supports it
+pub fn reveal(path: &Path) -> Result<(), String> {
+    let path = native_path(path);
+    if !path.exists() {
+        return Err(format!("File does not exist: {}", path.display()));
+    }
+    reveal_native(&path)
+}
+
+#[cfg(windows)]
+fn reveal_native(path: &Path) -> Result<(), String> {
+    use std::os::windows::process::CommandExt;
+    // explorer only understands the quotes after "/select,", not around the whole argument
+    Command::new("explorer")
+        .raw_arg(format!("/select,\"{}\"", path.display()))
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to start Explorer: {}", e))
+}
+
+#[cfg(target_os = "macos")]
+fn reveal_native(path: &Path) -> Result<(), String> {
+    Command::new("open")
+        .arg("-R")
+        .arg(path)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to start Finder: {}", e))
+}
+
+// Most Linux file managers (Nautilus, Dolphin, Nemo, Thunar, ...) implement the
+// FileManager1 D-Bus interface, which selects the file; otherwise open the folder
+#[cfg(all(unix, not(target_os = "macos")))]
+fn reveal_native(path: &Path) -> Result<(), String> {
+    let shown = Command::new("dbus-send")
+        .args([
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.FileManager1",
+            "--type=method_call",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(format!("array:string:{}", file_uri(path)))
+        .arg("string:")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if shown {
+        return Ok(());
+    }
+
+    let folder = if path.is_dir() { path } else { path.parent().unwrap_or(path) };
+    Command::new("xdg-open")
+        .arg(folder)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to start xdg-open: {}", e))
+}
+
+// "file:///home/me/my%20file.txt"; dbus-send also splits arrays on ','
+#[cfg(all(unix, not(target_os = "macos")))]
+fn file_uri(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+    let mut uri = String::from("file://");
+    for &byte in path.as_os_str().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn paths_are_formatted_for_copying() {
+        let root = Path::new("/srv/app");
+        let file = Path::new("/srv/app/./src/main.rs");
+        assert_eq!(format_path(file, Some(root), PathFormat::Absolute, Some(3)), "/srv/app/src/main.rs");
+        assert_eq!(format_path(file, Some(root), PathFormat::Relative, None), "src/main.rs");
+        assert_eq!(format_path(file, Some(Path::new("/srv/app/")), PathFormat::Relative, None), "src/main.rs");
+        assert_eq!(format_path(file, Some(root), PathFormat::FileLine, Some(42)), "/srv/app/src/main.rs:42");
+        assert_eq!(format_path(file, Some(root), PathFormat::FileLine, None), "/srv/app/src/main.rs");
+        // Relative keeps paths outside the root (or without one) absolute
+        assert_eq!(format_path(Path::new("/srv/application/a.rs"), Some(root), PathFormat::Relative, None), "/srv/application/a.rs");
+        assert_eq!(format_path(Path::new("/etc/hosts"), Some(root), PathFormat::Relative, None), "/etc/hosts");
+        assert_eq!(format_path(file, None, PathFormat::Relative, None), "/srv/app/src/main.rs");
+        // Result paths as ripgrep prints them
+        assert_eq!(format_path(Path::new("./src/main.rs"), None, PathFormat::Absolute, None), "src/main.rs");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn paths_are_formatted_for_copying() {
+        let root = Path::new(r"C:\work\app");
+        let file = Path::new("C:/work/app/./src/main.rs");
+        assert_eq!(format_path(file, Some(root), PathFormat::Absolute, None), r"C:\work\app\src\main.rs");
+        assert_eq!(format_path(file, Some(root), PathFormat::Relative, None), r"src\main.rs");
+        assert_eq!(format_path(file, Some(root), PathFormat::FileLine, Some(42)), r"C:\work\app\src\main.rs:42");
+        assert_eq!(format_path(Path::new(r"D:\other\a.rs"), Some(root), PathFormat::Relative, None), r"D:\other\a.rs");
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn file_uris_are_percent_encoded() {
+        assert_eq!(file_uri(Path::new("/home/me/notes-1_2.~txt")), "file:///home/me/notes-1_2.~txt");
+        assert_eq!(file_uri(Path::new("/home/me/my file.txt")), "file:///home/me/my%20file.txt");
+        assert_eq!(file_uri(Path::new("/tmp/a,b.log")), "file:///tmp/a%2Cb.log");
+        assert_eq!(file_uri(Path::new("/tmp/café")), "file:///tmp/caf%C3%A9");
+        assert_eq!(file_uri(Path::new("/tmp/100%")), "file:///tmp/100%25");
+    }
+}
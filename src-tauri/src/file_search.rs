@@ -0,0 +1,301 @@
+// file_search.rs
+// File-name search: walks the root like a content search (same hidden, ignore, glob and
+// excluded-directory rules, see walker.rs) and matches each file's name or path against
+// a fuzzy, glob or regex pattern. Hits are handed out in batches while the walk runs.
+//
+// Patterns without a '/' are matched against the file name only ("*Controller*.cs"),
+// patterns with one against the path below the root, '/'-separated on every platform
+// ("src/**/*.cs").
+
+use globset::{GlobBuilder, GlobMatcher};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs::Metadata;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::metadata_filter::MetadataFilter;
+use crate::search::{SearchControl, SearchWarning};
+use crate::settings::Settings;
+use crate::walker::{self, SkippedFiles, WalkOptions, WalkProgress};
+
+// Hand out hits at least this often, or once this many have piled up
+const BATCH_INTERVAL: Duration = Duration::from_millis(200);
+const BATCH_SIZE: usize = 200;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NameMatchMode {
+    Fuzzy,
+    Glob,
+    Regex,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileSearchOptions {
+    pub pattern: String,
+    pub path: String,
+    pub mode: NameMatchMode,
+    #[serde(default)]
+    pub file_filter: Option<String>, // --glob filter, as for content searches
+    #[serde(default)]
+    pub case_sensitive: Option<bool>, // default: case-insensitive
+    #[serde(default)]
+    pub metadata_filter: Option<MetadataFilter>,
+}
+
+impl FileSearchOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.pattern.trim().is_empty() {
+            return Err("File name pattern cannot be empty".to_string());
+        }
+        if !Path::new(&self.path).is_dir() {
+            return Err(format!("Path is not a directory: {}", self.path));
+        }
+        NameMatcher::new(self)?;
+        self.metadata_filter.as_ref().map_or(Ok(None), MetadataFilter::compile).map(|_| ())
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct FileHit {
+    pub path: String, // "./relative/path", openable with read_file_mmap_chunk and the search ID
+    pub size: u64,
+    pub modified: Option<String>, // RFC 3339, local time
+    pub score: Option<i64>,       // fuzzy mode only; higher is a better match
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct FileSearchStats {
+    pub files_visited: usize,
+    pub files_matched: usize,
+    pub skipped: SkippedFiles,
+    pub total_time_ms: f64,
+}
+
+#[derive(Default)]
+pub struct FileSearchOutcome {
+    pub stats: FileSearchStats,
+    pub warnings: Vec<SearchWarning>,
+    pub truncated: bool, // stopped at limits.max_results
+    pub cancelled: bool,
+}
+
+/// Walk `options.path` and call `on_hits` with batches of matching files.
+/// Stops at `limits.max_results` hits or when `control` is cancelled.
+pub fn run_file_search(
+    options: &FileSearchOptions,
+    settings: &Settings,
+    control: &SearchControl,
+    mut on_hits: impl FnMut(Vec<FileHit>),
+) -> Result<FileSearchOutcome, String> {
+    let started = Instant::now();
+    let matcher = NameMatcher::new(options)?;
+    let root = Path::new(&options.path);
+    // Binary and size limits are about contents; a name search lists every file
+    let walk_options = WalkOptions {
+        max_file_size: 0,
+        skip_binary: false,
+        metadata: options.metadata_filter.as_ref().map_or(Ok(None), MetadataFilter::compile)?,
+        ..WalkOptions::new(root, options.file_filter.as_deref(), settings)
+    };
+    let max_results = settings.limits.max_results;
+
+    let mut outcome = FileSearchOutcome::default();
+    let mut batch = Vec::new();
+    let mut last_batch = Instant::now();
+    let walk = walker::walk(&walk_options, &WalkProgress::default(), |path, meta| {
+        outcome.stats.files_visited += 1;
+        if let Some(score) = matcher.score(path) {
+            batch.push(file_hit(path, meta, score));
+            outcome.stats.files_matched += 1;
+        }
+        if batch.len() >= BATCH_SIZE || (!batch.is_empty() && last_batch.elapsed() >= BATCH_INTERVAL) {
+            on_hits(std::mem::take(&mut batch));
+            last_batch = Instant::now();
+        }
+        if max_results > 0 && outcome.stats.files_matched >= max_results {
+            outcome.truncated = true;
+            return false;
+        }
+        !control.is_cancelled()
+    })?;
+    if !batch.is_empty() {
+        on_hits(batch);
+    }
+
+    outcome.cancelled = control.is_cancelled();
+    outcome.stats.skipped = walk.skipped;
+    outcome.warnings = walk.warnings;
+    outcome.stats.total_time_ms = started.elapsed().as_secs_f64() * 1000.0;
+    Ok(outcome)
+}
+
+fn file_hit(path: &Path, meta: &Metadata, score: Option<i64>) -> FileHit {
+    FileHit {
+        path: path.to_string_lossy().replace('\\', "/"),
+        size: meta.len(),
+        modified: meta
+            .modified()
+            .ok()
+            .map(|t| chrono::DateTime::<chrono::Local>::from(t).to_rfc3339()),
+        score,
+    }
+}
+
+// -----------------------------------------------------------
+// Matching
+// -----------------------------------------------------------
+
+enum Matcher {
+    Fuzzy(Vec<char>),
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+struct NameMatcher {
+    matcher: Matcher,
+    full_path: bool, // match the path below the root instead of the file name
+    case_sensitive: bool,
+}
+
+impl NameMatcher {
+    fn new(options: &FileSearchOptions) -> Result<Self, String> {
+        let pattern = options.pattern.trim();
+        let case_sensitive = options.case_sensitive.unwrap_or(false);
+        let matcher = match options.mode {
+            NameMatchMode::Fuzzy => Matcher::Fuzzy(
+                pattern
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .map(|c| if case_sensitive { c } else { c.to_lowercase().next().unwrap_or(c) })
+                    .collect(),
+            ),
+            NameMatchMode::Glob => Matcher::Glob(
+                GlobBuilder::new(pattern)
+                    .case_insensitive(!case_sensitive)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|e| format!("Invalid glob '{}': {}", pattern, e))?
+                    .compile_matcher(),
+            ),
+            NameMatchMode::Regex => Matcher::Regex(
+                RegexBuilder::new(pattern)
+                    .case_insensitive(!case_sensitive)
+                    .build()
+                    .map_err(|e| format!("Invalid regex '{}': {}", pattern, e))?,
+            ),
+        };
+        Ok(Self {
+            matcher,
+            full_path: pattern.contains('/'), // '\\' escapes in globs and regexes
+            case_sensitive,
+        })
+    }
+
+    // None if `path` ("./relative/path") does not match; Some(score) in fuzzy mode
+    fn score(&self, path: &Path) -> Option<Option<i64>> {
+        let path = path.to_string_lossy().replace('\\', "/");
+        let relative = path.strip_prefix("./").unwrap_or(&path);
+        let target = if self.full_path {
+            relative
+        } else {
+            relative.rsplit('/').next().unwrap_or(relative)
+        };
+        match &self.matcher {
+            Matcher::Fuzzy(pattern) => fuzzy_score(pattern, target, self.case_sensitive).map(Some),
+            Matcher::Glob(glob) => glob.is_match(target).then_some(None),
+            Matcher::Regex(regex) => regex.is_match(target).then_some(None),
+        }
+    }
+}
+
+// The pattern's characters must appear in `text` in order. Consecutive characters and
+// characters starting a word (after a separator, or a lower-to-upper case change) score
+// higher; skipped characters cost a little. Every start of the first character is tried.
+fn fuzzy_score(pattern: &[char], text: &str, case_sensitive: bool) -> Option<i64> {
+    let original: Vec<char> = text.chars().collect();
+    let folded: Vec<char> = if case_sensitive {
+        original.clone()
+    } else {
+        original.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect()
+    };
+    let Some(&first) = pattern.first() else {
+        return Some(0);
+    };
+
+    let word_start = |i: usize| {
+        i == 0
+            || matches!(original[i - 1], '/' | '\\' | '_' | '-' | '.' | ' ')
+            || (original[i - 1].is_lowercase() && original[i].is_uppercase())
+    };
+
+    let mut best = None;
+    for start in (0..folded.len()).filter(|&i| folded[i] == first) {
+        let mut score = 0i64;
+        let mut previous: Option<usize> = None;
+        let mut next = start;
+        let mut matched = 0;
+        for &wanted in pattern {
+            let Some(offset) = folded[next..].iter().position(|&c| c == wanted) else {
+                break;
+            };
+            let i = next + offset;
+            score += 16;
+            if previous.is_some_and(|p| p + 1 == i) {
+                score += 8;
+            }
+            if word_start(i) {
+                score += 12;
+            }
+            score -= previous.map_or(i, |p| i - p - 1).min(8) as i64;
+            previous = Some(i);
+            next = i + 1;
+            matched += 1;
+        }
+        if matched < pattern.len() {
+            break; // later starts cannot do better than this one
+        }
+        best = best.max(Some(score - original.len() as i64 / 4));
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(pattern: &str, text: &str) -> Option<i64> {
+        let pattern: Vec<char> = pattern.chars().collect();
+        fuzzy_score(&pattern, text, false)
+    }
+
+    #[test]
+    fn characters_must_appear_in_order() {
+        assert!(score("smr", "search_manager.rs").is_some());
+        assert!(score("rsm", "search_manager.rs").is_none());
+        assert_eq!(score("", "anything"), Some(0));
+        assert!(score("a", "").is_none());
+    }
+
+    #[test]
+    fn runs_and_word_starts_score_higher() {
+        // Consecutive characters beat scattered ones
+        assert!(score("main", "main.rs") > score("main", "mxaxixn.rs"));
+        // Word starts (separators, camelCase humps) beat the middle of words
+        assert!(score("sm", "search_manager.rs") > score("sm", "chasm.rs"));
+        assert!(score("rs", "ResultStore.ts") > score("rs", "cursor.ts"));
+        // The best start is used, not the first
+        assert!(score("log", "blog/log_format.rs") > score("log", "blog/format.rs"));
+        // Shorter paths win ties
+        assert!(score("lib", "lib.rs") > score("lib", "src/deep/nested/lib.rs"));
+    }
+
+    #[test]
+    fn case_folding_follows_the_flag() {
+        let pattern: Vec<char> = "readme".chars().collect();
+        assert!(fuzzy_score(&pattern, "README.md", false).is_some());
+        assert!(fuzzy_score(&pattern, "README.md", true).is_none());
+    }
+}
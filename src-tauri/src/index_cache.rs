@@ -0,0 +1,66 @@
+// index_cache.rs
+// Keeps the LineIndex of recently viewed files so scrolling does not rescan them.
+// The cache is bounded by the `cache_budget_mb` setting; the least recently used
+// indexes are dropped first when it grows past the budget.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::memmap_line_reader::LineIndex;
+
+struct CachedIndex {
+    index: LineIndex,
+    last_used: Instant,
+}
+
+#[derive(Default)]
+pub struct IndexCache {
+    entries: HashMap<String, CachedIndex>,
+}
+
+impl IndexCache {
+    /// Return the index for `path`, building it on first use.
+    /// `budget_bytes` is enforced after insertion; the requested entry is never evicted.
+    pub fn get_or_build(&mut self, path: &str, budget_bytes: usize) -> Result<&LineIndex, String> {
+        if !self.entries.contains_key(path) {
+            let index = LineIndex::new(path).map_err(|e| format!("Failed to index file: {}", e))?;
+            self.entries.insert(path.to_string(), CachedIndex { index, last_used: Instant::now() });
+            self.evict_to_budget(path, budget_bytes);
+        }
+
+        let entry = self.entries.get_mut(path).expect("entry inserted above");
+        entry.last_used = Instant::now();
+        Ok(&entry.index)
+    }
+
+    /// Drop the index of a file whose contents changed
+    pub fn invalidate(&mut self, path: &str) {
+        self.entries.remove(path);
+    }
+
+    pub fn entry_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Approximate memory held by all cached indexes
+    pub fn memory_bytes(&self) -> usize {
+        self.entries.values().map(|e| e.index.memory_footprint()).sum()
+    }
+
+    fn evict_to_budget(&mut self, keep: &str, budget_bytes: usize) {
+        while self.memory_bytes() > budget_bytes {
+            let oldest = self
+                .entries
+                .iter()
+                .filter(|(path, _)| path.as_str() != keep)
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(path, _)| path.clone());
+            match oldest {
+                Some(path) => {
+                    self.entries.remove(&path);
+                }
+                None => break,
+            }
+        }
+    }
+}
@@ -0,0 +1,242 @@
+// line_edit.rs
+// Inline editing for the mmap viewer: replace a range of lines in a file.
+// Line numbers are the viewer's 0-based indexes (see LineIndex). The file is rewritten
+// atomically in its original encoding and line endings, and every edit is recorded
+// in the undo journal so it can be reverted with `undo_change`.
+
+use serde::Serialize;
+use std::path::Path;
+
+use crate::atomic_write::write_atomic;
+use crate::text_codec::{self, DecodedText, LineEnding};
+use crate::undo_journal::{self, sha256_hex, FileChange};
+
+#[derive(Serialize, Debug)]
+pub struct EditReport {
+    pub path: String,
+    pub undo_id: Option<String>,
+    pub undo_error: Option<String>, // the file was edited, but no undo entry could be saved
+    pub line_count: usize, // lines in the file after the edit, as the viewer counts them
+    pub sha256: String,
+}
+
+/// Replace `count` lines starting at `start` with `new_lines` (no line terminators needed).
+/// If `expected` is given, the current lines must equal it as the viewer shows them, so
+/// edits based on a stale view of the file are refused.
+pub fn edit_lines(
+    path: &Path,
+    start: usize,
+    count: usize,
+    new_lines: &[String],
+    expected: Option<&[String]>,
+) -> Result<EditReport, String> {
+    edit_lines_in(&undo_journal::journal_dir(), path, start, count, new_lines, expected)
+}
+
+fn edit_lines_in(
+    journal: &Path,
+    path: &Path,
+    start: usize,
+    count: usize,
+    new_lines: &[String],
+    expected: Option<&[String]>,
+) -> Result<EditReport, String> {
+    let original = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let decoded = text_codec::decode(&original)?;
+    let new_text = edit_text(&decoded, start, count, new_lines, expected)?;
+
+    let written = text_codec::encode(&new_text, decoded.encoding)?;
+    write_atomic(path, &written).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    let description = format!(
+        "Edit lines {}-{} of {}",
+        start + 1,
+        start + count.max(1),
+        path.display()
+    );
+    // The file is written by now; a journal failure is reported, not returned as an error
+    let (undo_id, undo_error) = match undo_journal::record_in(
+        journal,
+        "edit",
+        &description,
+        &[FileChange { path, original: &original, written: &written }],
+    ) {
+        Ok(entry) => (Some(entry.id), None),
+        Err(e) => (None, Some(e)),
+    };
+
+    Ok(EditReport {
+        path: path.display().to_string(),
+        undo_id,
+        undo_error,
+        line_count: split_lines(&new_text).len(),
+        sha256: sha256_hex(&written),
+    })
+}
+
+// The edit itself, on the decoded text
+fn edit_text(
+    decoded: &DecodedText,
+    start: usize,
+    count: usize,
+    new_lines: &[String],
+    expected: Option<&[String]>,
+) -> Result<String, String> {
+    let line_ending = decoded.line_ending;
+    let mut lines = split_lines(&decoded.text);
+    if start > lines.len() || count > lines.len() - start {
+        return Err(format!(
+            "Lines {}..{} are out of range (file has {} lines)",
+            start,
+            start.saturating_add(count),
+            lines.len()
+        ));
+    }
+    if let Some(expected) = expected {
+        // The viewer decodes lines the way `decode` does and trims trailing whitespace
+        let current = lines[start..start + count].iter().map(|(content, _)| content.trim_end());
+        let expected = expected.iter().enumerate().map(|(i, line)| {
+            // Clients may keep a UTF-8 BOM on the first line
+            let line = if start + i == 0 { line.trim_start_matches('\u{FEFF}') } else { line };
+            line.trim_end()
+        });
+        if expected.len() != count || !current.eq(expected) {
+            return Err("The file changed since it was loaded; reload it before editing".to_string());
+        }
+    }
+
+    // The last replaced line keeps its own terminator, so "no newline at end of file" survives
+    let mut last_terminator = match count {
+        0 => line_ending.as_str(),
+        _ => lines[start + count - 1].1,
+    };
+    if start == lines.len() && start > 0 && lines[start - 1].1.is_empty() {
+        // Appending after a last line that had no terminator: the new last line has none
+        lines[start - 1].1 = line_ending.as_str();
+        last_terminator = "";
+    }
+
+    let inserted: Vec<String> = new_lines
+        .iter()
+        .flat_map(|l| text_codec::normalize_newlines(l, LineEnding::Lf).split('\n').map(str::to_string).collect::<Vec<_>>())
+        .collect();
+    if inserted.is_empty() && count > 0 && start > 0 && start + count == lines.len() {
+        // Deleting the last lines: the line before them becomes the last one and takes
+        // over their terminator (none, for a file without a final newline)
+        lines[start - 1].1 = last_terminator;
+    }
+
+    let mut new_text = String::with_capacity(decoded.text.len());
+    for (content, terminator) in &lines[..start] {
+        new_text.push_str(content);
+        new_text.push_str(terminator);
+    }
+    for (i, content) in inserted.iter().enumerate() {
+        new_text.push_str(content);
+        new_text.push_str(if i + 1 == inserted.len() { last_terminator } else { line_ending.as_str() });
+    }
+    for (content, terminator) in &lines[start + count..] {
+        new_text.push_str(content);
+        new_text.push_str(terminator);
+    }
+    Ok(new_text)
+}
+
+// Split into (content, terminator) pairs, numbered the way LineIndex numbers lines:
+// text after the last '\n' (possibly empty) is a line of its own.
+fn split_lines(text: &str) -> Vec<(&str, &str)> {
+    let mut lines: Vec<(&str, &str)> = text
+        .split_inclusive('\n')
+        .map(|raw| {
+            let content_len = raw.trim_end_matches(['\r', '\n']).len();
+            raw.split_at(content_len)
+        })
+        .collect();
+    if text.is_empty() || text.ends_with('\n') {
+        lines.push(("", ""));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Run an edit on file bytes and return the bytes that would be written
+    fn edit(bytes: &[u8], start: usize, count: usize, new_lines: &[&str], expected: Option<&[&str]>) -> Result<Vec<u8>, String> {
+        let decoded = text_codec::decode(bytes)?;
+        let new_lines: Vec<String> = new_lines.iter().map(|l| l.to_string()).collect();
+        let expected: Option<Vec<String>> = expected.map(|e| e.iter().map(|l| l.to_string()).collect());
+        let text = edit_text(&decoded, start, count, &new_lines, expected.as_deref())?;
+        text_codec::encode(&text, decoded.encoding)
+    }
+
+    #[test]
+    fn trailing_whitespace_matches_the_trimmed_viewer_line() {
+        let written = edit(b"keep  \nold\t \nend\n", 1, 1, &["new"], Some(&["old"])).unwrap();
+        assert_eq!(written, b"keep  \nnew\nend\n");
+    }
+
+    #[test]
+    fn crlf_files_keep_crlf() {
+        let written = edit(b"a\r\nb\r\nc\r\n", 1, 1, &["x\ny"], Some(&["b"])).unwrap();
+        assert_eq!(written, b"a\r\nx\r\ny\r\nc\r\n");
+    }
+
+    #[test]
+    fn windows_1252_and_utf16_lines_compare_as_the_viewer_decodes_them() {
+        let written = edit(b"caf\xe9 \nx\n", 0, 1, &["th\u{e9}"], Some(&["caf\u{e9}"])).unwrap();
+        assert_eq!(written, b"th\xe9\nx\n");
+        let written = edit(b"\xFF\xFEa\0\n\0b\0", 1, 1, &["c"], Some(&["b"])).unwrap();
+        assert_eq!(written, b"\xFF\xFEa\0\n\0c\0");
+    }
+
+    #[test]
+    fn utf8_bom_is_ignored_in_the_first_expected_line() {
+        for first in ["first", "\u{FEFF}first"] {
+            let written = edit(b"\xEF\xBB\xBFfirst\nsecond\n", 0, 1, &["1st"], Some(&[first])).unwrap();
+            assert_eq!(written, b"\xEF\xBB\xBF1st\nsecond\n");
+        }
+    }
+
+    #[test]
+    fn stale_lines_are_refused() {
+        let err = edit(b"a\nb\n", 1, 1, &["x"], Some(&["B"])).unwrap_err();
+        assert!(err.contains("changed since it was loaded"));
+        assert!(edit(b"a\nb\n", 0, 2, &["x"], Some(&["a"])).is_err());
+    }
+
+    #[test]
+    fn deleting_an_unterminated_last_line_leaves_no_newline() {
+        assert_eq!(edit(b"a\nb", 1, 1, &[], Some(&["b"])).unwrap(), b"a");
+        assert_eq!(edit(b"a\nb\n", 1, 1, &[], Some(&["b"])).unwrap(), b"a\n");
+        assert_eq!(edit(b"a\nb\nc", 1, 1, &[], None).unwrap(), b"a\nc");
+    }
+
+    #[test]
+    fn appending_after_an_unterminated_last_line() {
+        assert_eq!(edit(b"a\nb", 2, 0, &["c"], None).unwrap(), b"a\nb\nc");
+    }
+
+    #[test]
+    fn a_journal_failure_still_reports_the_edit() {
+        let dir = std::env::temp_dir().join(format!("search-tool-edit-journal-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.txt");
+        std::fs::write(&path, "a\nb\n").unwrap();
+        // A file where the journal directory should be, so the journal cannot be written
+        let journal = dir.join("journal");
+        std::fs::write(&journal, "").unwrap();
+
+        let report = edit_lines_in(&journal, &path, 1, 1, &["x".to_string()], Some(&["b".to_string()])).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\nx\n");
+        assert_eq!(report.line_count, 3);
+        assert_eq!(report.undo_id, None);
+        assert!(report.undo_error.unwrap().contains("journal"));
+
+        // Huge ranges are refused, not overflowed
+        assert!(edit_lines_in(&journal, &path, 1, usize::MAX, &[], None).unwrap_err().contains("out of range"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
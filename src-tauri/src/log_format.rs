@@ -0,0 +1,470 @@
+// log_format.rs
+// Log mode: reads the timestamp and level of matched lines, so log searches can be
+// filtered by time window and level and sorted across files chronologically.
+// Recognised, in this order:
+// - custom regexes from the settings (`log_formats`), with named groups
+//   `timestamp` and/or `level`;
+// - JSON lines: {"time": ..., "level": ...} and the usual variants of those keys;
+// - ISO 8601 timestamps near the start of the line ("2025-01-31T14:00:00.123Z",
+//   "[2025-01-31 14:00:00,123]");
+// - syslog ("Jan 31 14:00:00 host app[42]: ..."), which has no year: the current one
+//   is assumed, or last year's if that would put the line in the future.
+// Times without a zone are local time, like the metadata filters. Levels are taken
+// from the first level word near the start of the line unless a format names one.
+// Lines without a timestamp (stack traces, wrapped messages) belong to the entry above
+// them and take its timestamp and level; see EntryHeads.
+
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, TimeZone};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::memmap_line_reader::LineIndex;
+use crate::metadata_filter;
+use crate::settings::CustomLogFormat;
+
+// How far into the line the timestamp may start ("[main] 2025-01-31 ...")
+const MAX_TIMESTAMP_START: usize = 32;
+
+// Lines looked at above a continuation line for the start of its entry
+const MAX_ENTRY_LINES: usize = 1000;
+
+// Lines read at a time while looking back
+const LOOKBACK_CHUNK: usize = 64;
+
+// Level words further into the line are part of the message ("... retrying after error")
+const LEVEL_SEARCH_LEN: usize = 160;
+
+static ISO_TIMESTAMP: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(\d{4}-\d{2}-\d{2})[T ](\d{2}:\d{2}:\d{2})(?:[.,](\d{1,9}))?\s?(Z|[+-]\d{2}:?\d{2})?").expect("valid regex")
+});
+
+static SYSLOG_TIMESTAMP: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^([A-Z][a-z]{2}) +(\d{1,2}) (\d{2}:\d{2}:\d{2})\b").expect("valid regex"));
+
+static LEVEL_WORD: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(trace|verbose|debug|dbg|info|information|notice|warn|warning|error|err|severe|critical|crit|fatal|panic|emerg|alert)\b")
+        .expect("valid regex")
+});
+
+// Keys tried in JSON lines, most common first
+const JSON_TIME_KEYS: &[&str] = &["timestamp", "time", "@timestamp", "ts", "datetime", "date", "t"];
+const JSON_LEVEL_KEYS: &[&str] = &["level", "severity", "lvl", "log.level", "loglevel", "levelname", "@l"];
+
+/// Severity of a log line, lowest first
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Severity {
+    /// "WARNING" -> Warn, "err" -> Error; None for words that are not levels
+    pub fn from_word(word: &str) -> Option<Self> {
+        Some(match word.to_ascii_lowercase().as_str() {
+            "trace" | "verbose" | "finest" | "finer" => Severity::Trace,
+            "debug" | "dbg" | "fine" => Severity::Debug,
+            "info" | "information" | "notice" => Severity::Info,
+            "warn" | "warning" => Severity::Warn,
+            "error" | "err" | "severe" => Severity::Error,
+            "fatal" | "critical" | "crit" | "panic" | "emerg" | "alert" => Severity::Fatal,
+            _ => return None,
+        })
+    }
+
+    /// Lowercase name, as serialized
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Trace => "trace",
+            Severity::Debug => "debug",
+            Severity::Info => "info",
+            Severity::Warn => "warn",
+            Severity::Error => "error",
+            Severity::Fatal => "fatal",
+        }
+    }
+
+    // Numeric levels of bunyan/pino (10 trace ... 60 fatal)
+    fn from_number(level: i64) -> Option<Self> {
+        Some(match level {
+            ..=10 => Severity::Trace,
+            11..=20 => Severity::Debug,
+            21..=30 => Severity::Info,
+            31..=40 => Severity::Warn,
+            41..=50 => Severity::Error,
+            _ => Severity::Fatal,
+        })
+    }
+}
+
+/// What log mode found on a line
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LogFields {
+    pub timestamp: Option<i64>, // unix time in milliseconds
+    pub level: Option<Severity>,
+}
+
+/// Log mode options as the frontend sends them; sending them at all turns log mode on
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFilter {
+    #[serde(default)]
+    pub from: Option<String>, // same forms as the metadata filters: "2h", "2025-01-31 14:00"
+    #[serde(default)]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub levels: Vec<Severity>, // empty = any level
+}
+
+/// A `LogFilter` with its times resolved and the line formats compiled
+pub struct LogMatcher {
+    parser: LogParser,
+    from: Option<i64>,
+    to: Option<i64>,
+    levels: Vec<Severity>,
+}
+
+impl LogFilter {
+    /// Check the time window without compiling the formats (they are checked with the settings)
+    pub fn validate(&self) -> Result<(), String> {
+        self.window().map(|_| ())
+    }
+
+    pub fn compile(&self, formats: &[CustomLogFormat]) -> Result<LogMatcher, String> {
+        let (from, to) = self.window()?;
+        Ok(LogMatcher {
+            parser: LogParser::new(formats)?,
+            from,
+            to,
+            levels: self.levels.clone(),
+        })
+    }
+
+    fn window(&self) -> Result<(Option<i64>, Option<i64>), String> {
+        let now = SystemTime::now();
+        let time = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(|v| metadata_filter::parse_time(v, now).map(unix_millis))
+                .transpose()
+        };
+        let (from, to) = (time(&self.from)?, time(&self.to)?);
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return Err("Log window start is later than its end".to_string());
+            }
+        }
+        Ok((from, to))
+    }
+}
+
+impl LogMatcher {
+    /// Fields of line `line` (1-based) of `path`, whose text is `text`; None if the filter
+    /// leaves it out. A line without a timestamp takes its entry's (see EntryHeads). Lines
+    /// still without one are left out only when a time window is set, lines without a
+    /// level only when levels are.
+    pub fn check(&self, heads: &mut EntryHeads, path: &Path, line: usize, text: &str) -> Option<LogFields> {
+        let fields = heads.resolve(&self.parser, path, line, self.parser.parse(text));
+        if self.from.is_some() || self.to.is_some() {
+            let timestamp = fields.timestamp?;
+            if self.from.is_some_and(|from| timestamp < from) || self.to.is_some_and(|to| timestamp > to) {
+                return None;
+            }
+        }
+        if !self.levels.is_empty() && !self.levels.contains(&fields.level?) {
+            return None;
+        }
+        Some(fields)
+    }
+}
+
+/// Finds the entry a continuation line belongs to, by reading back through the file to
+/// the nearest line with a timestamp. ripgrep reports matches file by file in line order,
+/// so the previous answer usually ends the walk after a few lines.
+#[derive(Default)]
+pub struct EntryHeads {
+    path: Option<PathBuf>,
+    index: Option<LineIndex>,
+    known: Option<(usize, LogFields)>, // a 0-based line of `path` and its entry's fields
+}
+
+impl EntryHeads {
+    /// The fields for line `line` (1-based) of `path`, given those parsed from the line
+    /// itself: its own if it has a timestamp, otherwise its entry's when one is found
+    pub fn resolve(&mut self, parser: &LogParser, path: &Path, line: usize, own: LogFields) -> LogFields {
+        if self.path.as_deref() != Some(path) {
+            *self = Self { path: Some(path.to_path_buf()), ..Self::default() };
+        }
+        let line = line.saturating_sub(1);
+        let fields = match own.timestamp {
+            Some(_) => Some(own),
+            None => self.find_head(parser, line),
+        };
+        match fields {
+            Some(fields) => {
+                self.known = Some((line, fields));
+                fields
+            }
+            None => own,
+        }
+    }
+
+    fn find_head(&mut self, parser: &LogParser, line: usize) -> Option<LogFields> {
+        if self.index.is_none() {
+            self.index = LineIndex::new(self.path.as_ref()?).ok();
+        }
+        let index = self.index.as_ref()?;
+        let first = line.saturating_sub(MAX_ENTRY_LINES);
+        let mut end = line;
+        while end > first {
+            let start = end.saturating_sub(LOOKBACK_CHUNK).max(first);
+            for (i, text) in index.get_lines(start, end - start).iter().enumerate().rev() {
+                match self.known {
+                    Some((known, fields)) if known == start + i => return Some(fields),
+                    _ => {}
+                }
+                let fields = parser.parse(text);
+                if fields.timestamp.is_some() {
+                    return Some(fields);
+                }
+            }
+            end = start;
+        }
+        None
+    }
+}
+
+// -----------------------------------------------------------
+// Line formats
+// -----------------------------------------------------------
+
+struct CompiledFormat {
+    regex: Regex,
+    timestamp_format: Option<String>,
+}
+
+pub struct LogParser {
+    custom: Vec<CompiledFormat>,
+}
+
+impl LogParser {
+    pub fn new(formats: &[CustomLogFormat]) -> Result<Self, String> {
+        let custom = formats.iter().map(compile_format).collect::<Result<_, _>>()?;
+        Ok(Self { custom })
+    }
+
+    pub fn parse(&self, line: &str) -> LogFields {
+        for format in &self.custom {
+            if let Some(caps) = format.regex.captures(line) {
+                let timestamp = caps
+                    .name("timestamp")
+                    .and_then(|t| parse_timestamp(t.as_str(), format.timestamp_format.as_deref()));
+                let level = caps.name("level").and_then(|l| Severity::from_word(l.as_str().trim()));
+                return LogFields { timestamp, level: level.or_else(|| level_word(line)) };
+            }
+        }
+        if line.trim_start().starts_with('{') {
+            if let Some(fields) = parse_json(line) {
+                return fields;
+            }
+        }
+        LogFields {
+            timestamp: iso_timestamp(line).or_else(|| syslog_timestamp(line)),
+            level: level_word(line),
+        }
+    }
+}
+
+/// Compile a custom format from the settings; it must name a `timestamp` or `level` group
+pub fn validate_format(format: &CustomLogFormat) -> Result<(), String> {
+    compile_format(format).map(|_| ())
+}
+
+fn compile_format(format: &CustomLogFormat) -> Result<CompiledFormat, String> {
+    let regex = Regex::new(&format.pattern).map_err(|e| format!("Log format '{}' has an invalid pattern: {}", format.name, e))?;
+    let names: Vec<&str> = regex.capture_names().flatten().collect();
+    if !names.contains(&"timestamp") && !names.contains(&"level") {
+        return Err(format!(
+            "Log format '{}' needs a (?P<timestamp>...) or (?P<level>...) group",
+            format.name
+        ));
+    }
+    Ok(CompiledFormat {
+        regex,
+        timestamp_format: format.timestamp_format.clone().filter(|f| !f.trim().is_empty()),
+    })
+}
+
+fn parse_json(line: &str) -> Option<LogFields> {
+    let Value::Object(object) = serde_json::from_str::<Value>(line).ok()? else {
+        return None;
+    };
+    let timestamp = JSON_TIME_KEYS.iter().filter_map(|key| object.get(*key)).find_map(|value| match value {
+        Value::String(text) => parse_timestamp(text, None),
+        // Epoch seconds or milliseconds; anything after 1973 in milliseconds is > 1e11
+        Value::Number(n) => n.as_f64().map(|n| if n > 1e11 { n as i64 } else { (n * 1000.0) as i64 }),
+        _ => None,
+    });
+    let level = JSON_LEVEL_KEYS.iter().filter_map(|key| object.get(*key)).find_map(|value| match value {
+        Value::String(text) => Severity::from_word(text.trim()),
+        Value::Number(n) => n.as_i64().and_then(Severity::from_number),
+        _ => None,
+    });
+    Some(LogFields { timestamp, level })
+}
+
+// A timestamp from a custom format or a JSON field: `format` (strftime) if given,
+// otherwise anything ISO-like
+fn parse_timestamp(text: &str, format: Option<&str>) -> Option<i64> {
+    let text = text.trim();
+    match format {
+        Some(format) => DateTime::parse_from_str(text, format)
+            .map(|t| t.timestamp_millis())
+            .ok()
+            .or_else(|| NaiveDateTime::parse_from_str(text, format).ok().and_then(local_millis)),
+        None => iso_timestamp(text),
+    }
+}
+
+fn iso_timestamp(line: &str) -> Option<i64> {
+    let caps = ISO_TIMESTAMP.captures(line)?;
+    if caps.get(0)?.start() > MAX_TIMESTAMP_START {
+        return None;
+    }
+    let fraction = caps.get(3).map_or("0", |f| f.as_str());
+    let millis: i64 = format!("{:0<3}", &fraction[..fraction.len().min(3)]).parse().ok()?;
+    let naive = NaiveDateTime::parse_from_str(&format!("{} {}", &caps[1], &caps[2]), "%Y-%m-%d %H:%M:%S").ok()?;
+    let seconds = match caps.get(4).map(|z| z.as_str()) {
+        Some("Z") => naive.and_utc().timestamp(),
+        Some(offset) => {
+            let offset = offset.replace(':', "");
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            let hours: i64 = offset[1..3].parse().ok()?;
+            let minutes: i64 = offset[3..5].parse().ok()?;
+            naive.and_utc().timestamp() - sign * (hours * 3600 + minutes * 60)
+        }
+        None => local_millis(naive)? / 1000,
+    };
+    Some(seconds * 1000 + millis)
+}
+
+fn syslog_timestamp(line: &str) -> Option<i64> {
+    let caps = SYSLOG_TIMESTAMP.captures(line)?;
+    let now = Local::now();
+    let at_year = |year: i32| {
+        let text = format!("{} {} {} {}", &caps[1], &caps[2], year, &caps[3]);
+        NaiveDateTime::parse_from_str(&text, "%b %d %Y %H:%M:%S").ok().and_then(local_millis)
+    };
+    // The latest year that puts the line in the past, with a day of slack for clock
+    // differences between machines; going back up to 4 years finds a leap year for "Feb 29"
+    let latest = now.timestamp_millis() + 24 * 60 * 60 * 1000;
+    (0..=4).filter_map(|back| at_year(now.year() - back)).find(|&t| t <= latest)
+}
+
+fn level_word(line: &str) -> Option<Severity> {
+    let mut end = line.len().min(LEVEL_SEARCH_LEN);
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    LEVEL_WORD.find(&line[..end]).and_then(|m| Severity::from_word(m.as_str()))
+}
+
+fn local_millis(naive: NaiveDateTime) -> Option<i64> {
+    Local.from_local_datetime(&naive).earliest().map(|t| t.timestamp_millis())
+}
+
+fn unix_millis(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_millis() as i64,
+        Err(e) => -(e.duration().as_millis() as i64),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Timelike;
+
+    fn matcher(from: Option<&str>, levels: &[Severity]) -> LogMatcher {
+        let filter = LogFilter { from: from.map(str::to_string), to: None, levels: levels.to_vec() };
+        filter.compile(&[]).expect("valid filter")
+    }
+
+    // A log file in the temp directory, removed when dropped
+    struct TempLog(PathBuf);
+
+    impl TempLog {
+        fn new(name: &str, text: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("search-tool-{}-{}.log", name, std::process::id()));
+            std::fs::write(&path, text).expect("write temp log");
+            Self(path)
+        }
+    }
+
+    impl Drop for TempLog {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    const TRACE: &str = "2025-01-31T14:00:00Z INFO started\n\
+                         2025-01-31T14:00:05Z ERROR request failed\n\
+                         java.lang.IllegalStateException: boom\n\
+                         \tat com.example.Handler.run(Handler.java:42)\n\
+                         2025-01-31T14:00:09Z INFO next\n";
+
+    #[test]
+    fn continuation_lines_take_the_timestamp_and_level_of_their_entry() {
+        let log = TempLog::new("continuation", TRACE);
+        let m = matcher(None, &[]);
+        let mut heads = EntryHeads::default();
+        let at = |heads: &mut EntryHeads, line: usize| {
+            let text = TRACE.lines().nth(line - 1).unwrap();
+            m.check(heads, &log.0, line, text).expect("passes")
+        };
+        let head = at(&mut heads, 2);
+        assert_eq!(head.level, Some(Severity::Error));
+        assert_eq!(at(&mut heads, 3), head);
+        assert_eq!(at(&mut heads, 4), head);
+        assert_eq!(at(&mut heads, 5).level, Some(Severity::Info));
+
+        // Without an earlier match to start from, the walk back finds the head itself
+        let mut fresh = EntryHeads::default();
+        assert_eq!(at(&mut fresh, 4), head);
+    }
+
+    #[test]
+    fn continuation_lines_pass_the_time_window_of_their_entry() {
+        let log = TempLog::new("window", TRACE);
+        let m = matcher(Some("2025-01-31T14:00:01Z"), &[Severity::Error]);
+        let mut heads = EntryHeads::default();
+        assert!(m.check(&mut heads, &log.0, 1, TRACE.lines().next().unwrap()).is_none());
+        assert!(m.check(&mut heads, &log.0, 4, TRACE.lines().nth(3).unwrap()).is_some());
+    }
+
+    #[test]
+    fn lines_without_an_entry_are_left_out_by_a_time_window() {
+        let log = TempLog::new("orphan", "no timestamp here\n");
+        let m = matcher(Some("2025-01-31"), &[]);
+        assert!(m.check(&mut EntryHeads::default(), &log.0, 1, "no timestamp here").is_none());
+    }
+
+    #[test]
+    fn syslog_feb_29_falls_back_to_a_leap_year() {
+        let timestamp = syslog_timestamp("Feb 29 10:00:00 host app: hello").expect("parsed");
+        let time = Local.timestamp_millis_opt(timestamp).unwrap();
+        assert_eq!((time.month(), time.day(), time.hour()), (2, 29, 10));
+        assert!(timestamp <= Local::now().timestamp_millis() + 24 * 60 * 60 * 1000);
+    }
+}
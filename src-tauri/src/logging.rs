@@ -0,0 +1,443 @@
+// logging.rs
+// Leveled, structured logging for the backend.
+// Records carry a message plus key/value fields (search_id, duration_ms, ...) and are
+// written to `search_tool.log` as one line each. The file is kept open, rotated by size
+// and daily, and old rotated files are deleted by count and age (settings.logging).
+// The most recent records are also kept in memory for `get_recent_logs`.
+//
+// With `logging.redact` on, the values of fields that may hold user data (queries,
+// paths, ...) are replaced by a short hash, so records can still be correlated.
+// Keep such values out of the message text and pass them as fields instead.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use crate::settings::{self, LogLevel, LogSettings};
+
+pub const LOG_FILE: &str = "search_tool.log";
+
+// Records kept in memory for get_recent_logs
+const RECENT_CAPACITY: usize = 2000;
+
+// Start a new file at least this often, whatever its size
+const MAX_FILE_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Field names whose values are redacted when logging.redact is on
+// ("error" too: error messages quote paths and queries)
+const SENSITIVE_FIELDS: &[&str] = &["query", "path", "root", "file_filter", "replacement", "target", "stderr", "error"];
+
+#[derive(Serialize, Clone, Debug)]
+pub struct LogRecord {
+    pub timestamp: String,
+    pub level: LogLevel,
+    pub message: String,
+    pub fields: BTreeMap<String, String>,
+}
+
+struct Logger {
+    dir: Option<PathBuf>,
+    file: Option<File>,
+    size: u64,
+    opened: SystemTime, // when the live file was started
+    recent: VecDeque<LogRecord>,
+}
+
+static LOGGER: Lazy<Mutex<Logger>> = Lazy::new(|| Mutex::new(Logger::new()));
+
+/// Start writing to the first usable log directory: `logs/` next to the executable,
+/// then the per-user data dir, then the temp dir. Directories that could not be used
+/// are logged as warnings once one works. Returns the chosen directory.
+pub fn init_default() -> Result<PathBuf, String> {
+    let mut candidates = Vec::new();
+    if let Some(exe_dir) = std::env::current_exe().ok().and_then(|p| p.parent().map(Path::to_path_buf)) {
+        candidates.push(exe_dir.join("logs"));
+    }
+    if let Some(data_dir) = dirs::data_local_dir() {
+        candidates.push(data_dir.join(settings::APP_DIR_NAME).join("logs"));
+    }
+    candidates.push(std::env::temp_dir().join(settings::APP_DIR_NAME).join("logs"));
+
+    let (dir, failures) = LOGGER.lock().unwrap().start_first(&candidates, &settings::logging())?;
+    for failure in &failures {
+        warn("Log directory not usable", &[("error", failure)]);
+    }
+    Ok(dir)
+}
+
+/// Start writing to `dir`. Records logged before this are only kept in memory.
+pub fn init(dir: &Path) -> Result<(), String> {
+    LOGGER.lock().unwrap().start(dir, &settings::logging())
+}
+
+/// Directory of the log file, once `init` succeeded
+pub fn log_dir() -> Option<PathBuf> {
+    LOGGER.lock().unwrap().dir.clone()
+}
+
+pub fn error(message: &str, fields: &[(&str, &dyn Display)]) {
+    log(LogLevel::Error, message, fields);
+}
+
+pub fn warn(message: &str, fields: &[(&str, &dyn Display)]) {
+    log(LogLevel::Warn, message, fields);
+}
+
+pub fn info(message: &str, fields: &[(&str, &dyn Display)]) {
+    log(LogLevel::Info, message, fields);
+}
+
+pub fn debug(message: &str, fields: &[(&str, &dyn Display)]) {
+    log(LogLevel::Debug, message, fields);
+}
+
+pub fn log(level: LogLevel, message: &str, fields: &[(&str, &dyn Display)]) {
+    let config = settings::logging();
+    if level > config.level {
+        return;
+    }
+    let record = LogRecord {
+        timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+        level,
+        message: message.to_string(),
+        fields: fields
+            .iter()
+            .map(|(key, value)| {
+                let value = value.to_string();
+                let value = if config.redact && is_sensitive(key) { redact(&value) } else { value };
+                (key.to_string(), value)
+            })
+            .collect(),
+    };
+
+    let mut logger = LOGGER.lock().unwrap();
+    logger.write(&format_line(&record), &config);
+    logger.remember(record);
+}
+
+/// The last `limit` records at `level` or more severe, oldest first
+pub fn recent(limit: usize, level: LogLevel) -> Vec<LogRecord> {
+    LOGGER.lock().unwrap().recent(limit, level)
+}
+
+/// Whether a field may hold user data (queries, paths, ...)
+pub fn is_sensitive(key: &str) -> bool {
+    SENSITIVE_FIELDS.contains(&key)
+}
+
+/// Stand-in for a sensitive value: equal inputs give equal outputs
+pub fn redact(value: &str) -> String {
+    let hash = format!("{:x}", Sha256::digest(value.as_bytes()));
+    format!("<redacted:{}>", &hash[..8])
+}
+
+// `2025-01-01 12:00:00.000 INFO  Search finished search_id=search-1 duration_ms=12.5`
+fn format_line(record: &LogRecord) -> String {
+    let level = match record.level {
+        LogLevel::Error => "ERROR",
+        LogLevel::Warn => "WARN ",
+        LogLevel::Info => "INFO ",
+        LogLevel::Debug => "DEBUG",
+    };
+    let mut line = format!("{} {} {}", record.timestamp, level, record.message);
+    for (key, value) in &record.fields {
+        if value.is_empty() || value.contains([' ', '"', '=', '\n', '\r', '\t']) {
+            line.push_str(&format!(" {}={:?}", key, value));
+        } else {
+            line.push_str(&format!(" {}={}", key, value));
+        }
+    }
+    line.push('\n');
+    line
+}
+
+impl Logger {
+    fn new() -> Self {
+        Self {
+            dir: None,
+            file: None,
+            size: 0,
+            opened: SystemTime::now(),
+            recent: VecDeque::with_capacity(RECENT_CAPACITY),
+        }
+    }
+
+    fn start(&mut self, dir: &Path, config: &LogSettings) -> Result<(), String> {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create log directory {}: {}", dir.display(), e))?;
+        let previous = self.dir.replace(dir.to_path_buf());
+        if let Err(e) = self.open() {
+            self.dir = previous;
+            return Err(e);
+        }
+        prune(dir, config);
+        Ok(())
+    }
+
+    // Start in the first of `candidates` that works; also returns why the ones before it did not
+    fn start_first(&mut self, candidates: &[PathBuf], config: &LogSettings) -> Result<(PathBuf, Vec<String>), String> {
+        let mut failures = Vec::new();
+        for dir in candidates {
+            match self.start(dir, config) {
+                Ok(()) => return Ok((dir.clone(), failures)),
+                Err(e) => failures.push(e),
+            }
+        }
+        Err(format!("No usable log directory: {}", failures.join("; ")))
+    }
+
+    fn open(&mut self) -> Result<(), String> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        let path = dir.join(LOG_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let meta = file.metadata().ok();
+        self.size = meta.as_ref().map(|m| m.len()).unwrap_or(0);
+        self.opened = meta
+            .and_then(|m| m.created().ok())
+            .unwrap_or_else(SystemTime::now);
+        self.file = Some(file);
+        Ok(())
+    }
+
+    fn write(&mut self, line: &str, config: &LogSettings) {
+        let max_size = config.max_file_mb * 1024 * 1024;
+        let too_old = self.opened.elapsed().map(|age| age > MAX_FILE_AGE).unwrap_or(false);
+        if self.size > 0 && (self.size + line.len() as u64 > max_size || too_old) {
+            self.rotate(config);
+        }
+        if let Some(file) = &mut self.file {
+            if file.write_all(line.as_bytes()).is_ok() {
+                self.size += line.len() as u64;
+            }
+        }
+    }
+
+    // search_tool.log -> search_tool.1.log -> search_tool.2.log ...
+    fn rotate(&mut self, config: &LogSettings) {
+        let Some(dir) = self.dir.clone() else {
+            return;
+        };
+        self.file = None; // close before renaming (required on Windows)
+        let max_files = config.max_files;
+        let _ = std::fs::remove_file(rotated_path(&dir, max_files.max(1)));
+        for n in (1..max_files.max(1)).rev() {
+            let _ = std::fs::rename(rotated_path(&dir, n), rotated_path(&dir, n + 1));
+        }
+        if max_files == 0 {
+            let _ = std::fs::remove_file(dir.join(LOG_FILE));
+        } else {
+            let _ = std::fs::rename(dir.join(LOG_FILE), rotated_path(&dir, 1));
+        }
+        // A new file has no creation time yet on some systems; count its age from now
+        let _ = self.open();
+        self.opened = SystemTime::now();
+        prune(&dir, config);
+    }
+
+    fn remember(&mut self, record: LogRecord) {
+        if self.recent.len() == RECENT_CAPACITY {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(record);
+    }
+
+    fn recent(&self, limit: usize, level: LogLevel) -> Vec<LogRecord> {
+        let mut records: Vec<LogRecord> = self.recent.iter().rev().filter(|r| r.level <= level).take(limit).cloned().collect();
+        records.reverse();
+        records
+    }
+}
+
+fn rotated_path(dir: &Path, n: usize) -> PathBuf {
+    dir.join(format!("search_tool.{}.log", n))
+}
+
+// Delete rotated files beyond max_files or older than max_age_days
+fn prune(dir: &Path, config: &LogSettings) {
+    let max_age = Duration::from_secs(config.max_age_days * 24 * 60 * 60);
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(n) = name
+            .strip_prefix("search_tool.")
+            .and_then(|rest| rest.strip_suffix(".log"))
+            .and_then(|n| n.parse::<usize>().ok())
+        else {
+            continue;
+        };
+        let expired = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.elapsed().ok())
+            .is_some_and(|age| age > max_age);
+        if n > config.max_files || expired {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A log directory under the system temp dir, removed on drop
+    struct TempLogDir(PathBuf);
+
+    impl TempLogDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("search-tool-logging-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            Self(dir)
+        }
+
+        fn names(&self) -> Vec<String> {
+            let mut names: Vec<String> = std::fs::read_dir(&self.0)
+                .unwrap()
+                .flatten()
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .collect();
+            names.sort();
+            names
+        }
+    }
+
+    impl Drop for TempLogDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn record(level: LogLevel, message: &str) -> LogRecord {
+        LogRecord { timestamp: "2025-01-01 12:00:00.000".to_string(), level, message: message.to_string(), fields: BTreeMap::new() }
+    }
+
+    fn config(max_files: usize) -> LogSettings {
+        LogSettings { max_file_mb: 1, max_files, ..LogSettings::default() }
+    }
+
+    #[test]
+    fn the_live_file_rotates_by_size() {
+        let temp = TempLogDir::new("size");
+        let mut logger = Logger::new();
+        logger.start(&temp.0, &config(2)).unwrap();
+
+        let line = format!("{}\n", "x".repeat(600 * 1024));
+        for _ in 0..4 {
+            logger.write(&line, &config(2));
+        }
+        // Each line overflows the 1 MB file started by the one before; the oldest is dropped
+        assert_eq!(temp.names(), ["search_tool.1.log", "search_tool.2.log", "search_tool.log"]);
+        assert_eq!(std::fs::metadata(temp.0.join(LOG_FILE)).unwrap().len(), line.len() as u64);
+    }
+
+    #[test]
+    fn the_live_file_rotates_daily() {
+        let temp = TempLogDir::new("age");
+        let mut logger = Logger::new();
+        logger.start(&temp.0, &config(5)).unwrap();
+        logger.write("first\n", &config(5));
+        logger.write("second\n", &config(5));
+        assert_eq!(temp.names(), ["search_tool.log"]);
+
+        logger.opened = SystemTime::now() - MAX_FILE_AGE - Duration::from_secs(60);
+        logger.write("a day later\n", &config(5));
+        assert_eq!(temp.names(), ["search_tool.1.log", "search_tool.log"]);
+        assert_eq!(std::fs::read_to_string(temp.0.join(LOG_FILE)).unwrap(), "a day later\n");
+        assert_eq!(std::fs::read_to_string(temp.0.join("search_tool.1.log")).unwrap(), "first\nsecond\n");
+    }
+
+    #[test]
+    fn rotated_files_are_pruned_by_count_and_age() {
+        let temp = TempLogDir::new("prune");
+        std::fs::create_dir_all(&temp.0).unwrap();
+        for name in ["search_tool.1.log", "search_tool.2.log", "search_tool.3.log", "search_tool.log", "other.log"] {
+            std::fs::write(temp.0.join(name), "x").unwrap();
+        }
+        let month_ago = SystemTime::now() - Duration::from_secs(30 * 24 * 60 * 60);
+        File::options().write(true).open(temp.0.join("search_tool.1.log")).unwrap().set_modified(month_ago).unwrap();
+
+        prune(&temp.0, &LogSettings { max_files: 2, max_age_days: 14, ..LogSettings::default() });
+        // 3 is past max_files, 1 is too old; the live file and others are never pruned
+        assert_eq!(temp.names(), ["other.log", "search_tool.2.log", "search_tool.log"]);
+    }
+
+    #[test]
+    fn lines_quote_values_that_need_it() {
+        let mut finished = record(LogLevel::Warn, "Search finished");
+        for (key, value) in [("search_id", "search-1"), ("query", "two words"), ("empty", ""), ("path", "a=b\n")] {
+            finished.fields.insert(key.to_string(), value.to_string());
+        }
+        assert_eq!(
+            format_line(&finished),
+            "2025-01-01 12:00:00.000 WARN  Search finished empty=\"\" path=\"a=b\\n\" query=\"two words\" search_id=search-1\n"
+        );
+        assert_eq!(format_line(&record(LogLevel::Error, "x")), "2025-01-01 12:00:00.000 ERROR x\n");
+    }
+
+    #[test]
+    fn sensitive_fields_are_redacted_consistently() {
+        for key in SENSITIVE_FIELDS {
+            assert!(is_sensitive(key), "{}", key);
+        }
+        for key in ["search_id", "duration_ms", "status", "Query"] {
+            assert!(!is_sensitive(key), "{}", key);
+        }
+        let hidden = redact("/home/jo/secret");
+        assert!(hidden.starts_with("<redacted:") && hidden.len() == "<redacted:>".len() + 8, "{}", hidden);
+        assert_eq!(hidden, redact("/home/jo/secret"));
+        assert_ne!(hidden, redact("/home/jo/secret2"));
+    }
+
+    #[test]
+    fn recent_records_are_capped_and_filtered_by_level() {
+        let mut logger = Logger::new();
+        for n in 0..RECENT_CAPACITY + 5 {
+            let level = if n % 10 == 0 { LogLevel::Warn } else { LogLevel::Debug };
+            logger.remember(record(level, &n.to_string()));
+        }
+        let all = logger.recent(usize::MAX, LogLevel::Debug);
+        assert_eq!(all.len(), RECENT_CAPACITY);
+        assert_eq!(all[0].message, "5"); // the oldest five were dropped
+        assert_eq!(all[RECENT_CAPACITY - 1].message, (RECENT_CAPACITY + 4).to_string());
+
+        let warnings = logger.recent(3, LogLevel::Warn);
+        let messages: Vec<&str> = warnings.iter().map(|r| r.message.as_str()).collect();
+        assert_eq!(messages, ["1980", "1990", "2000"]); // the last three, oldest first
+        assert!(logger.recent(10, LogLevel::Error).is_empty());
+    }
+
+    #[test]
+    fn the_first_usable_directory_is_used() {
+        let temp = TempLogDir::new("fallback");
+        std::fs::create_dir_all(&temp.0).unwrap();
+        // A directory cannot be created under a file
+        std::fs::write(temp.0.join("file"), "x").unwrap();
+        let candidates = [temp.0.join("file").join("logs"), temp.0.join("second"), temp.0.join("third")];
+
+        let mut logger = Logger::new();
+        let (dir, failures) = logger.start_first(&candidates, &config(5)).unwrap();
+        assert_eq!(dir, temp.0.join("second"));
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].starts_with("Failed to create log directory"), "{}", failures[0]);
+        assert!(temp.0.join("second").join(LOG_FILE).exists());
+        assert!(!temp.0.join("third").exists());
+
+        let err = Logger::new().start_first(&candidates[..1], &config(5)).unwrap_err();
+        assert!(err.starts_with("No usable log directory"), "{}", err);
+    }
+}
@@ -11,116 +11,205 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use once_cell::sync::Lazy;  //מאפשר להגדיר משתנים גלובליים שנטענים רק פעם אחת כשצריך
-use std::collections::HashMap; // מבנה נתונים של מפתח-ערך, משמש לשמירת LineIndex לכל path
-use std::sync::Mutex;   // מאפשר לגשת למשתנה משותף בבטחה מתהליכים שונים
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};   // מאפשר לגשת למשתנה משותף בבטחה מתהליכים שונים
 
 
-use std::process::Command;         // To run shell commands (ripgrep)
 use tauri::command;                // Attribute to expose functions to JS
+use tauri::{Manager, State};       // Access to state registered with .manage()
 use rfd::FileDialog;              // For native file dialogs
-use std::fs;
-use std::os::windows::process::CommandExt;  // For creation_flags
-use std::env::temp_dir;  // Add this for temporary directory
 
 // use std::fs::File;
 // use std::io::{BufRead, BufReader};
 
 // use std::io::Read;
-use std::io::Write;
 // use encoding_rs::WINDOWS_1252;
 
-mod memmap_line_reader;
-use memmap_line_reader::LineIndex;
-
-// Embed the ripgrep binary
-const RG_BINARY: &[u8] = include_bytes!("../bin/rg.exe");
+// The search backend lives in the library crate (lib.rs), shared with the CLI
+use search_tool_lib::{
+    api_server, binary_view, content_index, diagnostics, editor, export, file_actions, file_search, index_cache, line_edit, log_format, logging, metadata_filter, replace,
+    result_store, search, search_manager, settings, syntax_highlight, undo_journal,
+};
+use api_server::{ApiError, ApiHandler, ApiServer};
+use binary_view::HexChunk;
+use content_index::IndexStatus;
+use export::{ExportFormat, ExportSummary};
+use file_actions::PathFormat;
+use file_search::{FileHit, FileSearchOptions, FileSearchStats};
+use diagnostics::{CacheStats, DiagnosticsBundle, EngineInfo};
+use index_cache::IndexCache;
+use line_edit::EditReport;
+use log_format::{LogFields, LogFilter, LogParser};
+use metadata_filter::MetadataFilter;
+use replace::{ReplacePreview, ReplaceReport, ReplaceRequest};
+use result_store::{MatchesPage, PageRequest, ResultsPage, StoredResults, TimelinePage};
+use search::{SearchControl, SearchOptions, SearchOutcome, SearchProgress, SearchStats, SearchWarning};
+use search_manager::{SearchManager, SearchRecord};
+use logging::LogRecord;
+use settings::{ApiSettings, LogLevel, Settings};
+use syntax_highlight::{HighlightCache, TokenSpan};
+use undo_journal::{JournalEntry, UndoReport};
 
 // -----------------------------------------------------------
-// This struct is used to store the search stats
+// Events emitted while searches started with `start_search` run
 // -----------------------------------------------------------
 
-#[derive(serde::Serialize)]
-struct SearchStats {
-    total_matches: usize,
-    matched_lines: usize,
-    files_searched: usize,
-    search_time_ms: f64,
-    total_time_ms: f64,
+#[derive(serde::Serialize, Clone)]
+struct SearchProgressEvent<'a> {
+    search_id: &'a str,
+    #[serde(flatten)]
+    progress: &'a SearchProgress,
 }
 
-#[derive(serde::Serialize)]
-struct FileChunk {
-    lines: Vec<String>,
-    offset: usize,
-    has_more: bool,
+#[derive(serde::Serialize, Clone)]
+struct SearchFinishedEvent<'a> {
+    search_id: &'a str,
+    status: &'static str, // "completed", "cancelled" or "failed"
+    stats: Option<&'a SearchStats>,
+    warnings: &'a [SearchWarning], // paths ripgrep could not search
+    truncated: bool,
+    error: Option<&'a str>,
 }
 
+// Events emitted while file-name searches started with `start_file_search` run
+
+#[derive(serde::Serialize, Clone)]
+struct FileSearchResultsEvent<'a> {
+    search_id: &'a str,
+    hits: Vec<FileHit>,
+}
+
+#[derive(serde::Serialize, Clone)]
+struct FileSearchFinishedEvent<'a> {
+    search_id: &'a str,
+    status: &'static str, // "completed", "cancelled" or "failed"
+    stats: Option<&'a FileSearchStats>,
+    warnings: &'a [SearchWarning],
+    truncated: bool,
+    error: Option<&'a str>,
+}
 
 #[derive(serde::Serialize)]
 struct ChunkResponse {
     lines: Vec<String>,
     offset: usize,
     has_more: bool,
+    binary: bool, // no lines are returned; read the file with read_file_hex_chunk
+    language: Option<String>, // detected from the extension or first line, e.g. "Rust"
+    tokens: Vec<Vec<TokenSpan>>, // one list per line; empty when not highlighted
+}
+
+// Lines around a timeline entry, read through the file's LineIndex
+#[derive(serde::Serialize)]
+struct ContextLines {
+    first_line: usize, // 1-based number of lines[0]
+    lines: Vec<String>,
+    fields: Vec<LogFields>, // timestamp and level of each line, parallel to lines
+    has_before: bool,
+    has_after: bool,
 }
 
+// Context lines one request may ask for on each side of the match
+const MAX_CONTEXT_LINES: usize = 500;
+
+// Lines one viewer chunk may ask for
+const MAX_CHUNK_LINES: usize = 10_000;
+
 // A global cache that maps absolute file paths to LineIndex
-static INDEX_CACHE: Lazy<Mutex<HashMap<String, LineIndex>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static INDEX_CACHE: Lazy<Mutex<IndexCache>> = Lazy::new(|| Mutex::new(IndexCache::default()));
+
+// Syntax highlighter states, so the next chunk of a file continues where the last one ended
+static HIGHLIGHT_CACHE: Lazy<Mutex<HighlightCache>> = Lazy::new(|| Mutex::new(HighlightCache::default()));
+
+// The local HTTP API, while it is enabled (see apply_api_settings)
+static API_SERVER: Lazy<Mutex<Option<ApiServer>>> = Lazy::new(|| Mutex::new(None));
 
 // ----------------------
 // Main application entry
 // ----------------------
 fn main() {
-    // Set up logs directory and env var as early as possible
-    if let Ok(exe_path) = std::env::current_exe() {
-        if let Some(app_dir) = exe_path.parent() {
-            let logs_dir = app_dir.join("logs");
-            let _ = std::fs::create_dir_all(&logs_dir);
-            std::env::set_var("LOGS_DIR", logs_dir.to_str().unwrap_or(""));
-        }
+    // Load settings before anything reads them (log level, search defaults...)
+    let settings_report = settings::init();
+
+    // Set up logging as early as possible; falls back to a user-writable directory
+    // when the install directory is read-only (Program Files, read-only mounts)
+    let log_dir = logging::init_default();
+    if let Err(ref e) = log_dir {
+        eprintln!("{}", e);
+    }
+
+    logging::info("The new search tool in new face 2025 by KV labs", &[]);
+    let log_settings = if settings_report.invalid { logging::warn } else { logging::info };
+    log_settings(&settings_report.message, &[("path", &settings_report.path.display())]);
+    if let Ok(ref dir) = log_dir {
+        logging::info("Logging to directory", &[("path", &dir.display())]);
     }
-    
-    log_debug("The new search tool in new face 2025 by KV labs");
 
     #[cfg(target_os = "windows")]
     install_webview2_if_needed();
 
-    println!("Tauri backend starting...");
+    logging::info("Tauri backend starting", &[]);
     tauri::Builder::default()
+        .manage(SearchManager::default())
+        .setup(|app| {
+            apply_api_settings(&app.handle(), &settings::current().api);
+            Ok(())
+        })
         .invoke_handler(
-            tauri::generate_handler![search_text, open_folder_dialog, read_file_mmap_chunk, get_about_info] //what are these handlers?
-                // search_text is the function that is called when the user clicks the search button
-                // open_folder_dialog is the function that is called when the user clicks the open folder button
-                // read_file is the function that is called when the user clicks the read file butto. its not a button. its when pressing on the results
-                // get_about_info is the function that is called when the user clicks the about button
+            tauri::generate_handler![search_text, start_search, cancel_search, get_search_results, start_file_search, open_in_editor, reveal_in_folder, format_result_path, get_results_page, get_file_matches, get_timeline, read_context_lines, export_results, preview_replace, apply_replace, edit_lines, list_undo_entries, undo_change, list_running_searches, open_folder_dialog, read_file_mmap_chunk, read_file_hex_chunk, get_about_info, get_settings, update_settings, get_recent_logs, collect_diagnostics, get_index_status, list_indexes, build_index, rebuild_index, delete_index]
+                // search_text runs a search and waits for it, returning its totals like get_search_results
+                // start_search / cancel_search / get_search_results run searches in the background, several at once
+                // start_file_search finds files by name or path (fuzzy, glob or regex) and streams them as events
+                // open_in_editor opens a result in the configured external editor at its line and column
+                // reveal_in_folder shows a result in the file manager; format_result_path formats its path for copying
+                // get_results_page / get_file_matches page through the stored results of a search
+                // get_timeline pages through the matches of all files by log timestamp; read_context_lines expands an entry
+                // export_results writes the stored results to CSV, JSON Lines, Markdown or grep format
+                // preview_replace / apply_replace replace the matches of a search; undo_change reverts a journaled change
+                // edit_lines edits a range of lines shown in the viewer (journaled like replace)
+                // open_folder_dialog picks the folder to search
+                // read_file_mmap_chunk pages through a result file's lines for the viewer
+                // read_file_hex_chunk shows binary files as a hex + ASCII dump, paged by byte offset
+                // get_about_info returns the about text, app version and log directory
+                // get_settings / update_settings read and persist the settings file
+                // get_recent_logs returns the latest log records for display or export
+                // collect_diagnostics bundles versions, settings, recent searches and logs for bug reports
+                // get_index_status / list_indexes / build_index / rebuild_index / delete_index manage the per-root content indexes
         )
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
 
 #[command]
-fn read_file_mmap_chunk(path: String, offset: usize, count: usize) -> Result<ChunkResponse, String> {
-    log_debug(&format!("Reading file: {}", path));
-    log_debug(&format!("Offset: {}", offset));
-    log_debug(&format!("Count: {}", count));
-
-    // Try getting the directory from env or fallback to current dir
-    let current_dir = std::env::var("LAST_SEARCH_DIR")
-        .ok()
-        .map(std::path::PathBuf::from)
-        .unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
-
-    log_debug(&format!("Current directory: {}", current_dir.display()));
-
-    // Resolve absolute path safely
-    let abs_path = if path.starts_with("./") {
-        current_dir.join(&path[2..])
-    } else if !std::path::Path::new(&path).is_absolute() {
-        current_dir.join(&path)
-    } else {
-        std::path::PathBuf::from(&path)
-    };
+fn read_file_mmap_chunk(
+        manager: State<'_, SearchManager>,
+        path: String,
+        offset: usize,
+        count: Option<usize>,
+        search_id: Option<String>,
+    ) -> Result<ChunkResponse, String> {
+    read_chunk(&manager, &path, offset, count, search_id.as_deref())
+}
 
-    log_debug(&format!("Absolute path: {}", abs_path.display()));
+// Shared by read_file_mmap_chunk and the HTTP API
+fn read_chunk(
+        manager: &SearchManager,
+        path: &str,
+        offset: usize,
+        count: Option<usize>,
+        search_id: Option<&str>,
+    ) -> Result<ChunkResponse, String> {
+    let settings = settings::current();
+    let count = count.unwrap_or(settings.viewer_chunk_size).min(MAX_CHUNK_LINES);
+    logging::debug("Reading file chunk", &[
+        ("path", &path),
+        ("offset", &offset),
+        ("count", &count),
+        ("search_id", &search_id.unwrap_or("-")),
+    ]);
+
+    // Relative result paths are resolved against the root of the search that produced them
+    let abs_path = manager.resolve(search_id, path)?;
 
     // Check file exists
     if !abs_path.exists() {
@@ -129,26 +218,150 @@ fn read_file_mmap_chunk(path: String, offset: usize, count: usize) -> Result<Chu
 
     let abs_path_str = abs_path.to_string_lossy().to_string();
 
-    let mut cache = INDEX_CACHE.lock().unwrap();
-
-    if !cache.contains_key(&abs_path_str) {
-        let index = LineIndex::new(&abs_path_str)
-            .map_err(|e| format!("Failed to index file: {}", e))?;
-        cache.insert(abs_path_str.clone(), index);
-    }
-
-    let index = cache.get(&abs_path_str).unwrap();
-    let total_lines = index.line_count();
-    let lines = index.get_lines(offset, count);
+    // Copy out everything highlighting needs, so the index cache is not held while tokenizing
+    let (lines, total_lines, syntax, before) = {
+        let mut cache = INDEX_CACHE.lock().unwrap();
+        let index = cache.get_or_build(&abs_path_str, settings.cache_budget_mb * 1024 * 1024)?;
+        if index.is_binary() {
+            logging::debug("File looks binary, no lines returned", &[("path", &abs_path_str)]);
+            return Ok(ChunkResponse {
+                lines: Vec::new(),
+                offset,
+                has_more: false,
+                binary: true,
+                language: None,
+                tokens: Vec::new(),
+            });
+        }
+        let lines = index.get_lines(offset, count);
+        let syntax = if settings.viewer_syntax_highlight {
+            let first_line = index.get_lines(0, 1).pop().unwrap_or_default();
+            syntax_highlight::detect_language(&abs_path, &first_line)
+        } else {
+            None
+        };
+        // Lines the parser has to go through before the chunk
+        let before = match syntax {
+            Some(syntax) => {
+                let from = HIGHLIGHT_CACHE.lock().unwrap().first_line_needed(&abs_path_str, syntax, offset);
+                index.get_lines(from, offset - from)
+            }
+            None => Vec::new(),
+        };
+        (lines, index.line_count(), syntax, before)
+    };
     let next_offset = offset + lines.len();
 
+    // Only this chunk is tokenized, so large files stay lazy
+    let tokens = match syntax {
+        Some(syntax) => HIGHLIGHT_CACHE.lock().unwrap().tokenize(&abs_path_str, syntax, &before, offset, &lines),
+        None => Vec::new(),
+    };
+
     Ok(ChunkResponse {
         lines,
         offset: next_offset,
         has_more: next_offset < total_lines,
+        binary: false,
+        language: syntax.map(|s| s.name.clone()),
+        tokens,
     })
 }
 
+// `before` and `after` lines around line `line` (1-based) of a result file, to expand a
+// timeline entry in place. Each line comes with its parsed timestamp and level.
+#[command]
+fn read_context_lines(
+        manager: State<'_, SearchManager>,
+        path: String,
+        line: usize,
+        before: usize,
+        after: usize,
+        search_id: Option<String>,
+    ) -> Result<ContextLines, String> {
+    read_context(&manager, &path, line, before, after, search_id.as_deref())
+}
+
+// Shared by read_context_lines and the HTTP API
+fn read_context(
+        manager: &SearchManager,
+        path: &str,
+        line: usize,
+        before: usize,
+        after: usize,
+        search_id: Option<&str>,
+    ) -> Result<ContextLines, String> {
+    let settings = settings::current();
+    logging::debug("Reading context lines", &[
+        ("path", &path),
+        ("line", &line),
+        ("before", &before),
+        ("after", &after),
+        ("search_id", &search_id.unwrap_or("-")),
+    ]);
+    let abs_path = manager.resolve(search_id, path)?;
+    if !abs_path.exists() {
+        return Err(format!("File does not exist: {}", abs_path.display()));
+    }
+    let abs_path_str = abs_path.to_string_lossy().to_string();
+
+    let mut cache = INDEX_CACHE.lock().unwrap();
+    let index = cache.get_or_build(&abs_path_str, settings.cache_budget_mb * 1024 * 1024)?;
+    if index.is_binary() {
+        return Err(format!("{} is a binary file", abs_path.display()));
+    }
+    let total_lines = index.line_count();
+    let matched = line.max(1) - 1;
+    let start = matched.saturating_sub(before.min(MAX_CONTEXT_LINES));
+    let end = matched.saturating_add(1 + after.min(MAX_CONTEXT_LINES)).min(total_lines);
+    let lines = index.get_lines(start, end.saturating_sub(start));
+    drop(cache);
+
+    let parser = LogParser::new(&settings.log_formats)?;
+    Ok(ContextLines {
+        first_line: start + 1,
+        fields: lines.iter().map(|l| parser.parse(l)).collect(),
+        lines,
+        has_before: start > 0,
+        has_after: end < total_lines,
+    })
+}
+
+// Hex + ASCII dump of a binary file, paged by byte offset. `rows` (16 bytes each)
+// defaults to the viewer chunk size.
+#[command]
+fn read_file_hex_chunk(
+        manager: State<'_, SearchManager>,
+        path: String,
+        offset: u64,
+        rows: Option<usize>,
+        search_id: Option<String>,
+    ) -> Result<HexChunk, String> {
+    read_hex_chunk(&manager, &path, offset, rows, search_id.as_deref())
+}
+
+// Shared by read_file_hex_chunk and the HTTP API
+fn read_hex_chunk(
+        manager: &SearchManager,
+        path: &str,
+        offset: u64,
+        rows: Option<usize>,
+        search_id: Option<&str>,
+    ) -> Result<HexChunk, String> {
+    let rows = rows.unwrap_or(settings::current().viewer_chunk_size);
+    logging::debug("Reading hex chunk", &[
+        ("path", &path),
+        ("offset", &offset),
+        ("rows", &rows),
+        ("search_id", &search_id.unwrap_or("-")),
+    ]);
+    let abs_path = manager.resolve(search_id, path)?;
+    if !abs_path.exists() {
+        return Err(format!("File does not exist: {}", abs_path.display()));
+    }
+    binary_view::read_hex_chunk(&abs_path, offset, rows)
+}
+
 
 
 
@@ -156,218 +369,559 @@ fn read_file_mmap_chunk(path: String, offset: usize, count: usize) -> Result<Chu
 
 // -----------------------------------------------------------
 // This function is exposed to the frontend via Tauri's `invoke`
-// It runs `ripgrep` (rg) to search for `query` in `path`
+// It runs `ripgrep` (rg) to search for `query` in `path` and waits for the result.
+// It shares the engine, worker slots and result store with `start_search`, and like
+// `get_search_results` returns only the totals: the matches are fetched page by page
+// with get_results_page / get_file_matches.
 // -----------------------------------------------------------
 #[command]  // Expose this function to the frontend via Tauri
-fn search_text(
-        _app: tauri::AppHandle,
+#[allow(clippy::too_many_arguments)]  // Tauri maps each frontend argument to a parameter
+async fn search_text(
+        app: tauri::AppHandle,
+        manager: State<'_, SearchManager>,
         query: String,
         path: String,
         file_filter: Option<String>,
-        case_sensitive: bool,
-        whole_phrase: bool,
-        whole_words: bool,
-    ) -> Result<String, String> {
-    
-    log_debug("=== Starting new search ===");
-    log_debug(&format!("Query: '{}'", query));
-    log_debug(&format!("Path: '{}'", path));
-    log_debug(&format!("Case sensitive: '{}'", case_sensitive));
-    log_debug(&format!("Whole phrase: '{}'", whole_phrase));
-    log_debug(&format!("Whole words: '{}'", whole_words));
-    if let Some(ref filter) = file_filter {
-        log_debug(&format!("File filter: '{}'", filter));
-    }
-    std::env::set_var("LAST_SEARCH_DIR", &path);
-    if query.trim().is_empty() {
-        log_debug("Error: Empty query");
-        return Ok("Search query cannot be empty".to_string());
-    }
-    if path.trim().is_empty() {
-        log_debug("Error: Empty path");
-        return Ok("Search path cannot be empty".to_string());
-    }
-    if !std::path::Path::new(&path).exists() {
-        log_debug(&format!("Error: Path does not exist: {}", path));
-        return Ok(format!("Path does not exist: {}", path));
+        case_sensitive: Option<bool>,
+        whole_phrase: Option<bool>,
+        whole_words: Option<bool>,
+        metadata_filter: Option<MetadataFilter>,
+        log_filter: Option<LogFilter>,
+    ) -> Result<serde_json::Value, String> {
+
+    let options = SearchOptions {
+        query,
+        path,
+        file_filter,
+        case_sensitive,
+        whole_phrase,
+        whole_words,
+        metadata_filter,
+        log_filter,
+    };
+    log_search_options("Starting new search", &options);
+
+    if let Err(msg) = options.validate() {
+        logging::warn("Invalid search request", &[("error", &msg)]);
+        return Err(msg);
     }
 
-    let temp_dir = temp_dir();
-    // 🛡️ One-liner: wait if rg.exe exists and is locked
-    if temp_dir.join("rg.exe").exists() {
-        std::thread::sleep(std::time::Duration::from_millis(500));
+    // Results are printed relative to the root, so remember it under this search's ID
+    let session = manager.create_session(std::path::Path::new(&options.path));
+    logging::debug("Search session created", &[("search_id", &session.id), ("root", &session.root.display())]);
+
+    let search_id = session.id.clone();
+    let control = app.state::<SearchManager>().register(&search_id);
+    // Run on a blocking thread so the UI stays responsive while ripgrep works
+    tauri::async_runtime::spawn_blocking(move || {
+        execute_search(&app, &search_id, &options, control)
+    })
+    .await
+    .map_err(|e| format!("Search task failed: {}", e))??;
+
+    search_summary(&manager, &session.id)
+}
+
+// -----------------------------------------------------------
+// Background searches: start_search returns an ID immediately and reports
+// through "search-progress" / "search-finished" events.
+// -----------------------------------------------------------
+#[command]
+fn start_search(app: tauri::AppHandle, options: SearchOptions) -> Result<String, String> {
+    spawn_search(&app, options)
+}
+
+// Shared by start_search and the HTTP API
+fn spawn_search(app: &tauri::AppHandle, options: SearchOptions) -> Result<String, String> {
+    log_search_options("Starting background search", &options);
+    options.validate()?;
+
+    let session = app.state::<SearchManager>().create_session(std::path::Path::new(&options.path));
+    logging::debug("Search session created", &[("search_id", &session.id), ("root", &session.root.display())]);
+
+    let search_id = session.id;
+    let control = app.state::<SearchManager>().register(&search_id);
+    let thread_id = search_id.clone();
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let _ = execute_search(&app, &thread_id, &options, control);
+    });
+    Ok(search_id)
+}
+
+// -----------------------------------------------------------
+// File-name searches: start_file_search returns an ID immediately, streams hits through
+// "file-search-results" events and ends with "file-search-finished". Hit paths open in
+// the viewer with that ID; cancel_search stops them like content searches.
+// -----------------------------------------------------------
+#[command]
+fn start_file_search(
+        app: tauri::AppHandle,
+        manager: State<'_, SearchManager>,
+        options: FileSearchOptions,
+    ) -> Result<String, String> {
+    logging::info("Starting file name search", &[
+        ("query", &options.pattern),
+        ("path", &options.path),
+        ("mode", &format!("{:?}", options.mode)),
+        ("file_filter", &options.file_filter.as_deref().unwrap_or("")),
+    ]);
+    options.validate()?;
+
+    let session = manager.create_session(std::path::Path::new(&options.path));
+    let search_id = session.id;
+    let control = manager.register(&search_id);
+    let thread_id = search_id.clone();
+    std::thread::spawn(move || {
+        let search_id = thread_id.as_str();
+        let manager = app.state::<SearchManager>();
+        let settings = settings::current();
+        let result = match manager.acquire_slot(&control, settings.max_concurrent_searches) {
+            Some(_slot) => file_search::run_file_search(&options, &settings, &control, |hits| {
+                let _ = app.emit_all("file-search-results", FileSearchResultsEvent { search_id, hits });
+            }),
+            None => Ok(file_search::FileSearchOutcome { cancelled: true, ..Default::default() }),
+        };
+        manager.abandon(search_id);
+
+        let event = match &result {
+            Ok(outcome) => {
+                logging::info("File name search finished", &[
+                    ("search_id", &search_id),
+                    ("files_visited", &outcome.stats.files_visited),
+                    ("files_matched", &outcome.stats.files_matched),
+                    ("duration_ms", &format!("{:.3}", outcome.stats.total_time_ms)),
+                    ("truncated", &outcome.truncated),
+                ]);
+                FileSearchFinishedEvent {
+                    search_id,
+                    status: if outcome.cancelled { "cancelled" } else { "completed" },
+                    stats: Some(&outcome.stats),
+                    warnings: &outcome.warnings,
+                    truncated: outcome.truncated,
+                    error: None,
+                }
+            }
+            Err(e) => {
+                logging::error("File name search failed", &[("search_id", &search_id), ("error", e)]);
+                FileSearchFinishedEvent {
+                    search_id,
+                    status: "failed",
+                    stats: None,
+                    warnings: &[],
+                    truncated: false,
+                    error: Some(e),
+                }
+            }
+        };
+        let _ = app.emit_all("file-search-finished", event);
+    });
+    Ok(search_id)
+}
+
+// Open a result in the external editor from the settings. Paths are resolved like
+// read_file_mmap_chunk resolves them: relative to the root of the search that produced them.
+// Without a column, the first match on that line of the search's results is used.
+#[command]
+fn open_in_editor(
+        manager: State<'_, SearchManager>,
+        path: String,
+        line: Option<u64>,
+        column: Option<usize>,
+        search_id: Option<String>,
+    ) -> Result<(), String> {
+    let abs_path = manager.resolve(search_id.as_deref(), &path)?;
+    if !abs_path.exists() {
+        return Err(format!("File does not exist: {}", abs_path.display()));
     }
-    let rg_path = temp_dir.join("rg.exe");
+    let line = line.unwrap_or(1);
+    let column = column
+        .or_else(|| {
+            let stored = manager.results(search_id.as_deref()?)?;
+            let found = stored.outcome.matches.iter().find(|m| m.path == path && m.line == line)?;
+            // Editors count characters, ripgrep reports bytes
+            let start = found.ranges.first()?.start;
+            Some(found.text.get(..start)?.chars().count() + 1)
+        })
+        .unwrap_or(1);
+    let editor = settings::current().editor;
+    logging::info("Opening in editor", &[
+        ("path", &abs_path.display()),
+        ("line", &line),
+        ("column", &column),
+        ("preset", &format!("{:?}", editor.preset)),
+    ]);
+    editor::open(&editor, &abs_path, line, column)
+}
 
-    log_debug("Extracting ripgrep binary");
-    match fs::write(&rg_path, RG_BINARY) {
-        Ok(_) => log_debug("Successfully extracted ripgrep binary"),
-        Err(e) => {
-            log_debug(&format!("Failed to extract ripgrep: {}", e));
-            return Ok(format!("Failed to extract ripgrep: {}", e));
+// Open the folder of a result in Explorer, Finder or the Linux file manager, with the file
+// selected where possible. Paths are resolved like read_file_mmap_chunk resolves them.
+#[command]
+async fn reveal_in_folder(app: tauri::AppHandle, path: String, search_id: Option<String>) -> Result<(), String> {
+    let abs_path = app.state::<SearchManager>().resolve(search_id.as_deref(), &path)?;
+    logging::info("Revealing in file manager", &[("path", &abs_path.display())]);
+    // The Linux D-Bus call waits for the file manager to answer
+    tauri::async_runtime::spawn_blocking(move || file_actions::reveal(&abs_path))
+        .await
+        .map_err(|e| format!("Reveal task failed: {}", e))?
+}
+
+// A result path as absolute, relative to the search root, or "file:line"; the frontend
+// puts it on the clipboard
+#[command]
+fn format_result_path(
+        manager: State<'_, SearchManager>,
+        path: String,
+        format: PathFormat,
+        line: Option<u64>,
+        search_id: Option<String>,
+    ) -> Result<String, String> {
+    let abs_path = manager.resolve(search_id.as_deref(), &path)?;
+    let root = search_id.as_deref().and_then(|id| manager.root(id));
+    Ok(file_actions::format_path(&abs_path, root.as_deref(), format, line))
+}
+
+#[command]
+fn cancel_search(manager: State<'_, SearchManager>, search_id: String) -> bool {
+    logging::info("Cancel requested", &[("search_id", &search_id)]);
+    manager.cancel(&search_id)
+}
+
+#[derive(serde::Serialize)]
+struct SearchSummary<'a> {
+    search_id: &'a str,
+    root: String,
+    stats: &'a SearchStats,
+    warnings: &'a [SearchWarning],
+    file_count: usize,
+    line_count: usize,
+    truncated: bool,
+    cancelled: bool,
+}
+
+// Totals of a finished search; the matches themselves are fetched page by page
+#[command]
+fn get_search_results(manager: State<'_, SearchManager>, search_id: String) -> Result<serde_json::Value, String> {
+    search_summary(&manager, &search_id)
+}
+
+fn search_summary(manager: &SearchManager, search_id: &str) -> Result<serde_json::Value, String> {
+    let stored = stored_results(manager, search_id)?;
+    let summary = SearchSummary {
+        search_id,
+        root: stored.root.to_string_lossy().to_string(),
+        stats: &stored.outcome.stats,
+        warnings: &stored.outcome.warnings,
+        file_count: stored.file_count(),
+        line_count: stored.outcome.matches.len(),
+        truncated: stored.outcome.truncated,
+        cancelled: stored.outcome.cancelled,
+    };
+    serde_json::to_value(summary).map_err(|e| e.to_string())
+}
+
+// One page of file (or directory) groups, sorted and grouped as requested
+#[command]
+fn get_results_page(
+        manager: State<'_, SearchManager>,
+        search_id: String,
+        request: PageRequest,
+    ) -> Result<ResultsPage, String> {
+    Ok(stored_results(&manager, &search_id)?.page(&request))
+}
+
+// The matches of one file group, loaded when the group is expanded
+#[command]
+fn get_file_matches(
+        manager: State<'_, SearchManager>,
+        search_id: String,
+        path: String,
+        offset: usize,
+        limit: usize,
+    ) -> Result<MatchesPage, String> {
+    stored_results(&manager, &search_id)?
+        .file_matches(&path, offset, limit)
+        .ok_or_else(|| format!("No matches for {} in search {}", path, search_id))
+}
+
+// One page of the timeline: the matches of all files interleaved by log timestamp
+#[command]
+fn get_timeline(
+        manager: State<'_, SearchManager>,
+        search_id: String,
+        offset: usize,
+        limit: usize,
+    ) -> Result<TimelinePage, String> {
+    Ok(stored_results(&manager, &search_id)?.timeline(offset, limit))
+}
+
+// Export the stored results of a search. Without `path` the user picks the file in a save dialog;
+// returns None if the dialog is cancelled.
+#[command]
+async fn export_results(
+        app: tauri::AppHandle,
+        search_id: String,
+        format: ExportFormat,
+        path: Option<String>,
+    ) -> Result<Option<ExportSummary>, String> {
+    let stored = stored_results(&app.state::<SearchManager>(), &search_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let target = match path {
+            Some(path) => PathBuf::from(path),
+            None => match FileDialog::new()
+                .set_file_name(&format!("search-results.{}", format.extension()))
+                .add_filter(format.label(), &[format.extension()])
+                .save_file()
+            {
+                Some(path) => path,
+                None => return Ok(None),
+            },
+        };
+        let started = std::time::Instant::now();
+        let summary = export::export_to_file(&stored, format, &target)?;
+        logging::info("Exported results", &[
+            ("search_id", &search_id),
+            ("format", &format.extension()),
+            ("target", &target.display()),
+            ("matches", &summary.matches_written),
+            ("duration_ms", &started.elapsed().as_millis()),
+        ]);
+        Ok(Some(summary))
+    })
+    .await
+    .map_err(|e| format!("Export task failed: {}", e))?
+}
+
+// -----------------------------------------------------------
+// Search and replace, built on the options of a finished search
+// -----------------------------------------------------------
+
+// Dry run: per-file diff of what apply_replace would write
+#[command]
+async fn preview_replace(
+        app: tauri::AppHandle,
+        search_id: String,
+        request: ReplaceRequest,
+    ) -> Result<ReplacePreview, String> {
+    let stored = stored_results(&app.state::<SearchManager>(), &search_id)?;
+    tauri::async_runtime::spawn_blocking(move || replace::preview(&stored, &request, &settings::current()))
+        .await
+        .map_err(|e| format!("Replace preview failed: {}", e))?
+}
+
+#[command]
+async fn apply_replace(
+        app: tauri::AppHandle,
+        search_id: String,
+        request: ReplaceRequest,
+    ) -> Result<ReplaceReport, String> {
+    let stored = stored_results(&app.state::<SearchManager>(), &search_id)?;
+    logging::info("Applying replace", &[
+        ("search_id", &search_id),
+        ("query", &stored.options.query),
+        ("replacement", &request.replacement),
+    ]);
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        // Unmap the files before rewriting them (Windows refuses to replace a mapped file)
+        // and keep the viewer from mapping them again until the writes are done
+        let mut cache = INDEX_CACHE.lock().unwrap();
+        for path in stored.abs_paths() {
+            cache.invalidate(&path.to_string_lossy());
         }
+        replace::apply(&stored, &request, &settings::current())
+    })
+    .await
+    .map_err(|e| format!("Replace failed: {}", e))??;
+
+    logging::info("Replace done", &[
+        ("search_id", &search_id),
+        ("replacements", &report.total_replacements),
+        ("files", &report.files_changed.len()),
+        ("errors", &report.errors.len()),
+        ("undo_id", &report.undo_id.as_deref().unwrap_or("-")),
+    ]);
+    if let Some(e) = &report.undo_error {
+        logging::warn("Replace cannot be undone", &[("search_id", &search_id), ("error", e)]);
     }
-    let mut cmd = Command::new(&rg_path);
-    cmd.current_dir(&path)
-        .arg("--line-number")
-        .arg("--with-filename")
-        .arg("--no-ignore")
-        .arg("--hidden")
-        .arg("--text")
-        .arg("--stats");
-
-    // Handle case sensitivity
-    if case_sensitive {
-        cmd.arg("--case-sensitive");
-    } else {
-        cmd.arg("--ignore-case");
+    Ok(report)
+}
+
+// Replace `count` lines of a file starting at 0-based line `start` (the viewer's offsets).
+// `expected` holds the lines as the viewer shows them; the edit is refused if they changed.
+// The file keeps its encoding and line endings, and the edit can be reverted with undo_change.
+#[command]
+async fn edit_lines(
+        manager: State<'_, SearchManager>,
+        path: String,
+        search_id: Option<String>,
+        start: usize,
+        count: usize,
+        lines: Vec<String>,
+        expected: Option<Vec<String>>,
+    ) -> Result<EditReport, String> {
+    let abs_path = manager.resolve(search_id.as_deref(), &path)?;
+    logging::info("Editing lines", &[("path", &abs_path.display()), ("start", &start), ("count", &count)]);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let abs_path_str = abs_path.to_string_lossy().to_string();
+        let mut cache = INDEX_CACHE.lock().unwrap();
+        // Unmap before rewriting, then re-index so the viewer sees the new lines right away
+        cache.invalidate(&abs_path_str);
+        let report = line_edit::edit_lines(&abs_path, start, count, &lines, expected.as_deref())?;
+        cache.get_or_build(&abs_path_str, settings::current().cache_budget_mb * 1024 * 1024)?;
+        logging::debug("Edit saved", &[("undo_id", &report.undo_id.as_deref().unwrap_or("-"))]);
+        if let Some(e) = &report.undo_error {
+            logging::warn("Edit cannot be undone", &[("path", &abs_path.display()), ("error", e)]);
+        }
+        Ok(report)
+    })
+    .await
+    .map_err(|e| format!("Edit failed: {}", e))?
+}
+
+#[command]
+fn list_undo_entries() -> Vec<JournalEntry> {
+    undo_journal::list()
+}
+
+// Revert a journaled change; refuses if any of its files changed since
+#[command]
+fn undo_change(entry_id: String) -> Result<UndoReport, String> {
+    let entry = undo_journal::get(&entry_id)?;
+    // As for replace: unmap before the files are restored
+    let mut cache = INDEX_CACHE.lock().unwrap();
+    for file in &entry.files {
+        cache.invalidate(&file.path.to_string_lossy());
     }
-    
-    // Handle whole phrase (literal string match)
-    if whole_phrase {
-        cmd.arg("--fixed-strings"); // ensures query is interpreted literally
-    }
-
-    // Handle whole words (word regexp)
-    if whole_words {
-        cmd.arg("--word-regexp");
-    }
-
-    // Handle file filter (glob pattern)
-    if let Some(filter) = file_filter {
-        cmd.arg("--glob").arg(filter);
-    }
-
-    //
-    cmd.arg(&query).arg(".")
-        .creation_flags(0x08000000);
-
-    // Run the command and get the output
-    let output = cmd.output();
-    let _ = fs::remove_file(&rg_path);
-    log_debug("Cleaned up temporary ripgrep binary");
-
-    // Match the output of the ripgrep command to get the stats and the results
-    match output {
-        Ok(output) => {
-            log_debug(&format!("ripgrep exit status: {:?}", output.status));
-            
-            // Get the output as strings
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            
-            // Log any stderr output if present
-            if !stderr.is_empty() {
-                log_debug(&format!("ripgrep stderr: {}", stderr));
-            }
+    let report = undo_journal::undo(&entry_id)?;
+    drop(cache);
+    logging::info("Undid change", &[("undo_id", &entry_id), ("files", &report.restored.len())]);
+    Ok(report)
+}
 
-            let mut stats = SearchStats {
-                total_matches: 0,
-                matched_lines: 0,
-                files_searched: 0,
-                search_time_ms: 0.0,
-                total_time_ms: 0.0,
-            };
-            
-            for line in stdout.lines() {
-                if let Some(num) = line.split_whitespace().next() {
-                    if line.contains("matches") && !line.contains("matched lines") {
-                        stats.total_matches = num.parse().unwrap_or(0);
-                    } else if line.contains("matched lines") {
-                        stats.matched_lines = num.parse().unwrap_or(0);
-                    } else if line.contains("files searched") {
-                        stats.files_searched = num.parse().unwrap_or(0);
-                    } else if line.contains("seconds spent searching") {
-                        if let Some(num) = line.split_whitespace().next() {
-                            if let Ok(sec) = num.trim().parse::<f64>() {
-                                stats.search_time_ms = sec * 1000.0; // Convert to milliseconds
-                            }
-                        }
-                    } else if line.contains(" seconds") && !line.contains("spent searching") {
-                        if let Some(num) = line.split_whitespace().next() {
-                            if let Ok(sec) = num.trim().parse::<f64>() {
-                                stats.total_time_ms = sec * 1000.0; // Convert to milliseconds
-                            }
-                        }
-                    }                            
-                }
-            }
-            
-            log_debug("=== Ripgrep stats ===");
-            log_debug(&format!("files contained matches: {}", stats.total_matches));
-            log_debug(&format!("Matched lines: {}", stats.matched_lines));
-            log_debug(&format!("Files searched: {}", stats.files_searched));
-            log_debug(&format!("Search time (ms): {:.3}", stats.search_time_ms));
-            log_debug(&format!("Total time (ms): {:.3}", stats.total_time_ms));
-
-            // Check if we got any results
-            if stdout.trim().is_empty() {
-                log_debug("No matches found for the search query");
-                return Ok("".to_string()); // Return empty string instead of error
-            }
-            
-        
-// Format the results with HTML-like tags for styling
-// Create a string with this format
-// <line file="file_name" num="line_number">content</line>            
-
-            let formatted = stdout
-            .lines()
-            .filter(|line| !line.trim().is_empty())
-            .map(|line| {
-                let first_colon = line.find(':').unwrap_or(0); //find the first colon and second colon to find the file name and line number next to each other
-                let second_colon = line[first_colon + 1..]
-                    .find(':')
-                    .map(|i| i + first_colon + 1)
-                    .unwrap_or(0);
-                if first_colon > 0 && second_colon > first_colon {
-                    let file_name = line[..first_colon].trim().replace('\\', "/");
-                    let line_num = line[first_colon + 1..second_colon].trim();
-                    let content = line[second_colon + 1..].trim();
-        
-                    if content.len() > 1000 || content.chars().any(|c| !c.is_ascii() && !c.is_whitespace()) {
-                        return String::new();
-                    }
-                    let escaped_content = content
-                        .replace("&", "&amp;")
-                        .replace("<", "&lt;")
-                        .replace(">", "&gt;")
-                        .replace("\"", "&quot;")
-                        .replace("'", "&apos;");
-                    format!("<line file=\"{}\" num=\"{}\">{}</line>", file_name, line_num, escaped_content)                } else {
-                    String::new()
-                }
-            })
-            .filter(|line| !line.is_empty())            
-                .collect::<Vec<_>>()
-            .join("\n");
-            log_debug("=== Search completed successfully ===");
+fn stored_results(manager: &SearchManager, search_id: &str) -> Result<Arc<StoredResults>, String> {
+    manager
+        .results(search_id)
+        .ok_or_else(|| format!("No results for search ID: {}", search_id))
+}
 
+#[command]
+fn list_running_searches(manager: State<'_, SearchManager>) -> Vec<String> {
+    manager.running_ids()
+}
 
-            #[derive(serde::Serialize)]
-            struct SearchResponse {
-                html: String,
-                stats: SearchStats,
-            }
-            
-            // Create a response struct with the formatted HTML and stats
-            let response = SearchResponse {
-                html: formatted,    // the formatted HTML is from the ripgrep output
-                stats,              // the stats are from the ripgrep output
-            };
-            
-            // Convert the response to a JSON string
-            let json = serde_json::to_string(&response).unwrap();  // convert the response to a JSON string by using serde_json which is a library for serializing and deserializing JSON
-            // Delete it since its too long to display
-            // log_debug(&format!("Final JSON result: {}", json));
-            Ok(json)
+// Runs one search on the current thread: waits for a worker slot, runs ripgrep,
+// stores the outcome under `search_id` and emits progress/finished events.
+fn execute_search(
+        app: &tauri::AppHandle,
+        search_id: &str,
+        options: &SearchOptions,
+        control: Arc<SearchControl>,
+    ) -> Result<Arc<StoredResults>, String> {
+    let manager = app.state::<SearchManager>();
+    let settings = settings::current();
+    let started = chrono::Local::now().to_rfc3339();
+    let root = manager.root(search_id).unwrap_or_else(|| PathBuf::from(&options.path));
+
+    let result = match manager.acquire_slot(&control, settings.max_concurrent_searches) {
+        Some(_slot) => {
+            logging::debug("Search running", &[("search_id", &search_id)]);
+            search::run_search(options, &settings, &control, |progress| {
+                let _ = app.emit_all("search-progress", SearchProgressEvent { search_id, progress });
+            })
+        }
+        None => Ok(SearchOutcome { cancelled: true, ..Default::default() }),
+    };
+
+    let result = match result {
+        Ok(outcome) => {
+            log_search_outcome(search_id, &outcome);
+            // Changed files make the index less useful, not wrong; refresh it quietly
+            if outcome.stats.index_stale_files > 0 && settings.index.auto_update && !outcome.cancelled {
+                if let Err(e) = start_index_build(app.clone(), &root, false) {
+                    logging::warn("Content index update failed to start", &[("error", &e)]);
+                }
             }
+            Ok(manager.finish(search_id, StoredResults::new(root, options.clone(), outcome)))
+        }
         Err(e) => {
-            log_debug(&format!("Failed to run ripgrep: {}", e));
-            log_debug("=== Search failed ===");
-            Err(format!("Failed to run ripgrep: {}", e))
+            logging::error("Search failed", &[("search_id", &search_id), ("error", &e)]);
+            manager.abandon(search_id);
+            Err(e)
         }
+    };
+
+    let event = match &result {
+        Ok(stored) => SearchFinishedEvent {
+            search_id,
+            status: if stored.outcome.cancelled { "cancelled" } else { "completed" },
+            stats: Some(&stored.outcome.stats),
+            warnings: &stored.outcome.warnings,
+            truncated: stored.outcome.truncated,
+            error: None,
+        },
+        Err(e) => SearchFinishedEvent {
+            search_id,
+            status: "failed",
+            stats: None,
+            warnings: &[],
+            truncated: false,
+            error: Some(e),
+        },
+    };
+    manager.record(SearchRecord {
+        search_id: search_id.to_string(),
+        started,
+        root: result.as_ref().map(|s| s.root.display().to_string()).unwrap_or_else(|_| options.path.clone()),
+        options: options.clone(),
+        status: event.status.to_string(),
+        stats: event.stats.cloned(),
+        truncated: event.truncated,
+        stderr: result.as_ref().map(|s| s.outcome.stderr.clone()).unwrap_or_default(),
+        error: event.error.map(str::to_string),
+    });
+    let _ = app.emit_all("search-finished", event);
+    result
+}
+
+fn log_search_options(message: &str, options: &SearchOptions) {
+    logging::info(message, &[
+        ("query", &options.query),
+        ("path", &options.path),
+        ("file_filter", &options.file_filter.as_deref().unwrap_or("")),
+        ("case_sensitive", &format!("{:?}", options.case_sensitive)),
+        ("whole_phrase", &format!("{:?}", options.whole_phrase)),
+        ("whole_words", &format!("{:?}", options.whole_words)),
+        ("metadata_filter", &serde_json::to_string(&options.metadata_filter).unwrap_or_default()),
+        ("log_filter", &serde_json::to_string(&options.log_filter).unwrap_or_default()),
+    ]);
+}
+
+fn log_search_outcome(search_id: &str, outcome: &SearchOutcome) {
+    for warning in &outcome.warnings {
+        logging::warn("ripgrep could not search a path", &[
+            ("search_id", &search_id),
+            ("path", &warning.path.as_deref().unwrap_or("-")),
+            ("kind", &format!("{:?}", warning.kind)),
+            ("stderr", &warning.message),
+        ]);
     }
+    let stats = &outcome.stats;
+    let status = if outcome.cancelled { "cancelled" } else { "completed" };
+    logging::info("Search finished", &[
+        ("search_id", &search_id),
+        ("status", &status),
+        ("matches", &stats.total_matches),
+        ("matched_lines", &stats.matched_lines),
+        ("files_searched", &stats.files_searched),
+        ("search_ms", &format!("{:.3}", stats.search_time_ms)),
+        ("duration_ms", &format!("{:.3}", stats.total_time_ms)),
+        ("files_with_matches", &stats.files_with_matches),
+        ("bytes_searched", &stats.bytes_searched),
+        ("skipped", &stats.skipped.total()),
+        ("index_excluded", &stats.files_excluded_by_index),
+        ("truncated", &outcome.truncated),
+    ]);
 }
 
 // This command is exposed to the Tauri frontend.
@@ -516,26 +1070,257 @@ fn read_file(path: String, line_number: Option<usize>) -> Result<String, String>
 }
 */
 #[tauri::command]
-fn get_about_info(app_handle: tauri::AppHandle) -> Result<(String, String), String> {
+fn get_about_info(app_handle: tauri::AppHandle) -> Result<(String, String, String), String> {
     // Embed about.txt at compile time
     let about_text = include_str!("../about.txt").to_string();
     let version = app_handle.package_info().version.to_string();
-    Ok((about_text, version))
-}
-
-fn log_debug(msg: &str) {
-    if let Some(logs_dir) = std::env::var("LOGS_DIR").ok() {
-        let log_file = std::path::Path::new(&logs_dir).join("search_tool.log");
-        let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string();
-        let log_entry = format!("[{}] {}\n", timestamp, msg);
-        if let Ok(mut file) = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(log_file) 
-        {
-            let _ = file.write_all(log_entry.as_bytes());
-            let _ = file.flush(); // Ensure log is written immediately
+    // Where search_tool.log ended up (see logging::init_default), empty if logging is off
+    let log_dir = logging::log_dir().map(|d| d.display().to_string()).unwrap_or_default();
+    Ok((about_text, version, log_dir))
+}
+
+// -----------------------------------------------------------
+// Settings commands
+// -----------------------------------------------------------
+#[command]
+fn get_settings() -> Settings {
+    settings::current()
+}
+
+// -----------------------------------------------------------
+// Logs and diagnostics
+// -----------------------------------------------------------
+
+// Number of log records included in a diagnostics bundle
+const DIAGNOSTICS_LOG_TAIL: usize = 300;
+
+// Everything needed to investigate a bug report, as one JSON document.
+// `redact` defaults to the logging.redact setting. With `path`, the bundle is also saved there.
+#[command]
+async fn collect_diagnostics(
+        app: tauri::AppHandle,
+        redact: Option<bool>,
+        path: Option<String>,
+    ) -> Result<DiagnosticsBundle, String> {
+    // Running ripgrep and reading state can take a moment; keep it off the main thread
+    tauri::async_runtime::spawn_blocking(move || {
+        let settings = settings::current();
+        let manager = app.state::<SearchManager>();
+        let package = app.package_info();
+
+        let (version, error) = match search::engine_version() {
+            Ok(version) => (Some(version), None),
+            Err(e) => (None, Some(e)),
+        };
+        let engine = EngineInfo {
+            path: search::rg_path().ok().map(|p| p.display().to_string()),
+            version,
+            error,
+        };
+        let cache = {
+            let index_cache = INDEX_CACHE.lock().unwrap();
+            CacheStats {
+                indexed_files: index_cache.entry_count(),
+                index_memory_bytes: index_cache.memory_bytes(),
+                index_budget_bytes: settings.cache_budget_mb * 1024 * 1024,
+                stored_results: manager.stored_count(),
+                running_searches: manager.running_ids().len(),
+            }
+        };
+
+        let mut bundle = DiagnosticsBundle {
+            generated: chrono::Local::now().to_rfc3339(),
+            redacted: false,
+            app: diagnostics::AppInfo { name: package.name.clone(), version: package.version.to_string() },
+            os: diagnostics::OsInfo::current(),
+            engine,
+            log_dir: logging::log_dir().map(|d| d.display().to_string()),
+            searches: manager.history(),
+            cache,
+            logs: logging::recent(DIAGNOSTICS_LOG_TAIL, LogLevel::Debug),
+            settings: diagnostics::bundle_settings(&settings),
+        };
+        if redact.unwrap_or(settings.logging.redact) {
+            bundle.redact();
+        }
+
+        if let Some(path) = path {
+            let json = serde_json::to_vec_pretty(&bundle).map_err(|e| e.to_string())?;
+            std::fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+            logging::info("Diagnostics saved", &[("path", &path), ("redacted", &bundle.redacted)]);
         }
+        Ok(bundle)
+    })
+    .await
+    .map_err(|e| format!("Diagnostics failed: {}", e))?
+}
+
+// Latest log records, oldest first; `level` keeps that level and more severe (default: all)
+#[command]
+fn get_recent_logs(limit: Option<usize>, level: Option<LogLevel>) -> Vec<LogRecord> {
+    logging::recent(limit.unwrap_or(500), level.unwrap_or(LogLevel::Debug))
+}
+
+// -----------------------------------------------------------
+// Content indexes: builds run in the background and end with an "index-finished" event
+// -----------------------------------------------------------
+
+#[command]
+fn get_index_status(path: String) -> Result<IndexStatus, String> {
+    content_index::status(std::path::Path::new(&path))
+}
+
+#[command]
+fn list_indexes() -> Vec<IndexStatus> {
+    content_index::list()
+}
+
+// Create the index of `path`, or bring it up to date (only changed files are read)
+#[command]
+fn build_index(app: tauri::AppHandle, path: String) -> Result<IndexStatus, String> {
+    start_index_build(app, std::path::Path::new(&path), false)
+}
+
+// Throw the index away and read every file again
+#[command]
+fn rebuild_index(app: tauri::AppHandle, path: String) -> Result<IndexStatus, String> {
+    start_index_build(app, std::path::Path::new(&path), true)
+}
+
+#[command]
+fn delete_index(path: String) -> Result<(), String> {
+    content_index::delete(std::path::Path::new(&path))
+}
+
+fn start_index_build(app: tauri::AppHandle, root: &std::path::Path, full: bool) -> Result<IndexStatus, String> {
+    logging::info("Content index build requested", &[("root", &root.display()), ("full", &full)]);
+    content_index::start_build(root, full, move |status| {
+        let _ = app.emit_all("index-finished", status);
+    })
+}
+
+#[command]
+fn update_settings(app: tauri::AppHandle, settings: Settings) -> Result<Settings, String> {
+    let previous_api = settings::current().api;
+    let saved = settings::update(settings)?;
+    logging::info("Settings updated", &[("path", &settings::settings_path().display())]);
+    if saved.api != previous_api {
+        apply_api_settings(&app, &saved.api);
+    }
+    // Enabling the API may have generated a token
+    Ok(settings::current())
+}
+
+// -----------------------------------------------------------
+// Local HTTP API (see api_server.rs)
+// -----------------------------------------------------------
+
+// Stop the running server, then start a new one if the API is enabled. A missing
+// token is generated and saved, so the user can copy it from the settings file.
+fn apply_api_settings(app: &tauri::AppHandle, api: &ApiSettings) {
+    let mut running = API_SERVER.lock().unwrap();
+    running.take(); // dropping it stops it and frees the port
+    if !api.enabled {
+        return;
+    }
+
+    let mut api = api.clone();
+    if api.token.trim().is_empty() {
+        api.token = match api_server::generate_token() {
+            Ok(token) => token,
+            Err(e) => {
+                logging::error("API server not started", &[("error", &e)]);
+                return;
+            }
+        };
+        let mut settings = settings::current();
+        settings.api = api.clone();
+        match settings::update(settings) {
+            Ok(_) => logging::info("Generated an API token", &[("path", &settings::settings_path().display())]),
+            Err(e) => logging::error("Failed to save the generated API token", &[("error", &e)]),
+        }
+    }
+    match ApiServer::start(&api, Arc::new(AppApi(app.clone()))) {
+        Ok(server) => *running = Some(server),
+        Err(e) => logging::error("API server failed to start", &[("error", &e)]),
+    }
+}
+
+// Runs API requests through the same search manager and viewer cache as the UI
+struct AppApi(tauri::AppHandle);
+
+impl ApiHandler for AppApi {
+    fn status(&self) -> serde_json::Value {
+        serde_json::json!({
+            "app": self.0.package_info().name,
+            "version": self.0.package_info().version.to_string(),
+            "running": self.0.state::<SearchManager>().running_ids(),
+        })
+    }
+
+    fn start_search(&self, options: SearchOptions) -> Result<String, ApiError> {
+        Ok(spawn_search(&self.0, options)?)
+    }
+
+    fn search_status(&self, search_id: &str) -> Result<serde_json::Value, ApiError> {
+        let manager = self.0.state::<SearchManager>();
+        if manager.running_ids().iter().any(|id| id == search_id) {
+            return Ok(serde_json::json!({ "search_id": search_id, "status": "running" }));
+        }
+        if let Ok(mut summary) = search_summary(&manager, search_id) {
+            let cancelled = summary["cancelled"].as_bool().unwrap_or(false);
+            summary["status"] = (if cancelled { "cancelled" } else { "completed" }).into();
+            return Ok(summary);
+        }
+        // Failed searches leave no results, only their history record
+        match manager.history().into_iter().find(|r| r.search_id == search_id) {
+            Some(record) => Ok(serde_json::json!({
+                "search_id": search_id,
+                "status": record.status,
+                "error": record.error,
+            })),
+            None => Err(ApiError::not_found(format!("Unknown search ID: {}", search_id))),
+        }
+    }
+
+    fn cancel_search(&self, search_id: &str) -> bool {
+        logging::info("Cancel requested through the API", &[("search_id", &search_id)]);
+        self.0.state::<SearchManager>().cancel(search_id)
+    }
+
+    fn results_page(&self, search_id: &str, request: &PageRequest) -> Result<ResultsPage, ApiError> {
+        Ok(stored_results(&self.0.state::<SearchManager>(), search_id).map_err(ApiError::not_found)?.page(request))
+    }
+
+    fn file_matches(&self, search_id: &str, path: &str, offset: usize, limit: usize) -> Result<MatchesPage, ApiError> {
+        stored_results(&self.0.state::<SearchManager>(), search_id).map_err(ApiError::not_found)?
+            .file_matches(path, offset, limit)
+            .ok_or_else(|| ApiError::not_found(format!("No matches for {} in search {}", path, search_id)))
+    }
+
+    // The file reads below only serve files this search found, never other paths from a request
+    fn read_chunk(&self, search_id: &str, path: &str, offset: usize, count: Option<usize>) -> Result<serde_json::Value, ApiError> {
+        let manager = self.0.state::<SearchManager>();
+        manager.resolve_result(search_id, path).map_err(ApiError::not_found)?;
+        let chunk = read_chunk(&manager, path, offset, count, Some(search_id))?;
+        Ok(serde_json::to_value(chunk).map_err(|e| e.to_string())?)
+    }
+
+    fn read_hex_chunk(&self, search_id: &str, path: &str, offset: u64, rows: Option<usize>) -> Result<HexChunk, ApiError> {
+        let manager = self.0.state::<SearchManager>();
+        manager.resolve_result(search_id, path).map_err(ApiError::not_found)?;
+        Ok(read_hex_chunk(&manager, path, offset, rows, Some(search_id))?)
+    }
+
+    fn timeline(&self, search_id: &str, offset: usize, limit: usize) -> Result<TimelinePage, ApiError> {
+        Ok(stored_results(&self.0.state::<SearchManager>(), search_id).map_err(ApiError::not_found)?.timeline(offset, limit))
+    }
+
+    fn read_context(&self, search_id: &str, path: &str, line: usize, before: usize, after: usize) -> Result<serde_json::Value, ApiError> {
+        let manager = self.0.state::<SearchManager>();
+        manager.resolve_result(search_id, path).map_err(ApiError::not_found)?;
+        let context = read_context(&manager, path, line, before, after, Some(search_id))?;
+        Ok(serde_json::to_value(context).map_err(|e| e.to_string())?)
     }
 }
 
@@ -554,7 +1339,7 @@ fn install_webview2_if_needed() {
     use winreg::enums::*;
     use winreg::RegKey;
 
-    log_debug("Checking if WebView2 Runtime is installed...");
+    logging::debug("Checking if WebView2 Runtime is installed...", &[]);
     let mut found = false;
 
     // -- REGISTRY CHECK (HKLM) --
@@ -567,7 +1352,7 @@ fn install_webview2_if_needed() {
         let reg_path = format!("SOFTWARE\\Microsoft\\EdgeUpdate\\Clients\\{}", guid);
         if let Ok(key) = hklm.open_subkey(&reg_path) {
             if let Ok(version) = key.get_value::<String, _>("pv") {
-                log_debug(&format!("✅ WebView2 found in registry (HKLM): GUID {}, version {}", guid, version));
+                logging::info("✅ WebView2 found in registry (HKLM)", &[("guid", &guid), ("version", &version)]);
                 found = true;
                 break;
             }
@@ -580,7 +1365,7 @@ fn install_webview2_if_needed() {
         let user_path = "SOFTWARE\\Microsoft\\EdgeUpdate\\Clients\\{56EB18F8-B008-4CBD-B6D2-8C97FE7E9062}";
         if let Ok(key) = hkcu.open_subkey(user_path) {
             if let Ok(version) = key.get_value::<String, _>("pv") {
-                log_debug(&format!("✅ WebView2 found in registry (HKCU): version {}", version));
+                logging::info("✅ WebView2 found in registry (HKCU)", &[("version", &version)]);
                 found = true;
             }
         }
@@ -597,7 +1382,7 @@ fn install_webview2_if_needed() {
             // Direct match: C:\...\Application\msedgewebview2.exe
             let direct = base_path.join("msedgewebview2.exe");
             if direct.exists() {
-                log_debug(&format!("✅ WebView2 found at: {}", direct.display()));
+                logging::info("✅ WebView2 found", &[("path", &direct.display())]);
                 found = true;
                 break;
             }
@@ -609,7 +1394,7 @@ fn install_webview2_if_needed() {
                     if sub.is_dir() {
                         let exe = sub.join("msedgewebview2.exe");
                         if exe.exists() {
-                            log_debug(&format!("✅ WebView2 found in subfolder: {}", exe.display()));
+                            logging::info("✅ WebView2 found in subfolder", &[("path", &exe.display())]);
                             found = true;
                             break;
                         }
@@ -624,8 +1409,8 @@ fn install_webview2_if_needed() {
 
     // -- NOT FOUND: Prompt user to install --
     if !found {
-        log_debug("❌ WebView2 Runtime not found.");
-        log_debug("➡️ Please install it from:");
-        log_debug("   https://developer.microsoft.com/en-us/microsoft-edge/webview2/");
+        logging::warn("❌ WebView2 Runtime not found.", &[]);
+        logging::warn("➡️ Please install it from:", &[]);
+        logging::warn("   https://developer.microsoft.com/en-us/microsoft-edge/webview2/", &[]);
     }
 }
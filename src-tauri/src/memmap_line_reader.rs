@@ -8,10 +8,15 @@ use std::fs::File;
 use std::io::{self, BufReader};
 use std::path::Path;
 
+use crate::binary_view;
+use crate::text_codec::{self, TextEncoding};
+
 /// A structure to index lines in a file
 pub struct LineIndex {
     mmap: Mmap,
     line_offsets: Vec<usize>,
+    binary: bool,
+    encoding: TextEncoding, // as text_codec::decode sees the file, so the viewer and editing agree
 }
 
 
@@ -24,14 +29,10 @@ impl LineIndex {
         let file = File::open(&path)?;
         let mmap = unsafe { Mmap::map(&file)? };
 
-        let mut line_offsets = vec![0]; // first line starts at 0
-        for (i, &byte) in mmap.iter().enumerate() {
-            if byte == b'\n' {
-                line_offsets.push(i + 1); // next line starts after '\n'
-            }
-        }
-
-        Ok(Self { mmap, line_offsets })
+        let encoding = text_codec::detect_encoding(&mmap);
+        let line_offsets = line_starts(&mmap, encoding);
+        let binary = binary_view::looks_binary(&mmap);
+        Ok(Self { mmap, line_offsets, binary, encoding })
     }
 
     /// Return the total number of lines in the file
@@ -39,10 +40,20 @@ impl LineIndex {
         self.line_offsets.len()
     }
 
+    /// True if the file looks binary; its "lines" are not worth showing (see binary_view.rs)
+    pub fn is_binary(&self) -> bool {
+        self.binary
+    }
+
+    /// Approximate heap memory used by the index (the mmap itself is not counted)
+    pub fn memory_footprint(&self) -> usize {
+        self.line_offsets.capacity() * std::mem::size_of::<usize>()
+    }
+
     /// Get a chunk of lines from [start, start+count)
     pub fn get_lines(&self, start: usize, count: usize) -> Vec<String> {
-        let end = (start + count).min(self.line_count());
-        let mut result = Vec::with_capacity(count);
+        let end = start.saturating_add(count).min(self.line_count());
+        let mut result = Vec::with_capacity(end.saturating_sub(start));
 
         for i in start..end {
             let start_byte = self.line_offsets[i];
@@ -54,10 +65,73 @@ impl LineIndex {
 
             let line = &self.mmap[start_byte..end_byte];
             // Remove possible trailing \n or \r\n
-            let line_str = String::from_utf8_lossy(line).trim_end().to_string();
+            let line_str = text_codec::decode_lossy(line, self.encoding).trim_end().to_string();
             result.push(line_str);
         }
 
         result
     }
 }
+
+// Byte offsets where lines start, the first right after the BOM. In UTF-16 a newline is
+// a whole code unit; a 0x0A byte inside another character does not end a line.
+fn line_starts(bytes: &[u8], encoding: TextEncoding) -> Vec<usize> {
+    let bom = encoding.bom_len();
+    let mut starts = vec![bom];
+    let newline = match encoding {
+        TextEncoding::Utf16Le => Some([b'\n', 0]),
+        TextEncoding::Utf16Be => Some([0, b'\n']),
+        _ => None,
+    };
+    match newline {
+        Some(newline) => {
+            for (i, unit) in bytes[bom..].chunks_exact(2).enumerate() {
+                if unit == newline {
+                    starts.push(bom + 2 * i + 2);
+                }
+            }
+        }
+        None => {
+            for (i, &byte) in bytes.iter().enumerate().skip(bom) {
+                if byte == b'\n' {
+                    starts.push(i + 1); // next line starts after '\n'
+                }
+            }
+        }
+    }
+    starts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_lines_stops_at_the_end_of_the_file() {
+        let path = std::env::temp_dir().join(format!("search-tool-lines-{}.txt", std::process::id()));
+        std::fs::write(&path, "one\r\ntwo\nthree").unwrap();
+        let index = LineIndex::new(&path).unwrap();
+        assert_eq!(index.line_count(), 3);
+        assert_eq!(index.get_lines(1, 10), ["two", "three"]);
+        // A huge count neither overflows nor allocates for lines that do not exist
+        assert_eq!(index.get_lines(2, usize::MAX), ["three"]);
+        assert!(index.get_lines(usize::MAX, usize::MAX).is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn lines_are_decoded_in_the_file_encoding() {
+        let path = std::env::temp_dir().join(format!("search-tool-lines-encoded-{}.txt", std::process::id()));
+        let lines = |bytes: &[u8]| {
+            std::fs::write(&path, bytes).unwrap();
+            LineIndex::new(&path).unwrap().get_lines(0, 10)
+        };
+        // "\u{10A}" is 0A 01 in UTF-16LE: a 0x0A byte that is not a newline
+        assert_eq!(lines(b"\xFF\xFEo\0n\0e\0\r\0\n\0\x0A\x01\n\0"), ["one", "\u{10A}", ""]);
+        assert_eq!(lines(b"\xFE\xFF\0o\0\n\0t"), ["o", "t"]);
+        assert_eq!(lines(b"caf\xE9\n\x80"), ["caf\u{e9}", "\u{20ac}"]);
+        // The BOM is not part of the first line
+        assert_eq!(lines(b"\xEF\xBB\xBFfirst\nsecond"), ["first", "second"]);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,217 @@
+// metadata_filter.rs
+// Filters on file metadata (modified time, size, owner), applied by walker.rs while it
+// walks, before any file is read. Times are either absolute ("2025-01-31",
+// "2025-01-31 14:00", RFC 3339) or relative to when the search starts ("2h" = two hours
+// ago; s, m, h, d and w are understood).
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::fs::Metadata;
+use std::time::{Duration, SystemTime};
+
+/// Metadata filters as the frontend sends them; every field is optional
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataFilter {
+    #[serde(default)]
+    pub modified_after: Option<String>,
+    #[serde(default)]
+    pub modified_before: Option<String>,
+    #[serde(default)]
+    pub min_size: Option<u64>, // bytes
+    #[serde(default)]
+    pub max_size: Option<u64>, // bytes
+    #[serde(default)]
+    pub owner: Option<String>, // user name or numeric uid (not supported on Windows)
+}
+
+/// Which filter left a file out; the first failing one in this order is reported
+pub enum Exclusion {
+    Modified,
+    Size,
+    Owner,
+}
+
+/// A `MetadataFilter` with its times and owner resolved
+#[derive(Clone, Debug)]
+pub struct MetadataMatcher {
+    modified_after: Option<SystemTime>,
+    modified_before: Option<SystemTime>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    #[cfg_attr(windows, allow(dead_code))]
+    owner: Option<u32>,
+}
+
+impl MetadataFilter {
+    /// Resolve the filter, or None when no field is set
+    pub fn compile(&self) -> Result<Option<MetadataMatcher>, String> {
+        let now = SystemTime::now();
+        let time = |value: &Option<String>| value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(|v| parse_time(v, now)).transpose();
+        let matcher = MetadataMatcher {
+            modified_after: time(&self.modified_after)?,
+            modified_before: time(&self.modified_before)?,
+            min_size: self.min_size,
+            max_size: self.max_size,
+            owner: self.owner.as_deref().map(str::trim).filter(|o| !o.is_empty()).map(resolve_owner).transpose()?,
+        };
+        if let (Some(after), Some(before)) = (matcher.modified_after, matcher.modified_before) {
+            if after > before {
+                return Err("Modified-after time is later than the modified-before time".to_string());
+            }
+        }
+        if let (Some(min), Some(max)) = (matcher.min_size, matcher.max_size) {
+            if min > max {
+                return Err(format!("Minimum size {} is larger than the maximum size {}", min, max));
+            }
+        }
+        let empty = matcher.modified_after.is_none()
+            && matcher.modified_before.is_none()
+            && matcher.min_size.is_none()
+            && matcher.max_size.is_none()
+            && matcher.owner.is_none();
+        Ok((!empty).then_some(matcher))
+    }
+}
+
+impl MetadataMatcher {
+    pub fn excludes(&self, meta: &Metadata) -> Option<Exclusion> {
+        if self.modified_after.is_some() || self.modified_before.is_some() {
+            let Ok(modified) = meta.modified() else {
+                return Some(Exclusion::Modified);
+            };
+            if self.modified_after.is_some_and(|t| modified < t) || self.modified_before.is_some_and(|t| modified > t) {
+                return Some(Exclusion::Modified);
+            }
+        }
+        if self.min_size.is_some_and(|min| meta.len() < min) || self.max_size.is_some_and(|max| meta.len() > max) {
+            return Some(Exclusion::Size);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            if self.owner.is_some_and(|uid| meta.uid() != uid) {
+                return Some(Exclusion::Owner);
+            }
+        }
+        None
+    }
+}
+
+/// An absolute or relative time as described at the top of this file; also used for
+/// the time window of log mode
+pub fn parse_time(value: &str, now: SystemTime) -> Result<SystemTime, String> {
+    if let Some(ago) = parse_relative(value) {
+        return now.checked_sub(ago).ok_or_else(|| format!("Time out of range: {}", value));
+    }
+    let local = |naive: NaiveDateTime| Local.from_local_datetime(&naive).earliest();
+    let parsed = DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Local))
+        .ok()
+        .or_else(|| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").ok().and_then(local))
+        .or_else(|| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").ok().and_then(local))
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok().and_then(|d| d.and_hms_opt(0, 0, 0)).and_then(local));
+    parsed
+        .map(SystemTime::from)
+        .ok_or_else(|| format!("Unrecognised time '{}' (use e.g. 2h, 3d or 2025-01-31 14:00)", value))
+}
+
+// "90m", "2h", "3 d" -> how long ago
+fn parse_relative(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let unit_at = value.find(|c: char| !c.is_ascii_digit())?;
+    let amount: u64 = value[..unit_at].parse().ok()?;
+    let seconds = match value[unit_at..].trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    Some(Duration::from_secs(amount.checked_mul(seconds)?))
+}
+
+#[cfg(unix)]
+fn resolve_owner(owner: &str) -> Result<u32, String> {
+    if let Ok(uid) = owner.parse::<u32>() {
+        return Ok(uid);
+    }
+    // name:password:uid:... ; users only known to NSS (LDAP, ...) need their numeric uid
+    let passwd = std::fs::read_to_string("/etc/passwd").map_err(|e| format!("Failed to read /etc/passwd: {}", e))?;
+    passwd
+        .lines()
+        .map(|line| line.split(':').collect::<Vec<_>>())
+        .find(|fields| fields.first() == Some(&owner))
+        .and_then(|fields| fields.get(2)?.parse().ok())
+        .ok_or_else(|| format!("Unknown user '{}'; give the numeric uid instead", owner))
+}
+
+#[cfg(windows)]
+fn resolve_owner(_owner: &str) -> Result<u32, String> {
+    Err("Filtering by owner is not supported on Windows".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(date: &str) -> SystemTime {
+        let naive = NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap();
+        Local.from_local_datetime(&naive).earliest().unwrap().into()
+    }
+
+    #[test]
+    fn relative_units() {
+        assert_eq!(parse_relative("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_relative("90m"), Some(Duration::from_secs(90 * 60)));
+        assert_eq!(parse_relative("2h"), Some(Duration::from_secs(2 * 3600)));
+        assert_eq!(parse_relative(" 3 d "), Some(Duration::from_secs(3 * 86400)));
+        assert_eq!(parse_relative("1w"), Some(Duration::from_secs(7 * 86400)));
+        for bad in ["", "h", "2", "2y", "1.5h", "-2h", "18446744073709551615w"] {
+            assert_eq!(parse_relative(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn relative_times_count_back_from_now() {
+        let now = local("2025-01-31 14:00:00");
+        assert_eq!(parse_time("2h", now).unwrap(), local("2025-01-31 12:00:00"));
+        assert_eq!(parse_time("1d", now).unwrap(), local("2025-01-30 14:00:00"));
+    }
+
+    #[test]
+    fn absolute_times() {
+        let now = SystemTime::now();
+        // Date only is local midnight
+        assert_eq!(parse_time("2025-01-31", now).unwrap(), local("2025-01-31 00:00:00"));
+        assert_eq!(parse_time("2025-01-31 14:05", now).unwrap(), local("2025-01-31 14:05:00"));
+        assert_eq!(parse_time("2025-01-31 14:05:09", now).unwrap(), local("2025-01-31 14:05:09"));
+        // RFC 3339 carries its own offset
+        let utc = SystemTime::UNIX_EPOCH + Duration::from_secs(1_738_332_000);
+        assert_eq!(parse_time("2025-01-31T14:00:00Z", now).unwrap(), utc);
+        assert_eq!(parse_time("2025-01-31T16:00:00+02:00", now).unwrap(), utc);
+
+        for bad in ["yesterday", "31/01/2025", "2025-13-01", "2025-01-31T14:00"] {
+            assert!(parse_time(bad, now).unwrap_err().contains("Unrecognised time"), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn compile_checks_the_window() {
+        let filter = |after: &str, before: &str| MetadataFilter {
+            modified_after: Some(after.to_string()),
+            modified_before: Some(before.to_string()),
+            ..MetadataFilter::default()
+        };
+        assert!(filter("2025-02-01", "2025-01-01").compile().unwrap_err().contains("later than"));
+        // One day ago is after two days ago
+        assert!(filter("1d", "2d").compile().is_err());
+        assert!(filter("2d", "1d").compile().unwrap().is_some());
+        assert!(filter(" ", "").compile().unwrap().is_none());
+
+        let sizes = MetadataFilter { min_size: Some(10), max_size: Some(5), ..MetadataFilter::default() };
+        assert!(sizes.compile().unwrap_err().contains("Minimum size 10"));
+        assert!(MetadataFilter::default().compile().unwrap().is_none());
+    }
+}
@@ -0,0 +1,403 @@
+// replace.rs
+// Search and replace across the files of a finished search.
+// The pattern comes from the search's own options (literal or regex, case, whole words),
+// so the replacement hits exactly what was found. Like ripgrep, matching is line by line.
+//
+// `preview` is a dry run that returns a per-file diff; `apply` rewrites the files
+// atomically in their original encoding and line endings and records an undo journal entry.
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::atomic_write::write_all_atomic;
+use crate::result_store::StoredResults;
+use crate::search::SearchOptions;
+use crate::settings::Settings;
+use crate::text_codec::{self, DecodedText, LineEnding, TextEncoding};
+use crate::undo_journal::{self, sha256_hex, FileChange};
+
+/// Identifies one match: path as in the results, 1-based line and byte column
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MatchKey {
+    pub path: String,
+    pub line: u64,
+    pub column: usize,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceRequest {
+    pub replacement: String,
+    // Treat the replacement as plain text. Otherwise $1 / ${name} expand capture groups.
+    // Defaults to literal when the search itself was literal (whole phrase).
+    #[serde(default)]
+    pub literal_replacement: Option<bool>,
+    // Individual matches to leave untouched
+    #[serde(default)]
+    pub excluded: Vec<MatchKey>,
+    // Whole files to leave untouched
+    #[serde(default)]
+    pub excluded_files: Vec<String>,
+    // Checksums returned by the preview; a file that changed since is skipped on apply
+    #[serde(default)]
+    pub expected_sha256: HashMap<String, String>,
+}
+
+/// One changed line in the preview
+#[derive(Serialize, Debug)]
+pub struct DiffLine {
+    pub line: u64,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FilePreview {
+    pub path: String,
+    pub replacements: usize,
+    pub excluded: usize,
+    pub sha256: String, // of the file as read; pass back in expected_sha256 on apply
+    pub encoding: TextEncoding,
+    pub line_ending: LineEnding,
+    pub diff: Vec<DiffLine>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FileError {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReplacePreview {
+    pub files: Vec<FilePreview>,
+    pub errors: Vec<FileError>,
+    pub total_replacements: usize,
+    pub truncated: bool, // the search hit its result limit, so some files may be missing
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReplaceReport {
+    pub files_changed: Vec<String>,
+    pub total_replacements: usize,
+    pub errors: Vec<FileError>,
+    pub undo_id: Option<String>,
+    pub undo_error: Option<String>, // the files were changed, but no undo entry could be saved
+}
+
+// A file after replacement, before anything is written
+struct PlannedFile {
+    path: String,
+    abs_path: PathBuf,
+    original: Vec<u8>,
+    decoded: DecodedText,
+    new_text: String,
+    replacements: usize,
+    excluded: usize,
+    diff: Vec<DiffLine>,
+}
+
+/// Build the regex ripgrep used for `options`
+pub fn build_regex(options: &SearchOptions, settings: &Settings) -> Result<Regex, String> {
+    let defaults = &settings.search;
+    let mut pattern = if options.whole_phrase.unwrap_or(defaults.whole_phrase) {
+        regex::escape(&options.query)
+    } else {
+        options.query.clone()
+    };
+    if options.whole_words.unwrap_or(defaults.whole_words) {
+        pattern = format!(r"\b(?:{})\b", pattern);
+    }
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive.unwrap_or(defaults.case_sensitive))
+        .build()
+        .map_err(|e| format!("Invalid pattern: {}", e))
+}
+
+/// Dry run: what `apply` would change, file by file
+pub fn preview(stored: &StoredResults, request: &ReplaceRequest, settings: &Settings) -> Result<ReplacePreview, String> {
+    let (planned, errors) = plan(stored, request, settings)?;
+    let total_replacements = planned.iter().map(|p| p.replacements).sum();
+    let files = planned
+        .into_iter()
+        .map(|p| FilePreview {
+            sha256: sha256_hex(&p.original),
+            path: p.path,
+            replacements: p.replacements,
+            excluded: p.excluded,
+            encoding: p.decoded.encoding,
+            line_ending: p.decoded.line_ending,
+            diff: p.diff,
+        })
+        .collect();
+    Ok(ReplacePreview {
+        files,
+        errors,
+        total_replacements,
+        truncated: stored.outcome.truncated,
+    })
+}
+
+/// Rewrite every file with replacements and record one undo journal entry for all of them
+pub fn apply(stored: &StoredResults, request: &ReplaceRequest, settings: &Settings) -> Result<ReplaceReport, String> {
+    apply_in(&undo_journal::journal_dir(), stored, request, settings)
+}
+
+fn apply_in(journal: &Path, stored: &StoredResults, request: &ReplaceRequest, settings: &Settings) -> Result<ReplaceReport, String> {
+    let (planned, mut errors) = plan(stored, request, settings)?;
+
+    let mut written: Vec<(PlannedFile, Vec<u8>)> = Vec::new();
+    for file in planned {
+        if let Some(expected) = request.expected_sha256.get(&file.path) {
+            if *expected != sha256_hex(&file.original) {
+                errors.push(FileError {
+                    path: file.path,
+                    error: "File changed since the preview".to_string(),
+                });
+                continue;
+            }
+        }
+        match text_codec::encode(&file.new_text, file.decoded.encoding) {
+            Ok(bytes) => written.push((file, bytes)),
+            Err(e) => errors.push(FileError { path: file.path, error: e }),
+        }
+    }
+
+    // All or nothing: if one file cannot be written, none is changed
+    let batch: Vec<(&Path, &[u8])> = written.iter().map(|(f, bytes)| (f.abs_path.as_path(), bytes.as_slice())).collect();
+    if let Err(failure) = write_all_atomic(&batch) {
+        let path = &written[failure.index].0.path;
+        if failure.committed == 0 {
+            return Err(format!("Nothing was replaced: failed to write {}: {}", path, failure.error));
+        }
+        errors.push(FileError { path: path.clone(), error: format!("Failed to write: {}", failure.error) });
+        for (file, _) in &written[failure.index + 1..] {
+            errors.push(FileError { path: file.path.clone(), error: "Not written after an earlier failure".to_string() });
+        }
+        written.truncate(failure.committed);
+    }
+
+    // The files are written by now; a journal failure is reported, not returned as an error
+    let total_replacements = written.iter().map(|(f, _)| f.replacements).sum();
+    let journaled = if written.is_empty() {
+        Ok(None)
+    } else {
+        let changes: Vec<FileChange> = written
+            .iter()
+            .map(|(f, bytes)| FileChange { path: &f.abs_path, original: &f.original, written: bytes })
+            .collect();
+        let description = format!(
+            "Replace '{}' with '{}' in {} file(s)",
+            stored.options.query,
+            request.replacement,
+            written.len()
+        );
+        undo_journal::record_in(journal, "replace", &description, &changes).map(|entry| Some(entry.id))
+    };
+    let (undo_id, undo_error) = match journaled {
+        Ok(id) => (id, None),
+        Err(e) => (None, Some(e)),
+    };
+
+    Ok(ReplaceReport {
+        files_changed: written.into_iter().map(|(f, _)| f.abs_path.display().to_string()).collect(),
+        total_replacements,
+        errors,
+        undo_id,
+        undo_error,
+    })
+}
+
+fn plan(
+    stored: &StoredResults,
+    request: &ReplaceRequest,
+    settings: &Settings,
+) -> Result<(Vec<PlannedFile>, Vec<FileError>), String> {
+    let regex = build_regex(&stored.options, settings)?;
+    let literal = request
+        .literal_replacement
+        .unwrap_or_else(|| stored.options.whole_phrase.unwrap_or(settings.search.whole_phrase));
+    let excluded: HashSet<&MatchKey> = request.excluded.iter().collect();
+    let excluded_files: HashSet<&str> = request.excluded_files.iter().map(String::as_str).collect();
+
+    let mut planned = Vec::new();
+    let mut errors = Vec::new();
+    let mut seen = HashSet::new();
+    for m in stored.matches_by_file() {
+        if !seen.insert(m.path.as_str()) || excluded_files.contains(m.path.as_str()) {
+            continue;
+        }
+        let abs_path = stored.abs_path(&m.path);
+        match plan_file(&m.path, &abs_path, &regex, &request.replacement, literal, &excluded) {
+            Ok(Some(file)) => planned.push(file),
+            Ok(None) => {}
+            Err(error) => errors.push(FileError { path: m.path.clone(), error }),
+        }
+    }
+    Ok((planned, errors))
+}
+
+// Replace matches line by line. Returns None if nothing in the file changes.
+fn plan_file(
+    path: &str,
+    abs_path: &Path,
+    regex: &Regex,
+    replacement: &str,
+    literal: bool,
+    excluded: &HashSet<&MatchKey>,
+) -> Result<Option<PlannedFile>, String> {
+    let original = std::fs::read(abs_path).map_err(|e| format!("Failed to read: {}", e))?;
+    let decoded = text_codec::decode(&original)?;
+
+    let mut new_text = String::with_capacity(decoded.text.len());
+    let mut diff = Vec::new();
+    let mut replacements = 0;
+    let mut skipped = 0;
+
+    for (idx, raw_line) in decoded.text.split_inclusive('\n').enumerate() {
+        let line_no = idx as u64 + 1;
+        let content_len = raw_line.trim_end_matches(['\r', '\n']).len();
+        let (content, terminator) = raw_line.split_at(content_len);
+
+        let mut new_line = String::with_capacity(content.len());
+        let mut last = 0;
+        let mut changed = false;
+        for caps in regex.captures_iter(content) {
+            let whole = caps.get(0).expect("group 0 always participates");
+            if whole.as_str().is_empty() {
+                continue; // never "replace" empty matches
+            }
+            let key = MatchKey { path: path.to_string(), line: line_no, column: whole.start() + 1 };
+            if excluded.contains(&key) {
+                skipped += 1;
+                continue;
+            }
+            new_line.push_str(&content[last..whole.start()]);
+            if literal {
+                new_line.push_str(replacement);
+            } else {
+                caps.expand(replacement, &mut new_line);
+            }
+            last = whole.end();
+            replacements += 1;
+            changed = true;
+        }
+
+        if changed {
+            new_line.push_str(&content[last..]);
+            let new_line = text_codec::normalize_newlines(&new_line, decoded.line_ending);
+            diff.push(DiffLine { line: line_no, old: content.to_string(), new: new_line.clone() });
+            new_text.push_str(&new_line);
+        } else {
+            new_text.push_str(content);
+        }
+        new_text.push_str(terminator);
+    }
+
+    if replacements == 0 {
+        return Ok(None);
+    }
+    Ok(Some(PlannedFile {
+        path: path.to_string(),
+        abs_path: abs_path.to_path_buf(),
+        original,
+        decoded,
+        new_text,
+        replacements,
+        excluded: skipped,
+        diff,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+    // Replace `pattern` in a temp file holding `bytes`; returns the bytes apply would write
+    fn replace_in(bytes: &[u8], pattern: &str, replacement: &str, literal: bool, excluded: &[MatchKey]) -> Option<Vec<u8>> {
+        let n = NEXT_FILE.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("search-tool-replace-{}-{}.txt", std::process::id(), n));
+        std::fs::write(&path, bytes).unwrap();
+        let excluded: HashSet<&MatchKey> = excluded.iter().collect();
+        let planned = plan_file("./f.txt", &path, &Regex::new(pattern).unwrap(), replacement, literal, &excluded);
+        let _ = std::fs::remove_file(&path);
+        let planned = planned.unwrap()?;
+        assert_eq!(planned.original, bytes);
+        Some(text_codec::encode(&planned.new_text, planned.decoded.encoding).unwrap())
+    }
+
+    #[test]
+    fn utf8_bom_is_kept() {
+        assert_eq!(replace_in(b"\xEF\xBB\xBFold\nold\n", "old", "new", true, &[]).unwrap(), b"\xEF\xBB\xBFnew\nnew\n");
+    }
+
+    #[test]
+    fn utf16_is_written_back_as_utf16() {
+        let file: Vec<u8> = b"\xFF\xFE".iter().copied().chain("old\r\nx\r\n".encode_utf16().flat_map(u16::to_le_bytes)).collect();
+        let expected: Vec<u8> = b"\xFF\xFE".iter().copied().chain("new\r\nx\r\n".encode_utf16().flat_map(u16::to_le_bytes)).collect();
+        assert_eq!(replace_in(&file, "old", "new", true, &[]).unwrap(), expected);
+    }
+
+    #[test]
+    fn crlf_is_kept_and_inserted_newlines_follow_it() {
+        assert_eq!(replace_in(b"a old\r\nb\r\n", "old", "x\ny", true, &[]).unwrap(), b"a x\r\ny\r\nb\r\n");
+    }
+
+    #[test]
+    fn windows_1252_is_written_back_as_windows_1252() {
+        assert_eq!(replace_in(b"caf\xE9 old\n", "old", "\u{e9}t\u{e9}", true, &[]).unwrap(), b"caf\xE9 \xE9t\xE9\n");
+    }
+
+    #[test]
+    fn captures_expand_unless_literal() {
+        assert_eq!(replace_in(b"id=42\n", r"id=(\d+)", "n=$1", false, &[]).unwrap(), b"n=42\n");
+        assert_eq!(replace_in(b"id=42\n", r"id=(\d+)", "n=$1", true, &[]).unwrap(), b"n=$1\n");
+    }
+
+    #[test]
+    fn excluded_matches_stay() {
+        let keep = MatchKey { path: "./f.txt".to_string(), line: 1, column: 5 };
+        assert_eq!(replace_in(b"old old\n", "old", "new", true, std::slice::from_ref(&keep)).unwrap(), b"new old\n");
+        assert!(replace_in(b"old\n", "old", "new", true, &[MatchKey { column: 1, ..keep }]).is_none());
+    }
+
+    #[test]
+    fn a_journal_failure_still_reports_the_replace() {
+        let dir = std::env::temp_dir().join(format!("search-tool-replace-journal-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.txt"), "old value\n").unwrap();
+        // A file where the journal directory should be, so the journal cannot be written
+        let journal = dir.join("journal");
+        std::fs::write(&journal, "").unwrap();
+
+        let options: SearchOptions = serde_json::from_value(serde_json::json!({ "query": "old", "path": dir })).unwrap();
+        let outcome = crate::search::SearchOutcome {
+            matches: vec![crate::search::SearchMatch {
+                path: "./a.txt".to_string(),
+                line: 1,
+                text: "old value".to_string(),
+                ranges: vec![crate::search::MatchRange { start: 0, end: 3 }],
+                binary_offset: None,
+                timestamp: None,
+                level: None,
+            }],
+            ..Default::default()
+        };
+        let stored = StoredResults::new(dir.clone(), options, outcome);
+        let request: ReplaceRequest = serde_json::from_value(serde_json::json!({ "replacement": "new" })).unwrap();
+
+        let report = apply_in(&journal, &stored, &request, &Settings::default()).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("a.txt")).unwrap(), "new value\n");
+        assert_eq!((report.files_changed.len(), report.total_replacements), (1, 1));
+        assert!(report.errors.is_empty());
+        assert_eq!(report.undo_id, None);
+        assert!(report.undo_error.unwrap().contains("journal"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,490 @@
+// result_store.rs
+// Finished search results kept in the backend, so the frontend only ever receives a page.
+// Matches are grouped by file (or by directory) and sorted on request; the matches of
+// a file group are fetched separately when the group is expanded. Log-mode searches can
+// also list their matches across files by timestamp (the timeline), paged the same way.
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::search::{SearchMatch, SearchOptions, SearchOutcome};
+
+// Pages larger than this are clamped, whatever the frontend asks for
+const MAX_PAGE_SIZE: usize = 1000;
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SortKey {
+    #[default]
+    Path,
+    MatchCount,
+    Mtime,
+    Size,
+    Time, // earliest log timestamp among the file's matches (log mode)
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GroupBy {
+    #[default]
+    File,
+    Directory,
+}
+
+/// Which page of groups to return and how to order them
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PageRequest {
+    pub offset: usize,
+    pub limit: usize,
+    pub sort_by: SortKey,
+    pub descending: bool,
+    pub group_by: GroupBy,
+    // Only files inside this directory (used to expand a directory group)
+    pub directory: Option<String>,
+}
+
+/// One row of the results list: a file, or a directory of files
+#[derive(Serialize, Clone, Debug)]
+pub struct ResultGroup {
+    pub key: String,        // file path or directory, relative to the search root
+    pub kind: &'static str, // "file" or "directory"
+    pub file_count: usize,
+    pub match_count: usize,
+    pub line_count: usize,
+    pub size: Option<u64>,
+    pub modified: Option<i64>, // unix time in milliseconds
+    pub first_timestamp: Option<i64>, // log mode: earliest match, unix time in milliseconds
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResultsPage {
+    pub total_groups: usize,
+    pub offset: usize,
+    pub groups: Vec<ResultGroup>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MatchesPage {
+    pub path: String,
+    pub total: usize,
+    pub offset: usize,
+    pub matches: Vec<SearchMatch>,
+}
+
+/// A page of the timeline: matches of all files, oldest first
+#[derive(Serialize, Debug)]
+pub struct TimelinePage {
+    pub total: usize,
+    pub offset: usize,
+    pub sources: usize, // files the matches come from
+    pub matches: Vec<SearchMatch>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct FileMeta {
+    size: Option<u64>,
+    modified: Option<i64>,
+}
+
+struct FileEntry {
+    path: String,
+    match_indices: Vec<usize>, // into outcome.matches, in line order
+    match_count: usize,
+    first_timestamp: Option<i64>,
+    meta: OnceCell<FileMeta>, // stat'ed the first time it is needed
+}
+
+/// The outcome of one search plus the per-file index used for paging
+pub struct StoredResults {
+    pub root: PathBuf,
+    pub options: SearchOptions,
+    pub outcome: SearchOutcome,
+    files: Vec<FileEntry>,
+    by_path: HashMap<String, usize>,
+    by_time: OnceCell<Vec<usize>>, // into outcome.matches, sorted the first time it is needed
+}
+
+impl StoredResults {
+    pub fn new(root: PathBuf, options: SearchOptions, outcome: SearchOutcome) -> Self {
+        let mut files: Vec<FileEntry> = Vec::new();
+        let mut by_path: HashMap<String, usize> = HashMap::new();
+
+        for (i, m) in outcome.matches.iter().enumerate() {
+            let idx = *by_path.entry(m.path.clone()).or_insert_with(|| {
+                files.push(FileEntry {
+                    path: m.path.clone(),
+                    match_indices: Vec::new(),
+                    match_count: 0,
+                    first_timestamp: None,
+                    meta: OnceCell::new(),
+                });
+                files.len() - 1
+            });
+            let entry = &mut files[idx];
+            entry.match_indices.push(i);
+            entry.match_count += m.ranges.len().max(1);
+            if let Some(timestamp) = m.timestamp {
+                entry.first_timestamp = Some(entry.first_timestamp.map_or(timestamp, |t| t.min(timestamp)));
+            }
+        }
+
+        Self { root, options, outcome, files, by_path, by_time: OnceCell::new() }
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Absolute path of a result path ("./src/main.rs" -> "<root>/src/main.rs")
+    pub fn abs_path(&self, path: &str) -> PathBuf {
+        self.root.join(path.strip_prefix("./").unwrap_or(path))
+    }
+
+    /// Absolute paths of every file with matches
+    pub fn abs_paths(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.files.iter().map(move |f| self.abs_path(&f.path))
+    }
+
+    /// True if `path` (with or without the "./" ripgrep prints) has matches in these results
+    pub fn contains_file(&self, path: &str) -> bool {
+        let bare = path.strip_prefix("./").unwrap_or(path);
+        self.by_path.contains_key(bare) || self.by_path.contains_key(&format!("./{}", bare))
+    }
+
+    /// Return one page of file or directory groups
+    pub fn page(&self, request: &PageRequest) -> ResultsPage {
+        let files: Vec<&FileEntry> = match &request.directory {
+            Some(dir) => self.files.iter().filter(|f| parent_dir(&f.path) == dir.as_str()).collect(),
+            None => self.files.iter().collect(),
+        };
+
+        // Only stat files when the sort order needs it; the page itself is stat'ed below
+        if matches!(request.sort_by, SortKey::Mtime | SortKey::Size) {
+            for f in &files {
+                self.meta(f);
+            }
+        }
+
+        let mut groups = match request.group_by {
+            GroupBy::File => files.iter().map(|f| self.file_group(f)).collect::<Vec<_>>(),
+            GroupBy::Directory => self.directory_groups(&files, request.sort_by),
+        };
+        sort_groups(&mut groups, request.sort_by, request.descending);
+
+        let total_groups = groups.len();
+        let limit = request.limit.clamp(1, MAX_PAGE_SIZE);
+        let mut page: Vec<ResultGroup> = groups.into_iter().skip(request.offset).take(limit).collect();
+
+        // Fill in size/mtime for the visible file rows
+        for group in page.iter_mut().filter(|g| g.kind == "file") {
+            if let Some(&idx) = self.by_path.get(&group.key) {
+                let meta = self.meta(&self.files[idx]);
+                group.size = meta.size;
+                group.modified = meta.modified;
+            }
+        }
+
+        ResultsPage { total_groups, offset: request.offset, groups: page }
+    }
+
+    /// Matches of one file, for expanding its group
+    pub fn file_matches(&self, path: &str, offset: usize, limit: usize) -> Option<MatchesPage> {
+        let entry = &self.files[*self.by_path.get(path)?];
+        let limit = limit.clamp(1, MAX_PAGE_SIZE);
+        let matches = entry
+            .match_indices
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .map(|&i| self.outcome.matches[i].clone())
+            .collect();
+        Some(MatchesPage {
+            path: entry.path.clone(),
+            total: entry.match_indices.len(),
+            offset,
+            matches,
+        })
+    }
+
+    /// Matches in file order (files as ripgrep reported them, lines ascending)
+    pub fn matches_by_file(&self) -> impl Iterator<Item = &SearchMatch> {
+        self.files
+            .iter()
+            .flat_map(move |f| f.match_indices.iter().map(move |&i| &self.outcome.matches[i]))
+    }
+
+    /// Matches of all files by log timestamp. A match without one sorts with the match
+    /// before it in its file (log mode already gives continuation lines their entry's
+    /// timestamp; this covers entries it could not find). Ties keep their file order, and
+    /// matches with nothing before them go last.
+    pub fn matches_by_time(&self) -> impl Iterator<Item = &SearchMatch> {
+        let order = self.by_time.get_or_init(|| {
+            let mut keyed: Vec<(Option<i64>, usize)> = Vec::with_capacity(self.outcome.matches.len());
+            for file in &self.files {
+                let mut carried = None;
+                for &i in &file.match_indices {
+                    carried = self.outcome.matches[i].timestamp.or(carried);
+                    keyed.push((carried, i));
+                }
+            }
+            keyed.sort_by_key(|&(timestamp, _)| (timestamp.is_none(), timestamp));
+            keyed.into_iter().map(|(_, i)| i).collect()
+        });
+        order.iter().map(move |&i| &self.outcome.matches[i])
+    }
+
+    /// One page of `matches_by_time`
+    pub fn timeline(&self, offset: usize, limit: usize) -> TimelinePage {
+        let limit = limit.clamp(1, MAX_PAGE_SIZE);
+        TimelinePage {
+            total: self.outcome.matches.len(),
+            offset,
+            sources: self.files.len(),
+            matches: self.matches_by_time().skip(offset).take(limit).cloned().collect(),
+        }
+    }
+
+    /// Chronological for log-mode searches, by file otherwise
+    pub fn matches_in_order(&self) -> Box<dyn Iterator<Item = &SearchMatch> + '_> {
+        if self.options.log_filter.is_some() {
+            Box::new(self.matches_by_time())
+        } else {
+            Box::new(self.matches_by_file())
+        }
+    }
+
+    fn meta(&self, entry: &FileEntry) -> FileMeta {
+        *entry.meta.get_or_init(|| read_meta(&self.root, &entry.path))
+    }
+
+    // Size and mtime are only set when already stat'ed (see `page`)
+    fn file_group(&self, entry: &FileEntry) -> ResultGroup {
+        let meta = entry.meta.get().copied().unwrap_or_default();
+        ResultGroup {
+            key: entry.path.clone(),
+            kind: "file",
+            file_count: 1,
+            match_count: entry.match_count,
+            line_count: entry.match_indices.len(),
+            size: meta.size,
+            modified: meta.modified,
+            first_timestamp: entry.first_timestamp,
+        }
+    }
+
+    fn directory_groups(&self, files: &[&FileEntry], sort_by: SortKey) -> Vec<ResultGroup> {
+        let mut dirs: Vec<ResultGroup> = Vec::new();
+        let mut by_dir: HashMap<&str, usize> = HashMap::new();
+        let needs_meta = matches!(sort_by, SortKey::Mtime | SortKey::Size);
+
+        for f in files {
+            let dir = parent_dir(&f.path);
+            let idx = *by_dir.entry(dir).or_insert_with(|| {
+                dirs.push(ResultGroup {
+                    key: dir.to_string(),
+                    kind: "directory",
+                    file_count: 0,
+                    match_count: 0,
+                    line_count: 0,
+                    size: None,
+                    modified: None,
+                    first_timestamp: None,
+                });
+                dirs.len() - 1
+            });
+            let group = &mut dirs[idx];
+            group.file_count += 1;
+            group.match_count += f.match_count;
+            group.line_count += f.match_indices.len();
+            group.first_timestamp = match (group.first_timestamp, f.first_timestamp) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            if needs_meta {
+                let meta = self.meta(f);
+                group.size = Some(group.size.unwrap_or(0) + meta.size.unwrap_or(0));
+                group.modified = group.modified.max(meta.modified);
+            }
+        }
+        dirs
+    }
+}
+
+fn sort_groups(groups: &mut [ResultGroup], sort_by: SortKey, descending: bool) {
+    groups.sort_by(|a, b| {
+        let order = match sort_by {
+            SortKey::Path => a.key.cmp(&b.key),
+            SortKey::MatchCount => a.match_count.cmp(&b.match_count),
+            SortKey::Mtime => a.modified.cmp(&b.modified),
+            SortKey::Size => a.size.cmp(&b.size),
+            // Files without timestamps go last either way
+            SortKey::Time => match (a.first_timestamp, b.first_timestamp) {
+                (Some(x), Some(y)) => x.cmp(&y),
+                (x, y) => return x.is_none().cmp(&y.is_none()).then_with(|| a.key.cmp(&b.key)),
+            },
+        };
+        // Ties are always broken by path so paging is stable
+        let order = if descending { order.reverse() } else { order };
+        order.then_with(|| a.key.cmp(&b.key))
+    });
+}
+
+// "./src/main.rs" -> "./src", "main.rs" -> "."
+fn parent_dir(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) => "/",
+        Some(i) => &path[..i],
+        None => ".",
+    }
+}
+
+fn read_meta(root: &Path, path: &str) -> FileMeta {
+    let relative = path.strip_prefix("./").unwrap_or(path);
+    match std::fs::metadata(root.join(relative)) {
+        Ok(meta) => FileMeta {
+            size: Some(meta.len()),
+            modified: meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as i64),
+        },
+        Err(_) => FileMeta::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_match(path: &str, line: u64, timestamp: Option<i64>) -> SearchMatch {
+        SearchMatch {
+            path: path.to_string(),
+            line,
+            text: format!("{}:{}", path, line),
+            ranges: Vec::new(),
+            binary_offset: None,
+            timestamp,
+            level: None,
+        }
+    }
+
+    fn stored(matches: Vec<SearchMatch>) -> StoredResults {
+        let options: SearchOptions = serde_json::from_value(serde_json::json!({ "query": "req", "path": "." })).unwrap();
+        StoredResults::new(PathBuf::from("."), options, SearchOutcome { matches, ..Default::default() })
+    }
+
+    fn keys<'a>(matches: impl Iterator<Item = &'a SearchMatch>) -> Vec<(String, u64)> {
+        matches.map(|m| (m.path.clone(), m.line)).collect()
+    }
+
+    #[test]
+    fn timeline_interleaves_files_by_timestamp() {
+        let results = stored(vec![
+            log_match("./gateway.log", 1, Some(100)),
+            log_match("./gateway.log", 2, Some(400)),
+            log_match("./service-a.log", 7, Some(200)),
+            log_match("./service-b.log", 3, Some(300)),
+            log_match("./service-b.log", 4, Some(400)),
+        ]);
+        assert_eq!(
+            keys(results.matches_by_time()),
+            [("./gateway.log", 1), ("./service-a.log", 7), ("./service-b.log", 3), ("./gateway.log", 2), ("./service-b.log", 4)]
+                .map(|(p, l)| (p.to_string(), l))
+        );
+    }
+
+    #[test]
+    fn untimed_matches_stay_with_the_match_before_them() {
+        let results = stored(vec![
+            log_match("./a.log", 1, None), // nothing before it in its file
+            log_match("./a.log", 2, Some(300)),
+            log_match("./a.log", 3, None), // stack trace line under line 2
+            log_match("./b.log", 1, Some(200)),
+            log_match("./b.log", 2, Some(400)),
+        ]);
+        assert_eq!(
+            keys(results.matches_by_time()),
+            [("./b.log", 1), ("./a.log", 2), ("./a.log", 3), ("./b.log", 2), ("./a.log", 1)].map(|(p, l)| (p.to_string(), l))
+        );
+    }
+
+    #[test]
+    fn timeline_pages_through_all_matches() {
+        let results = stored((0..5).map(|i| log_match("./x.log", i + 1, Some(10 - i as i64))).collect());
+        let first = results.timeline(0, 2);
+        assert_eq!((first.total, first.offset, first.sources), (5, 0, 1));
+        assert_eq!(first.matches.iter().map(|m| m.line).collect::<Vec<_>>(), [5, 4]);
+        assert_eq!(results.timeline(2, 2).matches.iter().map(|m| m.line).collect::<Vec<_>>(), [3, 2]);
+        assert_eq!(results.timeline(4, 2).matches.iter().map(|m| m.line).collect::<Vec<_>>(), [1]);
+        assert!(results.timeline(5, 2).matches.is_empty());
+        // A zero limit still returns a page
+        assert_eq!(results.timeline(0, 0).matches.len(), 1);
+    }
+
+    fn page(results: &StoredResults, request: serde_json::Value) -> Vec<(String, usize, usize)> {
+        let request: PageRequest = serde_json::from_value(request).unwrap();
+        results.page(&request).groups.into_iter().map(|g| (g.key, g.file_count, g.line_count)).collect()
+    }
+
+    fn grouped() -> StoredResults {
+        stored(vec![
+            log_match("./src/a.rs", 1, None),
+            log_match("./src/b.rs", 1, Some(300)),
+            log_match("./src/b.rs", 2, None),
+            log_match("./src/b.rs", 5, None),
+            log_match("./docs/readme.md", 4, Some(200)),
+            log_match("./main.rs", 8, None),
+        ])
+    }
+
+    #[test]
+    fn files_page_in_the_requested_order() {
+        let results = grouped();
+        let first = results.page(&PageRequest { limit: 2, ..Default::default() });
+        assert_eq!((first.total_groups, first.offset), (4, 0));
+        assert_eq!(
+            page(&results, serde_json::json!({ "offset": 0, "limit": 2 })),
+            [("./docs/readme.md".to_string(), 1, 1), ("./main.rs".to_string(), 1, 1)]
+        );
+        assert_eq!(page(&results, serde_json::json!({ "offset": 2, "limit": 2 })).len(), 2);
+        assert!(page(&results, serde_json::json!({ "offset": 4, "limit": 2 })).is_empty());
+
+        // Ties on match count are broken by path, so pages never overlap
+        let by_count = page(&results, serde_json::json!({ "limit": 10, "sortBy": "matchCount", "descending": true }));
+        let keys: Vec<&str> = by_count.iter().map(|g| g.0.as_str()).collect();
+        assert_eq!(keys, ["./src/b.rs", "./docs/readme.md", "./main.rs", "./src/a.rs"]);
+
+        // Files without a log timestamp go last
+        let by_time = page(&results, serde_json::json!({ "limit": 10, "sortBy": "time" }));
+        let keys: Vec<&str> = by_time.iter().map(|g| g.0.as_str()).collect();
+        assert_eq!(keys, ["./docs/readme.md", "./src/b.rs", "./main.rs", "./src/a.rs"]);
+    }
+
+    #[test]
+    fn directories_group_their_files() {
+        let results = grouped();
+        assert_eq!(
+            page(&results, serde_json::json!({ "limit": 10, "groupBy": "directory" })),
+            [(".".to_string(), 1, 1), ("./docs".to_string(), 1, 1), ("./src".to_string(), 2, 4)]
+        );
+        assert_eq!(
+            page(&results, serde_json::json!({ "limit": 10, "directory": "./src" })),
+            [("./src/a.rs".to_string(), 1, 1), ("./src/b.rs".to_string(), 1, 3)]
+        );
+    }
+
+    #[test]
+    fn file_matches_page_within_a_file() {
+        let results = grouped();
+        let page = results.file_matches("./src/b.rs", 1, 1).unwrap();
+        assert_eq!((page.total, page.offset), (3, 1));
+        assert_eq!(page.matches.iter().map(|m| m.line).collect::<Vec<_>>(), [2]);
+        assert!(results.file_matches("./src/c.rs", 0, 10).is_none());
+        assert!(results.contains_file("src/b.rs") && results.contains_file("./main.rs"));
+    }
+}
@@ -0,0 +1,917 @@
+// search.rs
+// The search engine: walks the root (walker.rs), runs ripgrep with `--json` on batches
+// of the files found and streams the output into structured matches and stats.
+// When the root has a content index (content_index.rs), files it rules out are skipped.
+// It knows nothing about Tauri; search_manager.rs and the commands in main.rs drive it.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::binary_view;
+use crate::content_index::{self, IndexCheck, QueryTrigrams};
+use crate::log_format::{EntryHeads, LogFilter, LogMatcher, Severity};
+use crate::metadata_filter::{MetadataFilter, MetadataMatcher};
+use crate::settings::Settings;
+use crate::text_codec;
+use crate::walker::{self, SkippedFiles, WalkOptions, WalkProgress};
+
+// Embed the ripgrep binary (Windows builds ship it inside the exe)
+#[cfg(windows)]
+const RG_BINARY: &[u8] = include_bytes!("../bin/rg.exe");
+
+#[cfg(windows)]
+const RG_FILE_PREFIX: &str = "search-tool-rg-";
+
+// Hide the console window ripgrep would otherwise open
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+// How often the progress callback fires while a search runs
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+// Files are passed to ripgrep as arguments; keep each run's command line well under
+// the Windows limit of 32767 characters
+#[cfg(windows)]
+const MAX_BATCH_ARGS_LEN: usize = 24_000;
+#[cfg(not(windows))]
+const MAX_BATCH_ARGS_LEN: usize = 128 * 1024;
+
+// Batches the walker may get ahead of ripgrep
+const BATCH_QUEUE: usize = 4;
+
+// Rows kept in each breakdown
+const MAX_BREAKDOWN_ROWS: usize = 50;
+
+// -----------------------------------------------------------
+// Options, results and stats
+// -----------------------------------------------------------
+
+/// Everything needed to run one search. Flags the frontend omits come from the settings.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchOptions {
+    pub query: String,
+    pub path: String,
+    #[serde(default)]
+    pub file_filter: Option<String>,
+    #[serde(default)]
+    pub case_sensitive: Option<bool>,
+    #[serde(default)]
+    pub whole_phrase: Option<bool>,
+    #[serde(default)]
+    pub whole_words: Option<bool>,
+    #[serde(default)]
+    pub metadata_filter: Option<MetadataFilter>, // modified time, size and owner
+    #[serde(default)]
+    pub log_filter: Option<LogFilter>, // log mode: parse timestamps and levels, filter on them
+}
+
+impl SearchOptions {
+    /// Check the options before a search ID is handed out
+    pub fn validate(&self) -> Result<(), String> {
+        if self.query.trim().is_empty() {
+            return Err("Search query cannot be empty".to_string());
+        }
+        if self.path.trim().is_empty() {
+            return Err("Search path cannot be empty".to_string());
+        }
+        if !Path::new(&self.path).exists() {
+            return Err(format!("Path does not exist: {}", self.path));
+        }
+        if let Some(filter) = &self.log_filter {
+            filter.validate()?;
+        }
+        self.metadata_matcher().map(|_| ())
+    }
+
+    pub fn metadata_matcher(&self) -> Result<Option<MetadataMatcher>, String> {
+        self.metadata_filter.as_ref().map_or(Ok(None), MetadataFilter::compile)
+    }
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct SearchStats {
+    pub total_matches: usize,
+    pub matched_lines: usize,
+    pub files_searched: usize,
+    pub files_with_matches: usize,
+    pub bytes_searched: u64,
+    pub bytes_printed: u64,
+    pub search_time_ms: f64, // time ripgrep spent searching, summed over its runs
+    pub total_time_ms: f64,  // wall time of the whole search, walk included
+    pub skipped: SkippedFiles,
+    pub files_excluded_by_index: usize, // ruled out by the root's content index without being read
+    pub lines_excluded_by_log_filter: usize, // matched, but outside the log time window or levels
+    pub index_stale_files: usize,       // new or changed since the root's index was last updated
+    pub by_extension: Vec<StatsBucket>,
+    pub by_directory: Vec<StatsBucket>, // top-level directory under the root ("." for files in it)
+}
+
+/// Matches and files for one extension or top-level directory
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct StatsBucket {
+    pub key: String,
+    pub files_searched: usize,
+    pub files_matched: usize,
+    pub matches: usize,
+}
+
+/// Why ripgrep could not search a path
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    PermissionDenied,
+    NotFound,     // vanished while searching, or a broken symlink
+    SymlinkLoop,
+    Io,
+    Other,        // messages not about a single path (bad glob, ...)
+}
+
+/// One problem ripgrep reported on stderr
+#[derive(Serialize, Clone, Debug)]
+pub struct SearchWarning {
+    pub path: Option<String>, // relative to the root, like SearchMatch::path
+    pub kind: WarningKind,
+    pub message: String,
+}
+
+/// Byte range of a match inside `SearchMatch::text`
+#[derive(Serialize, Clone, Debug)]
+pub struct MatchRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// One matched line, with the path as printed by ripgrep (relative to the search root).
+/// In binary files the line is not kept: `text` names the match offset instead and
+/// `binary_offset` holds it, so the viewer can open the hex dump there.
+#[derive(Serialize, Clone, Debug)]
+pub struct SearchMatch {
+    pub path: String,
+    pub line: u64,
+    pub text: String,
+    pub ranges: Vec<MatchRange>,
+    pub binary_offset: Option<u64>, // byte offset of the first match in the file
+    pub timestamp: Option<i64>,     // log mode only: unix time in milliseconds
+    pub level: Option<Severity>,    // log mode only
+}
+
+impl SearchMatch {
+    /// 1-based byte column of the first match on the line
+    pub fn column(&self) -> usize {
+        self.ranges.first().map(|r| r.start + 1).unwrap_or(1)
+    }
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct SearchOutcome {
+    pub matches: Vec<SearchMatch>,
+    pub stats: SearchStats,
+    pub warnings: Vec<SearchWarning>, // parsed from stderr
+    pub stderr: String,
+    pub truncated: bool, // stopped early because of limits.max_results
+    pub cancelled: bool,
+}
+
+/// Snapshot handed to the progress callback
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct SearchProgress {
+    pub matches: usize,
+    pub files_matched: usize,
+    pub files_visited: usize,           // files the walker has looked at so far
+    pub bytes_scanned: u64,             // bytes ripgrep has searched, from its summaries
+    pub current_dir: String,            // directory being walked, "./relative/dir"
+    pub estimated_files: Option<usize>, // files to visit in total, once the pre-walk is done
+    pub elapsed_ms: f64,
+}
+
+/// Shared between the thread running a search and whoever wants to cancel it
+#[derive(Default)]
+pub struct SearchControl {
+    cancelled: AtomicBool,
+    child: Mutex<Option<Child>>,
+}
+
+impl SearchControl {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        if let Some(child) = self.child.lock().unwrap().as_mut() {
+            let _ = child.kill();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+// -----------------------------------------------------------
+// ripgrep binary
+// -----------------------------------------------------------
+
+static RG_PATH: OnceCell<PathBuf> = OnceCell::new();
+
+/// Path of the ripgrep executable, extracted once per process.
+/// Every running search shares it, so concurrent searches never fight over one temp file.
+pub fn rg_path() -> Result<&'static PathBuf, String> {
+    RG_PATH.get_or_try_init(extract_rg)
+}
+
+#[cfg(windows)]
+fn extract_rg() -> Result<PathBuf, String> {
+    let dir = std::env::temp_dir();
+    remove_stale_rg_copies(&dir);
+    let path = dir.join(format!("{}{}.exe", RG_FILE_PREFIX, std::process::id()));
+    std::fs::write(&path, RG_BINARY).map_err(|e| format!("Failed to extract ripgrep: {}", e))?;
+    Ok(path)
+}
+
+// Copies left behind by earlier runs; the ones still in use are locked and stay
+#[cfg(windows)]
+fn remove_stale_rg_copies(dir: &Path) {
+    let own = format!("{}{}.exe", RG_FILE_PREFIX, std::process::id());
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with(RG_FILE_PREFIX) && name != own {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+// Outside Windows ripgrep is expected on the PATH
+#[cfg(not(windows))]
+fn extract_rg() -> Result<PathBuf, String> {
+    Ok(PathBuf::from("rg"))
+}
+
+// -----------------------------------------------------------
+// Command building
+// -----------------------------------------------------------
+
+/// Build the ripgrep command that searches `files` (relative to the root) and prints JSON.
+/// Which files to search is decided by walker.rs, so no ignore or glob flags are passed.
+pub fn build_command(rg: &Path, options: &SearchOptions, settings: &Settings, files: &[PathBuf]) -> Command {
+    let defaults = &settings.search;
+    let mut cmd = Command::new(rg);
+    cmd.current_dir(&options.path).arg("--json");
+
+    if defaults.search_binary {
+        cmd.arg("--text");
+    }
+    if settings.limits.max_matches_per_file > 0 {
+        cmd.arg("--max-count").arg(settings.limits.max_matches_per_file.to_string());
+    }
+
+    // Handle case sensitivity
+    if options.case_sensitive.unwrap_or(defaults.case_sensitive) {
+        cmd.arg("--case-sensitive");
+    } else {
+        cmd.arg("--ignore-case");
+    }
+
+    // Handle whole phrase (literal string match)
+    if options.whole_phrase.unwrap_or(defaults.whole_phrase) {
+        cmd.arg("--fixed-strings");
+    }
+
+    // Handle whole words (word regexp)
+    if options.whole_words.unwrap_or(defaults.whole_words) {
+        cmd.arg("--word-regexp");
+    }
+
+    // --regexp keeps queries that start with '-' from being read as flags
+    cmd.arg("--regexp").arg(&options.query).arg("--").args(files);
+
+    hide_console(&mut cmd);
+    cmd
+}
+
+/// First line of `rg --version`, e.g. "ripgrep 14.1.0 (rev e50df40a19)"
+pub fn engine_version() -> Result<String, String> {
+    let mut cmd = Command::new(rg_path()?);
+    cmd.arg("--version");
+    hide_console(&mut cmd);
+    let output = cmd.output().map_err(|e| format!("Failed to run ripgrep: {}", e))?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(str::to_string)
+        .ok_or_else(|| "ripgrep printed no version".to_string())
+}
+
+fn hide_console(_cmd: &mut Command) {
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        _cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+}
+
+// -----------------------------------------------------------
+// Running
+// -----------------------------------------------------------
+
+/// Run a search to completion (or cancellation), calling `on_progress` periodically.
+/// The walk runs on its own thread and feeds ripgrep batches of files as it goes.
+pub fn run_search(
+    options: &SearchOptions,
+    settings: &Settings,
+    control: &SearchControl,
+    on_progress: impl FnMut(&SearchProgress),
+) -> Result<SearchOutcome, String> {
+    let started = Instant::now();
+    let mut outcome = SearchOutcome::default();
+    if control.is_cancelled() {
+        outcome.cancelled = true;
+        return Ok(outcome);
+    }
+
+    let rg = rg_path()?;
+    let root = Path::new(&options.path);
+    let log = options.log_filter.as_ref().map(|f| f.compile(&settings.log_formats)).transpose()?;
+    let walk_options = WalkOptions {
+        metadata: options.metadata_matcher()?,
+        ..WalkOptions::new(root, options.file_filter.as_deref(), settings)
+    };
+    let walk_progress = WalkProgress::default();
+    let index = if settings.index.enabled { content_index::for_root(root) } else { None };
+    let query_trigrams = index.as_ref().and_then(|_| QueryTrigrams::new(options, settings));
+    let mut index_excluded = 0;
+    let mut index_stale = 0;
+    let estimate = OnceCell::new();
+    let walk_done = AtomicBool::new(false);
+    let mut reporter = ProgressReporter::new(&walk_progress, &estimate, on_progress);
+    let mut batch_error = None;
+
+    let (batch_tx, batch_rx) = mpsc::sync_channel::<Vec<PathBuf>>(BATCH_QUEUE);
+    let (walk_result, buckets) = std::thread::scope(|scope| {
+        if settings.search.estimate_total {
+            // A second, cheaper walk that only counts; it gives up once the search is over
+            scope.spawn(|| {
+                let keep_going = || !walk_done.load(Ordering::Relaxed) && !control.is_cancelled();
+                if let Some(count) = walker::count_files(&walk_options, keep_going) {
+                    let _ = estimate.set(count);
+                }
+            });
+        }
+        let walker = scope.spawn(|| {
+            let mut buckets = Buckets::default();
+            let mut batch = Vec::new();
+            let mut batch_len = 0;
+            let result = walker::walk(&walk_options, &walk_progress, |path, meta| {
+                buckets.add_file(path);
+                if let (Some(index), Some(query)) = (&index, &query_trigrams) {
+                    match index.check(path, meta, query) {
+                        IndexCheck::Excluded => {
+                            index_excluded += 1;
+                            return !control.is_cancelled();
+                        }
+                        IndexCheck::Stale => index_stale += 1,
+                        IndexCheck::Candidate => {}
+                    }
+                }
+                batch_len += path.as_os_str().len() + 1;
+                batch.push(path.to_path_buf());
+                if batch_len >= MAX_BATCH_ARGS_LEN {
+                    batch_len = 0;
+                    // Fails once the search stopped reading (cancelled or truncated)
+                    if batch_tx.send(std::mem::take(&mut batch)).is_err() {
+                        return false;
+                    }
+                }
+                !control.is_cancelled()
+            });
+            if !batch.is_empty() {
+                let _ = batch_tx.send(batch);
+            }
+            drop(batch_tx);
+            (result, buckets)
+        });
+
+        loop {
+            if control.is_cancelled() {
+                break;
+            }
+            // Keep reporting while the walker looks for the next batch
+            let batch = match batch_rx.recv_timeout(PROGRESS_INTERVAL) {
+                Ok(batch) => batch,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    reporter.tick();
+                    continue;
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            };
+            if let Err(e) = run_batch(rg, options, settings, control, log.as_ref(), &batch, &mut outcome, &mut reporter) {
+                batch_error = Some(e);
+                break;
+            }
+            // ripgrep's own summary only counts files that produced output
+            outcome.stats.files_searched += batch.len();
+            reporter.progress.bytes_scanned = outcome.stats.bytes_searched;
+            if outcome.truncated {
+                break;
+            }
+        }
+        drop(batch_rx); // unblocks the walker if we stopped early
+        let result = walker.join().unwrap_or_else(|_| (Err("File walker panicked".to_string()), Buckets::default()));
+        walk_done.store(true, Ordering::Relaxed);
+        result
+    });
+    if let Some(e) = batch_error {
+        return Err(e);
+    }
+    let walk = walk_result?;
+
+    outcome.cancelled = control.is_cancelled();
+    // Stats from the summaries only cover complete runs and know nothing of the log
+    // filter; count what we have otherwise
+    if outcome.truncated || outcome.cancelled || log.is_some() {
+        outcome.stats.total_matches = outcome.matches.iter().map(|m| m.ranges.len()).sum();
+        outcome.stats.matched_lines = outcome.matches.len();
+        outcome.stats.files_with_matches = reporter.progress.files_matched;
+    }
+
+    // Walk errors first, then files ripgrep failed to read
+    let rg_warnings = parse_stderr(&outcome.stderr);
+    let mut failed: Vec<&str> = rg_warnings.iter().filter_map(|w| w.path.as_deref()).collect();
+    failed.sort_unstable();
+    failed.dedup();
+    // Files the index ruled out count as searched: they were, just not by ripgrep
+    outcome.stats.files_searched = (outcome.stats.files_searched + index_excluded).saturating_sub(failed.len());
+    outcome.stats.files_excluded_by_index = index_excluded;
+    outcome.stats.index_stale_files = index_stale;
+    outcome.stats.skipped = walk.skipped;
+    outcome.stats.skipped.error += failed.len();
+    outcome.warnings = walk.warnings;
+    outcome.warnings.extend(rg_warnings);
+
+    let (by_extension, by_directory) = buckets.finish(&outcome.matches);
+    outcome.stats.by_extension = by_extension;
+    outcome.stats.by_directory = by_directory;
+    outcome.stats.total_time_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    reporter.emit();
+    Ok(outcome)
+}
+
+// Builds progress snapshots from the search's own counts and the walker's shared state
+struct ProgressReporter<'a, F> {
+    progress: SearchProgress,
+    walk: &'a WalkProgress,
+    estimate: &'a OnceCell<usize>,
+    started: Instant,
+    last: Instant,
+    on_progress: F,
+}
+
+impl<'a, F: FnMut(&SearchProgress)> ProgressReporter<'a, F> {
+    fn new(walk: &'a WalkProgress, estimate: &'a OnceCell<usize>, on_progress: F) -> Self {
+        Self {
+            progress: SearchProgress::default(),
+            walk,
+            estimate,
+            started: Instant::now(),
+            last: Instant::now(),
+            on_progress,
+        }
+    }
+
+    // Report if the last report is older than PROGRESS_INTERVAL
+    fn tick(&mut self) {
+        if self.last.elapsed() >= PROGRESS_INTERVAL {
+            self.emit();
+        }
+    }
+
+    fn emit(&mut self) {
+        self.progress.files_visited = self.walk.files.load(Ordering::Relaxed);
+        self.progress.current_dir = self.walk.current_dir.lock().unwrap().to_string_lossy().replace('\\', "/");
+        self.progress.estimated_files = self.estimate.get().copied();
+        self.progress.elapsed_ms = self.started.elapsed().as_secs_f64() * 1000.0;
+        (self.on_progress)(&self.progress);
+        self.last = Instant::now();
+    }
+}
+
+// Run ripgrep on one batch of files, adding its matches and stats to `outcome`
+#[allow(clippy::too_many_arguments)]
+fn run_batch(
+    rg: &Path,
+    options: &SearchOptions,
+    settings: &Settings,
+    control: &SearchControl,
+    log: Option<&LogMatcher>,
+    files: &[PathBuf],
+    outcome: &mut SearchOutcome,
+    reporter: &mut ProgressReporter<'_, impl FnMut(&SearchProgress)>,
+) -> Result<(), String> {
+    let mut child = build_command(rg, options, settings, files)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run ripgrep: {}", e))?;
+
+    let stdout = child.stdout.take().ok_or("Failed to capture ripgrep output")?;
+    let mut stderr = child.stderr.take().ok_or("Failed to capture ripgrep errors")?;
+
+    // Drain stderr on its own thread so a chatty ripgrep cannot block on a full pipe
+    let stderr_reader = std::thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text);
+        text
+    });
+
+    *control.child.lock().unwrap() = Some(child);
+    if control.is_cancelled() {
+        control.cancel(); // cancelled between the last check and the spawn
+    }
+
+    let max_results = settings.limits.max_results;
+    let max_line_length = settings.limits.max_line_length;
+    let mut last_path: Option<String> = None;
+    let mut heads = EntryHeads::default();
+    let root = Path::new(&options.path);
+    for line in BufReader::new(stdout).lines() {
+        let Ok(line) = line else { break };
+        match serde_json::from_str::<RgMessage>(&line) {
+            // In log mode a file only counts once one of its lines passes the log filter
+            Ok(RgMessage::Begin(_)) if log.is_none() => reporter.progress.files_matched += 1,
+            Ok(RgMessage::Match(m)) => {
+                let found = m.into_match().filter(|found| found.text.len() <= max_line_length).and_then(|mut found| {
+                    if let Some(log) = log {
+                        let Some(fields) = log.check(&mut heads, &root.join(&found.path), found.line as usize, &found.text) else {
+                            outcome.stats.lines_excluded_by_log_filter += 1;
+                            return None;
+                        };
+                        found.timestamp = fields.timestamp;
+                        found.level = fields.level;
+                    }
+                    Some(found)
+                });
+                if let Some(found) = found {
+                    if log.is_some() && last_path.as_deref() != Some(found.path.as_str()) {
+                        reporter.progress.files_matched += 1;
+                        last_path = Some(found.path.clone());
+                    }
+                    outcome.matches.push(found);
+                    reporter.progress.matches += 1;
+                }
+                if max_results > 0 && outcome.matches.len() >= max_results {
+                    outcome.truncated = true;
+                    break;
+                }
+            }
+            Ok(RgMessage::Summary(summary)) => summary.add_to(&mut outcome.stats),
+            Ok(_) => {}
+            Err(_) => {} // not a JSON message (should not happen with --json)
+        }
+        reporter.tick();
+    }
+
+    // Reap ripgrep; kill it first if we stopped reading early
+    if let Some(mut child) = control.child.lock().unwrap().take() {
+        if outcome.truncated {
+            let _ = child.kill();
+        }
+        let _ = child.wait();
+    }
+    outcome.stderr.push_str(&stderr_reader.join().unwrap_or_default());
+    Ok(())
+}
+
+// -----------------------------------------------------------
+// Per-extension and per-directory breakdown
+// -----------------------------------------------------------
+
+#[derive(Default)]
+struct Buckets {
+    by_extension: HashMap<String, StatsBucket>,
+    by_directory: HashMap<String, StatsBucket>,
+}
+
+impl Buckets {
+    fn add_file(&mut self, path: &Path) {
+        let path = path.to_string_lossy();
+        bucket(&mut self.by_extension, extension_key(&path)).files_searched += 1;
+        bucket(&mut self.by_directory, directory_key(&path)).files_searched += 1;
+    }
+
+    // Add match counts and return both breakdowns, largest first
+    fn finish(mut self, matches: &[SearchMatch]) -> (Vec<StatsBucket>, Vec<StatsBucket>) {
+        let mut last_path: Option<&str> = None;
+        for m in matches {
+            let new_file = last_path != Some(m.path.as_str());
+            last_path = Some(&m.path);
+            for b in [
+                bucket(&mut self.by_extension, extension_key(&m.path)),
+                bucket(&mut self.by_directory, directory_key(&m.path)),
+            ] {
+                b.matches += m.ranges.len().max(1);
+                if new_file {
+                    b.files_matched += 1;
+                }
+            }
+        }
+        (sorted_buckets(self.by_extension), sorted_buckets(self.by_directory))
+    }
+}
+
+fn bucket(map: &mut HashMap<String, StatsBucket>, key: String) -> &mut StatsBucket {
+    map.entry(key.clone()).or_insert_with(|| StatsBucket { key, ..Default::default() })
+}
+
+fn sorted_buckets(map: HashMap<String, StatsBucket>) -> Vec<StatsBucket> {
+    let mut buckets: Vec<StatsBucket> = map.into_values().collect();
+    buckets.sort_by(|a, b| {
+        b.matches
+            .cmp(&a.matches)
+            .then(b.files_searched.cmp(&a.files_searched))
+            .then_with(|| a.key.cmp(&b.key))
+    });
+    buckets.truncate(MAX_BREAKDOWN_ROWS);
+    buckets
+}
+
+// "./src/Main.RS" -> "rs", "./Makefile" -> ""
+fn extension_key(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+// "./src/a/b.rs" -> "src", "./b.rs" -> "."
+fn directory_key(path: &str) -> String {
+    let relative = path.strip_prefix("./").unwrap_or(path).replace('\\', "/");
+    match relative.split_once('/') {
+        Some((top, _)) => top.to_string(),
+        None => ".".to_string(),
+    }
+}
+
+// -----------------------------------------------------------
+// ripgrep stderr
+// Per-path errors look like "rg: ./secret: Permission denied (os error 13)".
+// Other messages (regex or glob errors) may continue on lines without the "rg: " prefix.
+// -----------------------------------------------------------
+
+fn parse_stderr(stderr: &str) -> Vec<SearchWarning> {
+    let mut messages: Vec<String> = Vec::new();
+    for line in stderr.lines() {
+        match line.strip_prefix("rg: ") {
+            Some(message) => messages.push(message.to_string()),
+            None => match messages.last_mut() {
+                Some(last) => {
+                    last.push('\n');
+                    last.push_str(line);
+                }
+                None if !line.trim().is_empty() => messages.push(line.to_string()),
+                None => {}
+            },
+        }
+    }
+    messages.into_iter().map(|m| parse_warning(&m)).collect()
+}
+
+fn parse_warning(message: &str) -> SearchWarning {
+    let (path, text) = if let Some(rest) = message.strip_prefix("File system loop found: ") {
+        // "File system loop found: ./a/link points to an ancestor ./a"
+        (rest.split(" points to ").next(), message)
+    } else {
+        match message.split_once(": ") {
+            Some((path, text)) if looks_like_path(path) => (Some(path), text),
+            _ => (None, message),
+        }
+    };
+
+    let kind = if text.starts_with("File system loop") {
+        WarningKind::SymlinkLoop
+    } else if text.contains("(os error 13)") || text.contains("(os error 5)") {
+        WarningKind::PermissionDenied
+    } else if text.contains("(os error 2)") || text.contains("(os error 3)") {
+        WarningKind::NotFound
+    } else if text.contains("os error") || text.contains("IO error") {
+        WarningKind::Io
+    } else {
+        WarningKind::Other
+    };
+
+    SearchWarning {
+        path: path.map(|p| p.replace('\\', "/")),
+        kind,
+        message: text.to_string(),
+    }
+}
+
+// ripgrep is given paths like "./src/main.rs" and reports them the same way
+fn looks_like_path(text: &str) -> bool {
+    text.starts_with("./") || text.starts_with(".\\") || Path::new(text).is_absolute()
+}
+
+// -----------------------------------------------------------
+// ripgrep --json messages
+// See https://docs.rs/grep-printer/latest/grep_printer/struct.JSON.html
+// -----------------------------------------------------------
+
+#[derive(Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "lowercase")]
+enum RgMessage {
+    Begin(serde::de::IgnoredAny),
+    Match(RgMatch),
+    Context(serde::de::IgnoredAny),
+    End(serde::de::IgnoredAny),
+    Summary(RgSummary),
+}
+
+// Paths and lines are {"text": ...} when valid UTF-8, {"bytes": <base64>} otherwise
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RgData {
+    Text { text: String },
+    Bytes { bytes: String },
+}
+
+impl RgData {
+    fn into_text(self) -> Option<String> {
+        match self {
+            RgData::Text { text } => Some(text),
+            RgData::Bytes { .. } => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RgMatch {
+    path: RgData,
+    lines: RgData,
+    line_number: Option<u64>,
+    absolute_offset: u64, // of the start of the line
+    submatches: Vec<RgSubmatch>,
+}
+
+#[derive(Deserialize)]
+struct RgSubmatch {
+    start: usize,
+    end: usize,
+}
+
+impl RgMatch {
+    fn into_match(self) -> Option<SearchMatch> {
+        let path = self.path.into_text()?.replace('\\', "/");
+        let line = self.line_number.unwrap_or(0);
+        let decoded = match self.lines {
+            RgData::Text { text } if !binary_view::looks_binary(text.as_bytes()) => {
+                let text = text.trim_end_matches(['\r', '\n']).to_string();
+                let ranges = self
+                    .submatches
+                    .iter()
+                    .filter(|s| s.end <= text.len())
+                    .map(|s| MatchRange { start: s.start, end: s.end })
+                    .collect();
+                Some((text, ranges))
+            }
+            // Not UTF-8: a legacy encoding (Windows-1252 logs) or binary data
+            RgData::Bytes { bytes } => decode_line(&BASE64.decode(bytes).ok()?, &self.submatches),
+            RgData::Text { .. } => None,
+        };
+        // Binary files (searched with --text, or explicitly named): report where the
+        // match is, not the bytes around it
+        let Some((text, ranges)) = decoded else {
+            let offset = self.absolute_offset + self.submatches.first().map_or(0, |s| s.start as u64);
+            return Some(SearchMatch {
+                path,
+                line,
+                text: format!("Binary file matches at offset 0x{:x} ({})", offset, offset),
+                ranges: Vec::new(),
+                binary_offset: Some(offset),
+                timestamp: None,
+                level: None,
+            });
+        };
+        Some(SearchMatch {
+            path,
+            line,
+            text,
+            ranges,
+            binary_offset: None,
+            timestamp: None,
+            level: None,
+        })
+    }
+}
+
+// Decode a line ripgrep could not print as UTF-8, moving the match ranges along;
+// None when the bytes look binary
+fn decode_line(raw: &[u8], submatches: &[RgSubmatch]) -> Option<(String, Vec<MatchRange>)> {
+    if binary_view::looks_binary(raw) {
+        return None;
+    }
+    let decoded = text_codec::decode(raw).ok()?;
+    let text = decoded.text.trim_end_matches(['\r', '\n']).to_string();
+    let ranges = submatches
+        .iter()
+        .filter_map(|s| {
+            Some(MatchRange {
+                start: text_codec::decoded_offset(raw, decoded.encoding, s.start)?,
+                end: text_codec::decoded_offset(raw, decoded.encoding, s.end)?,
+            })
+        })
+        .filter(|r| r.end <= text.len())
+        .collect();
+    Some((text, ranges))
+}
+
+#[derive(Deserialize)]
+struct RgDuration {
+    secs: u64,
+    nanos: u32,
+}
+
+impl RgDuration {
+    fn as_ms(&self) -> f64 {
+        self.secs as f64 * 1000.0 + self.nanos as f64 / 1_000_000.0
+    }
+}
+
+#[derive(Deserialize)]
+struct RgStats {
+    searches_with_match: usize,
+    bytes_searched: u64,
+    bytes_printed: u64,
+    matched_lines: usize,
+    matches: usize,
+}
+
+#[derive(Deserialize)]
+struct RgSummary {
+    elapsed_total: RgDuration,
+    stats: RgStats,
+}
+
+impl RgSummary {
+    // Each batch of files is a separate ripgrep run with its own summary
+    fn add_to(&self, stats: &mut SearchStats) {
+        stats.total_matches += self.stats.matches;
+        stats.matched_lines += self.stats.matched_lines;
+        stats.files_with_matches += self.stats.searches_with_match;
+        stats.bytes_searched += self.stats.bytes_searched;
+        stats.bytes_printed += self.stats.bytes_printed;
+        stats.search_time_ms += self.elapsed_total.as_ms();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rg_match(lines: RgData, submatches: &[(usize, usize)]) -> RgMatch {
+        RgMatch {
+            path: RgData::Text { text: "./app.log".to_string() },
+            lines,
+            line_number: Some(3),
+            absolute_offset: 100,
+            submatches: submatches.iter().map(|&(start, end)| RgSubmatch { start, end }).collect(),
+        }
+    }
+
+    fn bytes(raw: &[u8]) -> RgData {
+        RgData::Bytes { bytes: BASE64.encode(raw) }
+    }
+
+    #[test]
+    fn utf8_lines_keep_their_ranges() {
+        let found = rg_match(RgData::Text { text: "café error\r\n".to_string() }, &[(6, 11)]).into_match().unwrap();
+        assert_eq!(found.text, "café error");
+        assert_eq!((found.ranges[0].start, found.ranges[0].end), (6, 11));
+        assert_eq!(found.binary_offset, None);
+    }
+
+    #[test]
+    fn windows_1252_lines_are_decoded() {
+        // "café error" with é as the single byte 0xE9
+        let found = rg_match(bytes(b"caf\xE9 error\n"), &[(5, 10)]).into_match().unwrap();
+        assert_eq!(found.text, "café error");
+        assert_eq!(&found.text[found.ranges[0].start..found.ranges[0].end], "error");
+        assert_eq!(found.binary_offset, None);
+    }
+
+    #[test]
+    fn binary_lines_report_the_offset() {
+        let found = rg_match(bytes(b"\x00\x01\x02error\x00"), &[(3, 8)]).into_match().unwrap();
+        assert_eq!(found.binary_offset, Some(103));
+        assert!(found.text.starts_with("Binary file matches at offset 0x67"));
+        assert!(found.ranges.is_empty());
+    }
+}
@@ -0,0 +1,319 @@
+// search_manager.rs
+// Tracks every search the backend is running or has finished, managed through Tauri state.
+// Each search has its own SearchControl (for cancellation) and its results in the store.
+// Concurrency is capped by the `max_concurrent_searches` setting: searches past
+// the cap wait in `acquire_slot` until a running one finishes, so the disk is not thrashed.
+// A short history of finished searches (including failures and ripgrep's stderr) is kept
+// for `collect_diagnostics`. The manager also hands out search IDs and remembers the root
+// of each, so result paths resolve even after the stored results have been dropped.
+
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use crate::result_store::StoredResults;
+use crate::search::{SearchControl, SearchOptions, SearchStats};
+use crate::session::{self, SearchSession};
+
+// Finished results kept in memory; the oldest are dropped first
+const MAX_STORED_RESULTS: usize = 16;
+
+// Finished searches remembered for diagnostics
+const MAX_HISTORY: usize = 50;
+
+// How often a queued search wakes up to check whether it was cancelled
+const QUEUE_POLL: Duration = Duration::from_millis(250);
+
+#[derive(Default)]
+pub struct SearchManager {
+    running: Mutex<HashMap<String, Arc<SearchControl>>>,
+    results: Mutex<Vec<(String, Arc<StoredResults>)>>,
+    active: Mutex<usize>,
+    slot_freed: Condvar,
+    history: Mutex<VecDeque<SearchRecord>>,
+    // Root of every search this run; a path each, so none are ever dropped
+    roots: Mutex<HashMap<String, PathBuf>>,
+    next_seq: AtomicU64,
+}
+
+/// What happened to one search, kept after its results are dropped
+#[derive(Serialize, Clone, Debug)]
+pub struct SearchRecord {
+    pub search_id: String,
+    pub started: String,
+    pub root: String,
+    pub options: SearchOptions,
+    pub status: String, // "completed", "cancelled" or "failed"
+    pub stats: Option<SearchStats>,
+    pub truncated: bool,
+    pub stderr: String,
+    pub error: Option<String>,
+}
+
+/// A held worker slot; dropping it lets the next queued search start
+pub struct WorkerSlot<'a> {
+    manager: &'a SearchManager,
+}
+
+impl Drop for WorkerSlot<'_> {
+    fn drop(&mut self) {
+        let mut active = self.manager.active.lock().unwrap();
+        *active = active.saturating_sub(1);
+        self.manager.slot_freed.notify_all();
+    }
+}
+
+impl SearchManager {
+    /// Give a new search (content or file name) its ID, rooted at `root`
+    pub fn create_session(&self, root: &Path) -> SearchSession {
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed) + 1;
+        let session = SearchSession {
+            id: format!("search-{}", seq),
+            root: session::absolute_path(root),
+        };
+        self.roots.lock().unwrap().insert(session.id.clone(), session.root.clone());
+        session
+    }
+
+    pub fn root(&self, search_id: &str) -> Option<PathBuf> {
+        self.roots.lock().unwrap().get(search_id).cloned()
+    }
+
+    /// Resolve a result path against the root of `search_id` (see session::resolve_in)
+    pub fn resolve(&self, search_id: Option<&str>, path: &str) -> Result<PathBuf, String> {
+        let root = match search_id {
+            Some(id) => Some(self.root(id).ok_or_else(|| format!("Unknown search ID: {}", id))?),
+            None => None,
+        };
+        Ok(session::resolve_in(root.as_deref(), path))
+    }
+
+    /// Like `resolve`, for callers that may only read the result files of a finished
+    /// search (the HTTP API): anything that is not one of its stored result paths is refused
+    pub fn resolve_result(&self, search_id: &str, path: &str) -> Result<PathBuf, String> {
+        let relative = session::result_relative(path)?;
+        let stored = self
+            .results(search_id)
+            .ok_or_else(|| format!("No results for search ID: {}", search_id))?;
+        if !stored.contains_file(relative) {
+            return Err(format!("Not a result of search {}: {}", search_id, path));
+        }
+        Ok(stored.root.join(relative))
+    }
+
+    /// Register a search so it can be cancelled, before it starts running
+    pub fn register(&self, search_id: &str) -> Arc<SearchControl> {
+        let control = Arc::new(SearchControl::default());
+        self.running
+            .lock()
+            .unwrap()
+            .insert(search_id.to_string(), control.clone());
+        control
+    }
+
+    /// Wait for a free worker slot. Returns None if the search was cancelled while queued.
+    pub fn acquire_slot(&self, control: &SearchControl, max_workers: usize) -> Option<WorkerSlot<'_>> {
+        let max_workers = max_workers.max(1);
+        let mut active = self.active.lock().unwrap();
+        loop {
+            if control.is_cancelled() {
+                return None;
+            }
+            if *active < max_workers {
+                *active += 1;
+                return Some(WorkerSlot { manager: self });
+            }
+            active = self.slot_freed.wait_timeout(active, QUEUE_POLL).unwrap().0;
+        }
+    }
+
+    /// Cancel a queued or running search. Returns false if it is not running.
+    pub fn cancel(&self, search_id: &str) -> bool {
+        match self.running.lock().unwrap().get(search_id) {
+            Some(control) => {
+                control.cancel();
+                self.slot_freed.notify_all();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Store the results of a search and forget its control
+    pub fn finish(&self, search_id: &str, stored: StoredResults) -> Arc<StoredResults> {
+        self.running.lock().unwrap().remove(search_id);
+
+        let stored = Arc::new(stored);
+        let mut results = self.results.lock().unwrap();
+        results.retain(|(id, _)| id != search_id);
+        if results.len() >= MAX_STORED_RESULTS {
+            results.remove(0);
+        }
+        results.push((search_id.to_string(), stored.clone()));
+        stored
+    }
+
+    /// Forget a search that failed before producing an outcome
+    pub fn abandon(&self, search_id: &str) {
+        self.running.lock().unwrap().remove(search_id);
+    }
+
+    pub fn results(&self, search_id: &str) -> Option<Arc<StoredResults>> {
+        self.results
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(id, _)| id == search_id)
+            .map(|(_, stored)| stored.clone())
+    }
+
+    pub fn running_ids(&self) -> Vec<String> {
+        self.running.lock().unwrap().keys().cloned().collect()
+    }
+
+    pub fn stored_count(&self) -> usize {
+        self.results.lock().unwrap().len()
+    }
+
+    /// Remember a finished search for diagnostics
+    pub fn record(&self, record: SearchRecord) {
+        let mut history = self.history.lock().unwrap();
+        if history.len() >= MAX_HISTORY {
+            history.pop_front();
+        }
+        history.push_back(record);
+    }
+
+    /// Finished searches, newest first
+    pub fn history(&self) -> Vec<SearchRecord> {
+        self.history.lock().unwrap().iter().rev().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{SearchMatch, SearchOutcome};
+    use std::sync::atomic::AtomicBool;
+
+    fn options() -> SearchOptions {
+        serde_json::from_value(serde_json::json!({ "query": "error", "path": "/srv/app" })).unwrap()
+    }
+
+    // Results of a search of /srv/app that matched `paths`
+    fn stored(paths: &[&str]) -> StoredResults {
+        let matches = paths
+            .iter()
+            .map(|path| SearchMatch {
+                path: path.to_string(),
+                line: 1,
+                text: "error".to_string(),
+                ranges: Vec::new(),
+                binary_offset: None,
+                timestamp: None,
+                level: None,
+            })
+            .collect();
+        StoredResults::new(PathBuf::from("/srv/app"), options(), SearchOutcome { matches, ..Default::default() })
+    }
+
+    fn record(search_id: &str) -> SearchRecord {
+        SearchRecord {
+            search_id: search_id.to_string(),
+            started: String::new(),
+            root: "/srv/app".to_string(),
+            options: options(),
+            status: "completed".to_string(),
+            stats: None,
+            truncated: false,
+            stderr: String::new(),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn searches_past_the_limit_wait_for_a_slot() {
+        let manager = SearchManager::default();
+        let first = manager.acquire_slot(&manager.register("search-1"), 2).unwrap();
+        let _second = manager.acquire_slot(&manager.register("search-2"), 2).unwrap();
+
+        let started = AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            let queued = scope.spawn(|| {
+                let slot = manager.acquire_slot(&manager.register("search-3"), 2);
+                started.store(true, Ordering::SeqCst);
+                slot.is_some()
+            });
+            std::thread::sleep(Duration::from_millis(100));
+            assert!(!started.load(Ordering::SeqCst));
+            drop(first);
+            assert!(queued.join().unwrap());
+        });
+        assert_eq!(*manager.active.lock().unwrap(), 1); // the third slot was dropped with its thread
+    }
+
+    #[test]
+    fn a_queued_search_can_be_cancelled() {
+        let manager = SearchManager::default();
+        let _busy = manager.acquire_slot(&manager.register("search-1"), 1).unwrap();
+        let control = manager.register("search-2");
+        std::thread::scope(|scope| {
+            let queued = scope.spawn(|| manager.acquire_slot(&control, 1).is_none());
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(manager.cancel("search-2"));
+            assert!(queued.join().unwrap());
+        });
+        assert_eq!(*manager.active.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn cancelling_an_unknown_or_finished_search_does_nothing() {
+        let manager = SearchManager::default();
+        assert!(!manager.cancel("search-9"));
+
+        let control = manager.register("search-1");
+        manager.finish("search-1", stored(&[]));
+        assert!(!manager.cancel("search-1"));
+        assert!(!control.is_cancelled());
+        assert!(manager.running_ids().is_empty());
+    }
+
+    #[test]
+    fn the_oldest_results_and_history_are_dropped() {
+        let manager = SearchManager::default();
+        for n in 1..=MAX_STORED_RESULTS + 2 {
+            manager.finish(&format!("search-{}", n), stored(&["./a.log"]));
+        }
+        assert_eq!(manager.stored_count(), MAX_STORED_RESULTS);
+        assert!(manager.results("search-1").is_none() && manager.results("search-2").is_none());
+        assert!(manager.results("search-3").is_some());
+        // Storing a search again replaces it rather than taking another place
+        manager.finish("search-3", stored(&["./b.log"]));
+        assert_eq!(manager.stored_count(), MAX_STORED_RESULTS);
+        assert!(manager.results("search-3").unwrap().contains_file("b.log"));
+
+        for n in 1..=MAX_HISTORY + 5 {
+            manager.record(record(&format!("search-{}", n)));
+        }
+        let history = manager.history();
+        assert_eq!(history.len(), MAX_HISTORY);
+        assert_eq!(history[0].search_id, format!("search-{}", MAX_HISTORY + 5)); // newest first
+        assert_eq!(history[MAX_HISTORY - 1].search_id, "search-6");
+    }
+
+    #[test]
+    fn only_result_files_resolve_for_the_api() {
+        let manager = SearchManager::default();
+        manager.finish("search-1", stored(&["./logs/a.log"]));
+
+        assert_eq!(manager.resolve_result("search-1", "./logs/a.log").unwrap(), Path::new("/srv/app/logs/a.log"));
+        assert_eq!(manager.resolve_result("search-1", "logs/a.log").unwrap(), Path::new("/srv/app/logs/a.log"));
+        for path in ["./logs/b.log", "../app/logs/a.log", "/srv/app/logs/a.log", "logs/../logs/a.log"] {
+            assert!(manager.resolve_result("search-1", path).is_err(), "{}", path);
+        }
+        assert!(manager.resolve_result("search-2", "./logs/a.log").unwrap_err().starts_with("No results"));
+    }
+}
@@ -0,0 +1,102 @@
+// session.rs
+// Per-search IDs and roots. Every search gets an ID that maps to the absolute root it ran
+// in, so relative result paths can be resolved correctly no matter which search (tab,
+// window) is most recent. The roots are kept by the SearchManager (search_manager.rs)
+// for as long as the app runs, so old result tabs keep working after their stored
+// results are dropped.
+
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Clone, Debug)]
+pub struct SearchSession {
+    pub id: String,
+    pub root: PathBuf,
+}
+
+/// Resolve a result path to an absolute path.
+/// Relative paths (as printed by ripgrep, e.g. "./src/main.rs") are joined to `root`;
+/// without one they fall back to the current directory.
+pub fn resolve_in(root: Option<&Path>, path: &str) -> PathBuf {
+    let relative = strip_dot(path);
+    if Path::new(relative).is_absolute() {
+        return PathBuf::from(relative);
+    }
+    match root {
+        Some(root) => root.join(relative),
+        None => std::env::current_dir().unwrap_or_default().join(relative),
+    }
+}
+
+/// The path as the result store keys it, for callers that may only read result files
+/// (the HTTP API): absolute paths and ".." are refused
+pub fn result_relative(path: &str) -> Result<&str, String> {
+    let relative = strip_dot(path);
+    let escapes = Path::new(relative)
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+    if relative.is_empty() || escapes {
+        return Err(format!("Not a result path: {}", path));
+    }
+    Ok(relative)
+}
+
+fn strip_dot(path: &str) -> &str {
+    path.strip_prefix("./").or_else(|| path.strip_prefix(".\\")).unwrap_or(path)
+}
+
+// Make a path absolute without canonicalizing (canonicalize turns C:\ into \\?\C:\ on Windows)
+pub fn absolute_path(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_dot_removes_one_leading_dot_slash() {
+        assert_eq!(strip_dot("./src/main.rs"), "src/main.rs");
+        assert_eq!(strip_dot(".\\src\\main.rs"), "src\\main.rs");
+        assert_eq!(strip_dot("././a"), "./a");
+        assert_eq!(strip_dot(".hidden"), ".hidden");
+        assert_eq!(strip_dot("../a"), "../a");
+    }
+
+    #[test]
+    fn relative_paths_resolve_against_the_root() {
+        let root = absolute_path(Path::new("srv"));
+        assert_eq!(resolve_in(Some(&root), "./src/main.rs"), root.join("src/main.rs"));
+        assert_eq!(resolve_in(Some(&root), "src/main.rs"), root.join("src/main.rs"));
+        // Local callers may name any file; the API checks with result_relative first
+        assert_eq!(resolve_in(Some(&root), "../etc/passwd"), root.join("../etc/passwd"));
+        assert_eq!(resolve_in(None, "./a.txt"), std::env::current_dir().unwrap().join("a.txt"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn absolute_paths_ignore_the_root() {
+        assert_eq!(resolve_in(Some(Path::new("/srv")), "/etc/hosts"), PathBuf::from("/etc/hosts"));
+        assert_eq!(resolve_in(Some(Path::new("/srv")), ".//etc/hosts"), PathBuf::from("/etc/hosts"));
+    }
+
+    #[test]
+    fn result_paths_stay_inside_the_root() {
+        assert_eq!(result_relative("./src/main.rs"), Ok("src/main.rs"));
+        assert_eq!(result_relative("src/./main.rs"), Ok("src/./main.rs"));
+        for bad in ["", "./", "..", "../secret", "./src/../../secret", "src/..", "/etc/passwd", ".//etc/passwd"] {
+            assert!(result_relative(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_absolute_and_parent_paths_are_refused() {
+        for bad in ["C:\\Windows\\win.ini", "\\\\server\\share\\a", ".\\..\\secret", "C:relative"] {
+            assert!(result_relative(bad).is_err(), "{}", bad);
+        }
+    }
+}
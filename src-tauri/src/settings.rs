@@ -0,0 +1,425 @@
+// settings.rs
+// Typed, versioned application settings.
+// The settings live in a JSON file in the user's config directory and are loaded
+// once at startup. Commands read them through `current()` and replace them through `update()`.
+// When the schema changes, bump SETTINGS_VERSION and add a step to `migrate`.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use crate::editor::{self, EditorPreset};
+use crate::log_format;
+
+/// Current schema version written to settings.json
+pub const SETTINGS_VERSION: u32 = 1;
+
+const SETTINGS_FILE: &str = "settings.json";
+pub const APP_DIR_NAME: &str = "SearchTool";
+
+/// Verbosity of the log file, ordered from quietest to noisiest
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+/// Flags used by `search_text` when the frontend does not pass them explicitly
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SearchDefaults {
+    pub case_sensitive: bool,
+    pub whole_phrase: bool,
+    pub whole_words: bool,
+    pub include_hidden: bool,  // --hidden
+    pub respect_ignore: bool,  // honour .gitignore/.ignore (omits --no-ignore)
+    pub search_binary: bool,   // --text
+    pub estimate_total: bool,  // count the files first so progress can show a total
+}
+
+/// Caps applied to a single search
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ResultLimits {
+    pub max_results: usize,          // total matched lines returned, 0 = unlimited
+    pub max_matches_per_file: usize, // --max-count, 0 = unlimited
+    pub max_line_length: usize,      // longer lines are dropped from the results
+    pub max_file_size_mb: u64,       // larger files are skipped, 0 = unlimited
+}
+
+/// Log file behaviour (see logging.rs)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct LogSettings {
+    pub level: LogLevel,
+    pub redact: bool,        // hide queries and paths in log records
+    pub max_file_mb: u64,    // rotate the live file past this size
+    pub max_files: usize,    // rotated files to keep
+    pub max_age_days: u64,   // rotated files older than this are deleted
+}
+
+/// Content indexes (see content_index.rs); both off until the user turns them on
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct IndexSettings {
+    pub enabled: bool,     // narrow searches with the root's index when it has one
+    pub auto_update: bool, // refresh the index in the background when a search finds changed files
+}
+
+/// Local HTTP API (see api_server.rs)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct ApiSettings {
+    pub enabled: bool, // off unless the user turns it on
+    pub port: u16,     // on 127.0.0.1 only
+    pub token: String, // "Authorization: Bearer <token>"; generated when the API is enabled without one
+}
+
+/// A log line format for log mode (see log_format.rs), e.g. for
+/// "31/01/2025 14:00:00 [ERROR] ...":
+///   pattern: "^(?P<timestamp>\\S+ \\S+) \\[(?P<level>\\w+)\\]", timestamp_format: "%d/%m/%Y %H:%M:%S"
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CustomLogFormat {
+    pub name: String,
+    pub pattern: String, // regex with a `timestamp` and/or `level` named group
+    #[serde(default)]
+    pub timestamp_format: Option<String>, // strftime; ISO 8601 when left out
+}
+
+/// External editor for "Open in editor" (see editor.rs)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct EditorSettings {
+    pub preset: EditorPreset,
+    pub custom_command: String, // used with the Custom preset, e.g. "subl {file}:{line}:{column}"
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub search: SearchDefaults,
+    pub excluded_dirs: Vec<String>,
+    pub log_formats: Vec<CustomLogFormat>, // tried before the built-in log formats
+    pub limits: ResultLimits,
+    pub logging: LogSettings,
+    pub index: IndexSettings,
+    pub api: ApiSettings,
+    pub editor: EditorSettings,
+    pub viewer_chunk_size: usize,
+    pub viewer_syntax_highlight: bool, // token spans for code in the viewer
+    pub cache_budget_mb: usize,
+    pub max_concurrent_searches: usize, // searches running at once; the rest wait in a queue
+}
+
+impl Default for SearchDefaults {
+    fn default() -> Self {
+        Self {
+            case_sensitive: true,
+            whole_phrase: true,
+            whole_words: false,
+            include_hidden: true,
+            respect_ignore: false,
+            search_binary: true,
+            estimate_total: false,
+        }
+    }
+}
+
+impl Default for ResultLimits {
+    fn default() -> Self {
+        Self {
+            max_results: 0,
+            max_matches_per_file: 0,
+            max_line_length: 1000,
+            max_file_size_mb: 0,
+        }
+    }
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::Debug,
+            redact: false,
+            max_file_mb: 5,
+            max_files: 5,
+            max_age_days: 14,
+        }
+    }
+}
+
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 47811,
+            token: String::new(),
+        }
+    }
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        Self {
+            preset: EditorPreset::VsCode,
+            custom_command: String::new(),
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            search: SearchDefaults::default(),
+            excluded_dirs: Vec::new(),
+            log_formats: Vec::new(),
+            limits: ResultLimits::default(),
+            logging: LogSettings::default(),
+            index: IndexSettings::default(),
+            api: ApiSettings::default(),
+            editor: EditorSettings::default(),
+            viewer_chunk_size: 100,
+            viewer_syntax_highlight: true,
+            cache_budget_mb: 256,
+            max_concurrent_searches: 2,
+        }
+    }
+}
+
+impl Settings {
+    /// Reject values that would break searching or viewing
+    pub fn validate(&self) -> Result<(), String> {
+        if self.viewer_chunk_size == 0 {
+            return Err("Viewer chunk size must be greater than 0".to_string());
+        }
+        if self.max_concurrent_searches == 0 {
+            return Err("Max concurrent searches must be at least 1".to_string());
+        }
+        if self.limits.max_line_length == 0 {
+            return Err("Max line length must be greater than 0".to_string());
+        }
+        if self.logging.max_file_mb == 0 {
+            return Err("Max log file size must be greater than 0".to_string());
+        }
+        if self.api.enabled && self.api.port == 0 {
+            return Err("API port must be greater than 0".to_string());
+        }
+        if self.editor.preset == EditorPreset::Custom {
+            editor::validate_template(&self.editor.custom_command)?;
+        }
+        for format in &self.log_formats {
+            log_format::validate_format(format)?;
+        }
+        if let Some(dir) = self.excluded_dirs.iter().find(|d| d.trim().is_empty()) {
+            return Err(format!("Excluded directory entry is empty: '{}'", dir));
+        }
+        Ok(())
+    }
+}
+
+// The live settings. Starts with defaults until `init` has loaded the file.
+static SETTINGS: Lazy<RwLock<Settings>> = Lazy::new(|| RwLock::new(Settings::default()));
+
+/// Directory holding settings.json (and other per-user app data)
+pub fn config_dir() -> PathBuf {
+    dirs::config_dir()
+        .map(|d| d.join(APP_DIR_NAME))
+        .or_else(|| std::env::current_exe().ok().and_then(|p| p.parent().map(|p| p.to_path_buf())))
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+/// Directory for app data that is not configuration (undo journal, ...)
+pub fn data_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .map(|d| d.join(APP_DIR_NAME))
+        .unwrap_or_else(config_dir)
+}
+
+pub fn settings_path() -> PathBuf {
+    config_dir().join(SETTINGS_FILE)
+}
+
+/// Snapshot of the live settings
+pub fn current() -> Settings {
+    SETTINGS.read().map(|s| s.clone()).unwrap_or_default()
+}
+
+/// Snapshot of the log settings, without cloning the whole settings
+pub fn logging() -> LogSettings {
+    SETTINGS.read().map(|s| s.logging.clone()).unwrap_or_default()
+}
+
+/// What `init` did, for the caller to log
+pub struct InitReport {
+    pub message: String, // without the path, which goes in a log field so redaction can hide it
+    pub path: PathBuf,
+    pub invalid: bool, // settings.json exists but could not be used; the defaults are in effect
+}
+
+/// Load settings.json (migrating it if needed) into the live settings.
+/// Never fails: a missing or broken file falls back to defaults.
+pub fn init() -> InitReport {
+    let path = settings_path();
+    let mut invalid = false;
+    let (settings, message) = match std::fs::read_to_string(&path) {
+        Ok(text) => match load_from_str(&text) {
+            Ok((settings, migrated_from)) => {
+                let mut report = "Loaded settings".to_string();
+                if settings.version > SETTINGS_VERSION {
+                    report.push_str(&format!(" (from newer version {}; changes will not be saved)", settings.version));
+                }
+                if let Some(old) = migrated_from {
+                    report.push_str(&format!(" (migrated from version {})", old));
+                    if let Err(e) = save(&settings) {
+                        report.push_str(&format!(", failed to save migrated file: {}", e));
+                    }
+                }
+                (settings, report)
+            }
+            Err(e) => {
+                invalid = true;
+                (Settings::default(), format!("Invalid settings file: {} - using defaults", e))
+            }
+        },
+        Err(_) => (Settings::default(), "No settings file - using defaults".to_string()),
+    };
+
+    if let Ok(mut live) = SETTINGS.write() {
+        *live = settings;
+    }
+    InitReport { message, path, invalid }
+}
+
+/// Validate, persist and apply new settings
+pub fn update(mut settings: Settings) -> Result<Settings, String> {
+    check_writable(&current())?;
+    settings.version = SETTINGS_VERSION;
+    settings.validate()?;
+    save(&settings)?;
+    let mut live = SETTINGS.write().map_err(|_| "Settings lock poisoned".to_string())?;
+    *live = settings.clone();
+    Ok(settings)
+}
+
+// A file from a newer build has fields this one would drop; leave it as it is
+fn check_writable(loaded: &Settings) -> Result<(), String> {
+    if loaded.version > SETTINGS_VERSION {
+        return Err(format!(
+            "settings.json was written by a newer version of the app (settings version {}, this build knows {}); not overwriting it",
+            loaded.version, SETTINGS_VERSION
+        ));
+    }
+    Ok(())
+}
+
+fn save(settings: &Settings) -> Result<(), String> {
+    let path = settings_path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create settings directory {}: {}", dir.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+    // Write next to the target and rename, so a crash never leaves a half-written file
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, json)
+        .map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+    std::fs::rename(&tmp_path, &path)
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+/// Parse settings text, applying migrations.
+/// Returns the settings and, if a migration ran, the version it started from.
+fn load_from_str(text: &str) -> Result<(Settings, Option<u32>), String> {
+    let value: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let original_version = file_version(&value);
+    let value = migrate(value)?;
+    let settings: Settings = serde_json::from_value(value).map_err(|e| e.to_string())?;
+    let migrated_from = (original_version < SETTINGS_VERSION).then_some(original_version);
+    Ok((settings, migrated_from))
+}
+
+// Files written before versioning was introduced have no "version" field
+fn file_version(value: &Value) -> u32 {
+    value.get("version").and_then(Value::as_u64).unwrap_or(0) as u32
+}
+
+/// Upgrade a raw settings document one version at a time until it matches SETTINGS_VERSION.
+/// Each step only rewrites what changed; fields it does not know about fall back to
+/// `#[serde(default)]` when the result is deserialized.
+fn migrate(mut value: Value) -> Result<Value, String> {
+    let mut version = file_version(&value);
+    if version > SETTINGS_VERSION {
+        // Written by a newer build: keep what we understand, ignore the rest.
+        // The version stays, so `update` knows not to overwrite the file.
+        return Ok(value);
+    }
+    while version < SETTINGS_VERSION {
+        value = match version {
+            0 => migrate_v0_to_v1(value),
+            v => return Err(format!("No migration from settings version {}", v)),
+        };
+        version += 1;
+    }
+    if let Some(obj) = value.as_object_mut() {
+        obj.insert("version".to_string(), Value::from(SETTINGS_VERSION));
+    }
+    Ok(value)
+}
+
+// v0: unversioned file with the same layout as v1
+fn migrate_v0_to_v1(value: Value) -> Value {
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn old_files_are_migrated() {
+        let (settings, migrated_from) = load_from_str(r#"{ "logging": { "level": "warn" }, "excluded_dirs": ["target"] }"#).unwrap();
+        assert_eq!(migrated_from, Some(0));
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.logging.level, LogLevel::Warn);
+        assert_eq!(settings.excluded_dirs, ["target"]);
+        // Sections the file does not have get their defaults
+        assert_eq!(settings.logging.max_files, Settings::default().logging.max_files);
+        assert_eq!(settings.limits.max_results, Settings::default().limits.max_results);
+    }
+
+    #[test]
+    fn current_files_load_as_they_are() {
+        let text = serde_json::to_string(&Settings { viewer_chunk_size: 123, ..Settings::default() }).unwrap();
+        let (settings, migrated_from) = load_from_str(&text).unwrap();
+        assert_eq!(migrated_from, None);
+        assert_eq!(settings.viewer_chunk_size, 123);
+        assert!(check_writable(&settings).is_ok());
+    }
+
+    #[test]
+    fn newer_files_load_but_are_not_overwritten() {
+        let text = json!({ "version": SETTINGS_VERSION + 1, "viewer_chunk_size": 321, "new_section": { "x": 1 } }).to_string();
+        let (settings, migrated_from) = load_from_str(&text).unwrap();
+        assert_eq!(migrated_from, None);
+        assert_eq!(settings.version, SETTINGS_VERSION + 1);
+        assert_eq!(settings.viewer_chunk_size, 321);
+        assert!(check_writable(&settings).unwrap_err().contains("newer version"));
+    }
+
+    #[test]
+    fn broken_files_are_errors() {
+        assert!(load_from_str("{ not json").is_err());
+        assert!(load_from_str(r#"{ "version": 1, "viewer_chunk_size": "big" }"#).is_err());
+    }
+}
@@ -0,0 +1,324 @@
+// syntax_highlight.rs
+// Language detection and token spans for the file viewer, using syntect's bundled
+// grammars. Highlighting runs per chunk, never over the whole file:
+// - the parser state at the end of each chunk is kept, so scrolling down continues
+//   exactly where the previous chunk stopped;
+// - any other chunk (a jump to a match, scrolling up) starts parsing WARMUP_LINES
+//   before it. That keeps multi-GB files lazy; the price is the odd wrong colour right
+//   after a jump into the middle of a very long comment or string.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::path::Path;
+use std::time::SystemTime;
+use syntect::parsing::{ParseState, Scope, ScopeStack, SyntaxReference, SyntaxSet};
+
+// Lines parsed (and thrown away) before a chunk that does not continue the previous one
+const WARMUP_LINES: usize = 200;
+
+// Longer lines (minified code, data) are shown without tokens; they are slow to parse
+const MAX_HIGHLIGHT_LINE: usize = 4096;
+
+// Files whose parser state is kept for the next chunk
+const MAX_CHECKPOINTS: usize = 16;
+
+static SYNTAXES: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_nonewlines);
+
+/// What a span of source text is, coarse enough for one colour each
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    Comment,
+    String,
+    Number,
+    Constant,
+    Keyword,
+    Operator,
+    Type,
+    Function,
+    Variable,
+    Tag,
+    Attribute,
+}
+
+// Scope prefixes and the kind they map to; more specific prefixes first
+const SCOPE_KINDS: &[(&str, TokenKind)] = &[
+    ("comment", TokenKind::Comment),
+    ("constant.character.escape", TokenKind::Constant),
+    ("string", TokenKind::String),
+    ("constant.numeric", TokenKind::Number),
+    ("constant", TokenKind::Constant),
+    ("keyword.operator", TokenKind::Operator),
+    ("keyword", TokenKind::Keyword),
+    ("storage.type", TokenKind::Keyword),
+    ("storage.modifier", TokenKind::Keyword),
+    ("entity.name.function", TokenKind::Function),
+    ("support.function", TokenKind::Function),
+    ("variable.function", TokenKind::Function),
+    ("entity.name.type", TokenKind::Type),
+    ("entity.name.class", TokenKind::Type),
+    ("entity.name.struct", TokenKind::Type),
+    ("entity.name.enum", TokenKind::Type),
+    ("entity.other.inherited-class", TokenKind::Type),
+    ("support.type", TokenKind::Type),
+    ("support.class", TokenKind::Type),
+    ("entity.name.tag", TokenKind::Tag),
+    ("entity.other.attribute-name", TokenKind::Attribute),
+    ("variable", TokenKind::Variable),
+];
+
+static SCOPE_SELECTORS: Lazy<Vec<(Scope, TokenKind)>> = Lazy::new(|| {
+    SCOPE_KINDS
+        .iter()
+        .map(|&(prefix, kind)| (Scope::new(prefix).expect("valid scope"), kind))
+        .collect()
+});
+
+/// A token inside one line. Offsets are UTF-16 code units, so the frontend can slice
+/// the line string with them directly.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct TokenSpan {
+    pub start: usize,
+    pub end: usize,
+    pub kind: TokenKind,
+}
+
+/// The grammar for a file: by file name or extension, then by the first line
+/// (shebangs such as "#!/usr/bin/env python", "<?xml", editor mode lines).
+/// None for plain text.
+pub fn detect_language(path: &Path, first_line: &str) -> Option<&'static SyntaxReference> {
+    let by_name = path.file_name().and_then(|name| SYNTAXES.find_syntax_by_extension(&name.to_string_lossy()));
+    let by_extension = || path.extension().and_then(|ext| SYNTAXES.find_syntax_by_extension(&ext.to_string_lossy()));
+    by_name
+        .or_else(by_extension)
+        .or_else(|| SYNTAXES.find_syntax_by_first_line(first_line))
+        .filter(|syntax| syntax.name != "Plain Text")
+}
+
+// Parser position after the last chunk served for a file
+struct Checkpoint {
+    path: String,
+    stamp: Option<(u64, SystemTime)>, // size and mtime, so a rewritten file starts over
+    syntax: String,
+    line: usize, // next line to parse
+    state: ParseState,
+    stack: ScopeStack,
+}
+
+/// Parser states kept between chunks, one per recently viewed file
+#[derive(Default)]
+pub struct HighlightCache {
+    checkpoints: Vec<Checkpoint>, // least recently used first
+}
+
+impl HighlightCache {
+    /// The first line `tokenize` needs for a chunk starting at `start`: where the parser
+    /// stopped for this file, when that is just before the chunk, or WARMUP_LINES before it
+    pub fn first_line_needed(&self, path: &str, syntax: &SyntaxReference, start: usize) -> usize {
+        let stamp = file_stamp(path);
+        self.checkpoints
+            .iter()
+            .find(|c| c.path == path && c.stamp == stamp && c.syntax == syntax.name && c.line <= start && start - c.line <= WARMUP_LINES)
+            .map_or(start.saturating_sub(WARMUP_LINES), |c| c.line)
+    }
+
+    /// Token spans for `lines`, which are lines `start..` of the file. `before` holds the
+    /// lines from `first_line_needed` up to `start`, read by the caller.
+    pub fn tokenize(
+        &mut self,
+        path: &str,
+        syntax: &SyntaxReference,
+        before: &[String],
+        start: usize,
+        lines: &[String],
+    ) -> Vec<Vec<TokenSpan>> {
+        let stamp = file_stamp(path);
+        let from = start.saturating_sub(before.len());
+        let resume = self
+            .checkpoints
+            .iter()
+            .position(|c| c.path == path && c.stamp == stamp && c.syntax == syntax.name && c.line == from);
+        let mut parser = match resume {
+            Some(i) => {
+                let c = self.checkpoints.remove(i);
+                LineParser { state: c.state, stack: c.stack, line: c.line }
+            }
+            // The checkpoint moved on since first_line_needed; parse `before` from scratch
+            None => {
+                self.checkpoints.retain(|c| c.path != path);
+                LineParser { state: ParseState::new(syntax), stack: ScopeStack::new(), line: from }
+            }
+        };
+
+        // Catch up to the chunk, then tokenize it
+        for line in before {
+            parser.parse(line);
+        }
+        let tokens = lines.iter().map(|line| parser.parse(line)).collect();
+
+        if self.checkpoints.len() >= MAX_CHECKPOINTS {
+            self.checkpoints.remove(0);
+        }
+        self.checkpoints.push(Checkpoint {
+            path: path.to_string(),
+            stamp,
+            syntax: syntax.name.clone(),
+            line: parser.line,
+            state: parser.state,
+            stack: parser.stack,
+        });
+        tokens
+    }
+}
+
+fn file_stamp(path: &str) -> Option<(u64, SystemTime)> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.len(), meta.modified().ok()?))
+}
+
+struct LineParser {
+    state: ParseState,
+    stack: ScopeStack,
+    line: usize,
+}
+
+impl LineParser {
+    // Parse one line and return its tokens; the state moves on to the next line
+    fn parse(&mut self, line: &str) -> Vec<TokenSpan> {
+        self.line += 1;
+        if line.len() > MAX_HIGHLIGHT_LINE {
+            return Vec::new();
+        }
+        let ops = match self.state.parse_line(line, &SYNTAXES) {
+            Ok(ops) => ops,
+            Err(_) => return Vec::new(),
+        };
+
+        let mut spans: Vec<TokenSpan> = Vec::new();
+        let mut utf16 = Utf16Offsets::new(line);
+        let mut pos = 0;
+        for (byte, op) in ops.iter().map(|(byte, op)| ((*byte).min(line.len()), op)) {
+            if byte > pos {
+                push_span(&mut spans, utf16.at(pos), utf16.at(byte), kind_of(&self.stack));
+                pos = byte;
+            }
+            if self.stack.apply(op).is_err() {
+                return Vec::new();
+            }
+        }
+        if pos < line.len() {
+            push_span(&mut spans, utf16.at(pos), utf16.at(line.len()), kind_of(&self.stack));
+        }
+        spans
+    }
+}
+
+// Add a span, merging it into the previous one when they touch and have the same kind
+fn push_span(spans: &mut Vec<TokenSpan>, start: usize, end: usize, kind: Option<TokenKind>) {
+    let Some(kind) = kind else { return };
+    match spans.last_mut() {
+        Some(last) if last.end == start && last.kind == kind => last.end = end,
+        _ => spans.push(TokenSpan { start, end, kind }),
+    }
+}
+
+// The innermost scope that maps to a kind decides
+fn kind_of(stack: &ScopeStack) -> Option<TokenKind> {
+    stack.as_slice().iter().rev().find_map(|scope| {
+        SCOPE_SELECTORS
+            .iter()
+            .find(|(prefix, _)| prefix.is_prefix_of(*scope))
+            .map(|&(_, kind)| kind)
+    })
+}
+
+// Byte offsets to UTF-16 offsets, for offsets that only grow
+struct Utf16Offsets<'a> {
+    line: &'a str,
+    byte: usize,
+    utf16: usize,
+}
+
+impl<'a> Utf16Offsets<'a> {
+    fn new(line: &'a str) -> Self {
+        Self { line, byte: 0, utf16: 0 }
+    }
+
+    fn at(&mut self, byte: usize) -> usize {
+        if byte > self.byte {
+            self.utf16 += self.line.get(self.byte..byte).map_or(0, |s| s.encode_utf16().count());
+            self.byte = byte;
+        }
+        self.utf16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn language(path: &str, first_line: &str) -> Option<String> {
+        detect_language(Path::new(path), first_line).map(|s| s.name.clone())
+    }
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn language_from_extension_file_name_or_shebang() {
+        assert_eq!(language("src/main.rs", "").as_deref(), Some("Rust"));
+        assert_eq!(language("/srv/app/Makefile", "").as_deref(), Some("Makefile"));
+        assert_eq!(language("bin/deploy", "#!/usr/bin/env python").as_deref(), Some("Python"));
+        assert_eq!(language("bin/run", "#!/bin/bash").as_deref(), Some("Bourne Again Shell (bash)"));
+        // The name wins over the first line
+        assert_eq!(language("tool.rs", "#!/usr/bin/env python").as_deref(), Some("Rust"));
+        assert_eq!(language("notes.txt", "just text"), None);
+        assert_eq!(language("README", "just text"), None);
+    }
+
+    #[test]
+    fn utf16_offsets_count_code_units() {
+        let line = "é𝄞x"; // 2, 4 and 1 bytes; 1, 2 and 1 UTF-16 units
+        let mut utf16 = Utf16Offsets::new(line);
+        assert_eq!(utf16.at(0), 0);
+        assert_eq!(utf16.at(2), 1);
+        assert_eq!(utf16.at(6), 3);
+        assert_eq!(utf16.at(7), 4);
+        assert_eq!(utf16.at(7), 4);
+    }
+
+    #[test]
+    fn spans_are_in_utf16_units() {
+        let rust = detect_language(Path::new("a.rs"), "").unwrap();
+        let line = "let s = \"𝄞\"; // é";
+        let tokens = HighlightCache::default().tokenize("a.rs", rust, &[], 0, &lines(&[line]));
+        let units: Vec<u16> = line.encode_utf16().collect();
+        let text = |span: &TokenSpan| String::from_utf16(&units[span.start..span.end]).unwrap();
+
+        let string = tokens[0].iter().find(|s| s.kind == TokenKind::String).unwrap();
+        assert_eq!(text(string), "\"𝄞\"");
+        let comment = tokens[0].iter().find(|s| s.kind == TokenKind::Comment).unwrap();
+        assert_eq!(text(comment), "// é");
+    }
+
+    #[test]
+    fn later_chunks_continue_from_the_checkpoint() {
+        let rust = detect_language(Path::new("a.rs"), "").unwrap();
+        let file = lines(&["/* a comment", "still in it", "*/ let x = 1;"]);
+        let whole = HighlightCache::default().tokenize("a.rs", rust, &[], 0, &file);
+
+        let mut cache = HighlightCache::default();
+        assert_eq!(cache.first_line_needed("a.rs", rust, 1), 0);
+        let first = cache.tokenize("a.rs", rust, &[], 0, &file[..1]);
+        assert_eq!(cache.first_line_needed("a.rs", rust, 1), 1);
+        let rest = cache.tokenize("a.rs", rust, &[], 1, &file[1..]);
+        assert_eq!([first, rest].concat(), whole);
+
+        // A jump back parses the lines before the chunk again
+        let from = cache.first_line_needed("a.rs", rust, 2);
+        assert_eq!(from, 0);
+        let again = cache.tokenize("a.rs", rust, &file[from..2], 2, &file[2..]);
+        assert_eq!(again[0], whole[2]);
+    }
+}
@@ -0,0 +1,236 @@
+// text_codec.rs
+// Decode a file into a String and encode it back exactly the way it was stored:
+// same encoding, same BOM, same line endings. Used by everything that rewrites files.
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use serde::Serialize;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TextEncoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Windows1252,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+pub struct DecodedText {
+    pub text: String,
+    pub encoding: TextEncoding,
+    pub line_ending: LineEnding, // the dominant one; mixed files keep theirs untouched
+}
+
+impl TextEncoding {
+    /// Bytes the BOM takes at the start of the file
+    pub fn bom_len(self) -> usize {
+        match self {
+            TextEncoding::Utf8Bom => 3,
+            TextEncoding::Utf16Le | TextEncoding::Utf16Be => 2,
+            TextEncoding::Utf8 | TextEncoding::Windows1252 => 0,
+        }
+    }
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Decode file bytes. Fails for binary files (NUL bytes outside UTF-16).
+pub fn decode(bytes: &[u8]) -> Result<DecodedText, String> {
+    let (text, encoding) = if let Some(rest) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        (utf8(rest)?, TextEncoding::Utf8Bom)
+    } else if let Some(rest) = bytes.strip_prefix(b"\xFF\xFE") {
+        (utf16(rest, u16::from_le_bytes)?, TextEncoding::Utf16Le)
+    } else if let Some(rest) = bytes.strip_prefix(b"\xFE\xFF") {
+        (utf16(rest, u16::from_be_bytes)?, TextEncoding::Utf16Be)
+    } else if bytes.contains(&0) {
+        return Err("File looks binary".to_string());
+    } else if let Ok(text) = std::str::from_utf8(bytes) {
+        (text.to_string(), TextEncoding::Utf8)
+    } else {
+        let (text, _, _) = WINDOWS_1252.decode(bytes);
+        (text.into_owned(), TextEncoding::Windows1252)
+    };
+
+    let line_ending = detect_line_ending(&text);
+    Ok(DecodedText { text, encoding, line_ending })
+}
+
+/// The encoding `decode` would pick for `bytes`, without decoding or checking them all
+/// (binary files come out as UTF-8 or Windows-1252)
+pub fn detect_encoding(bytes: &[u8]) -> TextEncoding {
+    if bytes.starts_with(b"\xEF\xBB\xBF") {
+        TextEncoding::Utf8Bom
+    } else if bytes.starts_with(b"\xFF\xFE") {
+        TextEncoding::Utf16Le
+    } else if bytes.starts_with(b"\xFE\xFF") {
+        TextEncoding::Utf16Be
+    } else if std::str::from_utf8(bytes).is_ok() {
+        TextEncoding::Utf8
+    } else {
+        TextEncoding::Windows1252
+    }
+}
+
+/// Decode part of a file in `encoding` (no BOM) for display: invalid sequences,
+/// including half a UTF-16 character, become U+FFFD instead of failing
+pub fn decode_lossy(bytes: &[u8], encoding: TextEncoding) -> String {
+    let encoding: &Encoding = match encoding {
+        TextEncoding::Utf8 | TextEncoding::Utf8Bom => UTF_8,
+        TextEncoding::Utf16Le => UTF_16LE,
+        TextEncoding::Utf16Be => UTF_16BE,
+        TextEncoding::Windows1252 => WINDOWS_1252,
+    };
+    encoding.decode_without_bom_handling(bytes).0.into_owned()
+}
+
+/// Encode text back into `encoding`. Fails if a character cannot be represented.
+pub fn encode(text: &str, encoding: TextEncoding) -> Result<Vec<u8>, String> {
+    match encoding {
+        TextEncoding::Utf8 => Ok(text.as_bytes().to_vec()),
+        TextEncoding::Utf8Bom => {
+            let mut bytes = b"\xEF\xBB\xBF".to_vec();
+            bytes.extend_from_slice(text.as_bytes());
+            Ok(bytes)
+        }
+        TextEncoding::Utf16Le => Ok(b"\xFF\xFE"
+            .iter()
+            .copied()
+            .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect()),
+        TextEncoding::Utf16Be => Ok(b"\xFE\xFF"
+            .iter()
+            .copied()
+            .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+            .collect()),
+        TextEncoding::Windows1252 => {
+            let (bytes, _, unmappable) = WINDOWS_1252.encode(text);
+            if unmappable {
+                return Err("Text contains characters that Windows-1252 cannot store".to_string());
+            }
+            Ok(bytes.into_owned())
+        }
+    }
+}
+
+/// Where byte `offset` of `bytes` ends up in the text `decode` made of them.
+/// None for UTF-16, where a byte offset may fall inside a character.
+pub fn decoded_offset(bytes: &[u8], encoding: TextEncoding, offset: usize) -> Option<usize> {
+    match encoding {
+        TextEncoding::Utf8 => Some(offset),
+        TextEncoding::Utf8Bom => offset.checked_sub(3),
+        TextEncoding::Windows1252 => Some(WINDOWS_1252.decode_without_bom_handling(bytes.get(..offset)?).0.len()),
+        TextEncoding::Utf16Le | TextEncoding::Utf16Be => None,
+    }
+}
+
+/// Convert bare "\n" in inserted text to the file's line ending
+pub fn normalize_newlines(text: &str, line_ending: LineEnding) -> String {
+    match line_ending {
+        LineEnding::Lf => text.replace("\r\n", "\n"),
+        LineEnding::CrLf => text.replace("\r\n", "\n").replace('\n', "\r\n"),
+    }
+}
+
+fn detect_line_ending(text: &str) -> LineEnding {
+    let crlf = text.matches("\r\n").count();
+    let lf = text.matches('\n').count() - crlf;
+    if crlf > lf {
+        LineEnding::CrLf
+    } else {
+        LineEnding::Lf
+    }
+}
+
+fn utf8(bytes: &[u8]) -> Result<String, String> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| "File has a UTF-8 BOM but invalid UTF-8 content".to_string())
+}
+
+fn utf16(bytes: &[u8], to_unit: fn([u8; 2]) -> u16) -> Result<String, String> {
+    let chunks = bytes.chunks_exact(2);
+    if !chunks.remainder().is_empty() {
+        return Err("File has a UTF-16 BOM but an odd number of bytes".to_string());
+    }
+    let units: Vec<u16> = chunks.map(|c| to_unit([c[0], c[1]])).collect();
+    String::from_utf16(&units).map_err(|_| "File has a UTF-16 BOM but invalid UTF-16 content".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(bytes: &[u8]) -> DecodedText {
+        let decoded = decode(bytes).unwrap();
+        assert_eq!(encode(&decoded.text, decoded.encoding).unwrap(), bytes);
+        decoded
+    }
+
+    #[test]
+    fn utf8_with_and_without_bom() {
+        let plain = round_trip("caf\u{e9}\n".as_bytes());
+        assert_eq!((plain.text.as_str(), plain.encoding), ("caf\u{e9}\n", TextEncoding::Utf8));
+        let bom = round_trip(b"\xEF\xBB\xBFcaf\xC3\xA9\n");
+        assert_eq!((bom.text.as_str(), bom.encoding), ("caf\u{e9}\n", TextEncoding::Utf8Bom));
+    }
+
+    #[test]
+    fn utf16_in_both_byte_orders() {
+        let le = round_trip(b"\xFF\xFEh\0i\0\r\0\n\0");
+        assert_eq!((le.text.as_str(), le.encoding, le.line_ending), ("hi\r\n", TextEncoding::Utf16Le, LineEnding::CrLf));
+        let be = round_trip(b"\xFE\xFF\0h\0i\0\n");
+        assert_eq!((be.text.as_str(), be.encoding), ("hi\n", TextEncoding::Utf16Be));
+    }
+
+    #[test]
+    fn windows_1252_when_not_utf8() {
+        let decoded = round_trip(b"caf\xE9 \x80 5\r\n");
+        assert_eq!((decoded.text.as_str(), decoded.encoding), ("caf\u{e9} \u{20ac} 5\r\n", TextEncoding::Windows1252));
+        assert!(encode("\u{4e2d}", TextEncoding::Windows1252).is_err());
+    }
+
+    #[test]
+    fn dominant_line_ending_and_normalizing() {
+        assert_eq!(round_trip(b"a\r\nb\r\nc\n").line_ending, LineEnding::CrLf);
+        assert_eq!(round_trip(b"a\nb\nc\r\n").line_ending, LineEnding::Lf);
+        assert_eq!(normalize_newlines("x\ny\r\nz", LineEnding::CrLf), "x\r\ny\r\nz");
+        assert_eq!(normalize_newlines("x\r\ny", LineEnding::Lf), "x\ny");
+    }
+
+    #[test]
+    fn binary_bytes_are_refused() {
+        assert!(decode(b"ab\0cd").is_err());
+    }
+
+    #[test]
+    fn detection_matches_decode_and_lossy_decoding_never_fails() {
+        for bytes in [&b"plain\n"[..], b"\xEF\xBB\xBFx", b"\xFF\xFEx\0", b"\xFE\xFF\0x", b"caf\xE9"] {
+            assert_eq!(detect_encoding(bytes), decode(bytes).unwrap().encoding);
+        }
+        assert_eq!(decode_lossy(b"caf\xE9", TextEncoding::Windows1252), "caf\u{e9}");
+        assert_eq!(decode_lossy(b"h\0i\0", TextEncoding::Utf16Le), "hi");
+        assert_eq!(decode_lossy(b"\0h\0", TextEncoding::Utf16Be), "h\u{FFFD}");
+        assert_eq!(decode_lossy(b"a\xFFb", TextEncoding::Utf8), "a\u{FFFD}b");
+    }
+
+    #[test]
+    fn offsets_follow_the_decoding() {
+        let bytes = b"caf\xE9 error";
+        assert_eq!(decoded_offset(bytes, TextEncoding::Windows1252, 5), Some(6));
+        assert_eq!(decoded_offset(b"abc", TextEncoding::Utf8, 2), Some(2));
+        assert_eq!(decoded_offset(b"\xEF\xBB\xBFabc", TextEncoding::Utf8Bom, 4), Some(1));
+        assert_eq!(decoded_offset(b"\xFF\xFEa\0", TextEncoding::Utf16Le, 2), None);
+    }
+}
@@ -0,0 +1,273 @@
+// undo_journal.rs
+// On-disk journal of every change the app makes to a file, so it can be undone.
+// Each entry keeps the original bytes of each file plus SHA-256 checksums of the
+// original and of what was written. Undo refuses to run if a file no longer matches
+// the written checksum, i.e. it was changed by something else since.
+//
+// Layout (in the app data dir):
+//   journal/<id>.json       entry metadata
+//   journal/<id>.<n>.orig   original bytes of the n-th file of the entry
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::atomic_write::{write_all_atomic, write_atomic};
+use crate::settings;
+
+// Older entries (and their backups) are deleted past this many
+const MAX_ENTRIES: usize = 100;
+
+// Serializes journal writes, undo and pruning
+static JOURNAL_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+static NEXT_SEQ: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JournalFile {
+    pub path: PathBuf,
+    pub original_sha256: String,
+    pub written_sha256: String,
+    backup: String, // file name of the original bytes inside the journal dir
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JournalEntry {
+    pub id: String,
+    pub kind: String, // "replace", "edit", ...
+    pub description: String,
+    pub created: String,
+    pub files: Vec<JournalFile>,
+}
+
+/// One file change to record: its path, the bytes before and the bytes written
+pub struct FileChange<'a> {
+    pub path: &'a Path,
+    pub original: &'a [u8],
+    pub written: &'a [u8],
+}
+
+#[derive(Serialize, Debug)]
+pub struct UndoReport {
+    pub entry_id: String,
+    pub restored: Vec<String>,
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+pub fn journal_dir() -> PathBuf {
+    settings::data_dir().join("journal")
+}
+
+/// Save an entry for changes that were just written. Call after the files are on disk.
+pub fn record(kind: &str, description: &str, changes: &[FileChange]) -> Result<JournalEntry, String> {
+    record_in(&journal_dir(), kind, description, changes)
+}
+
+/// `record` into the journal at `dir`
+pub fn record_in(dir: &Path, kind: &str, description: &str, changes: &[FileChange]) -> Result<JournalEntry, String> {
+    let _guard = JOURNAL_LOCK.lock().unwrap();
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create journal directory: {}", e))?;
+
+    let now = chrono::Local::now();
+    let id = format!(
+        "{}-{}",
+        now.format("%Y%m%d-%H%M%S%.3f"),
+        NEXT_SEQ.fetch_add(1, Ordering::Relaxed)
+    );
+
+    let mut files = Vec::with_capacity(changes.len());
+    for (n, change) in changes.iter().enumerate() {
+        let backup = format!("{}.{}.orig", id, n);
+        std::fs::write(dir.join(&backup), change.original)
+            .map_err(|e| format!("Failed to save backup of {}: {}", change.path.display(), e))?;
+        files.push(JournalFile {
+            path: change.path.to_path_buf(),
+            original_sha256: sha256_hex(change.original),
+            written_sha256: sha256_hex(change.written),
+            backup,
+        });
+    }
+
+    let entry = JournalEntry {
+        id,
+        kind: kind.to_string(),
+        description: description.to_string(),
+        created: now.to_rfc3339(),
+        files,
+    };
+    let json = serde_json::to_vec_pretty(&entry).map_err(|e| e.to_string())?;
+    write_atomic(&dir.join(format!("{}.json", entry.id)), &json)
+        .map_err(|e| format!("Failed to write journal entry: {}", e))?;
+
+    prune(dir);
+    Ok(entry)
+}
+
+/// All entries, newest first
+pub fn list() -> Vec<JournalEntry> {
+    let _guard = JOURNAL_LOCK.lock().unwrap();
+    read_entries(&journal_dir())
+}
+
+/// One entry by ID
+pub fn get(entry_id: &str) -> Result<JournalEntry, String> {
+    let _guard = JOURNAL_LOCK.lock().unwrap();
+    read_entry(&journal_dir(), entry_id)
+}
+
+/// Restore the original bytes of every file in an entry, then drop the entry.
+/// Nothing is restored unless every file still matches what was written.
+pub fn undo(entry_id: &str) -> Result<UndoReport, String> {
+    undo_in(&journal_dir(), entry_id)
+}
+
+fn undo_in(dir: &Path, entry_id: &str) -> Result<UndoReport, String> {
+    let _guard = JOURNAL_LOCK.lock().unwrap();
+    let entry = read_entry(dir, entry_id)?;
+
+    let mut originals = Vec::with_capacity(entry.files.len());
+    for file in &entry.files {
+        let current = std::fs::read(&file.path)
+            .map_err(|e| format!("Cannot undo: failed to read {}: {}", file.path.display(), e))?;
+        if sha256_hex(&current) != file.written_sha256 {
+            return Err(format!(
+                "Cannot undo: {} has changed since this edit",
+                file.path.display()
+            ));
+        }
+        let original = std::fs::read(dir.join(&file.backup))
+            .map_err(|e| format!("Cannot undo: backup of {} is missing: {}", file.path.display(), e))?;
+        if sha256_hex(&original) != file.original_sha256 {
+            return Err(format!("Cannot undo: backup of {} is corrupt", file.path.display()));
+        }
+        originals.push(original);
+    }
+
+    // All files or none, like the replace that wrote them
+    let batch: Vec<(&Path, &[u8])> = entry.files.iter().zip(&originals).map(|(f, o)| (f.path.as_path(), o.as_slice())).collect();
+    write_all_atomic(&batch).map_err(|failure| {
+        format!("Failed to restore {}: {}", entry.files[failure.index].path.display(), failure.error)
+    })?;
+    let restored = entry.files.iter().map(|f| f.path.display().to_string()).collect();
+    remove_entry(dir, &entry);
+    Ok(UndoReport { entry_id: entry.id, restored })
+}
+
+fn read_entry(dir: &Path, entry_id: &str) -> Result<JournalEntry, String> {
+    // IDs come from the frontend; never let one point outside the journal
+    if entry_id.contains(['/', '\\']) || entry_id.contains("..") {
+        return Err(format!("Invalid journal entry ID: {}", entry_id));
+    }
+    let text = std::fs::read_to_string(dir.join(format!("{}.json", entry_id)))
+        .map_err(|_| format!("No journal entry with ID {}", entry_id))?;
+    serde_json::from_str(&text).map_err(|e| format!("Journal entry {} is unreadable: {}", entry_id, e))
+}
+
+fn read_entries(dir: &Path) -> Vec<JournalEntry> {
+    let mut entries: Vec<JournalEntry> = std::fs::read_dir(dir)
+        .map(|rd| {
+            rd.flatten()
+                .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+                .filter_map(|e| std::fs::read_to_string(e.path()).ok())
+                .filter_map(|text| serde_json::from_str(&text).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    entries.sort_by(|a: &JournalEntry, b| b.id.cmp(&a.id));
+    entries
+}
+
+fn remove_entry(dir: &Path, entry: &JournalEntry) {
+    for file in &entry.files {
+        let _ = std::fs::remove_file(dir.join(&file.backup));
+    }
+    let _ = std::fs::remove_file(dir.join(format!("{}.json", entry.id)));
+}
+
+fn prune(dir: &Path) {
+    for entry in read_entries(dir).iter().skip(MAX_ENTRIES) {
+        remove_entry(dir, entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A journal dir and a file dir under the system temp dir, removed on drop
+    struct TempJournal(PathBuf);
+
+    impl TempJournal {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("search-tool-journal-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+            std::fs::create_dir_all(root.join("files")).unwrap();
+            Self(root)
+        }
+
+        fn dir(&self) -> PathBuf {
+            self.0.join("journal")
+        }
+
+        fn file(&self, name: &str, bytes: &[u8]) -> PathBuf {
+            let path = self.0.join("files").join(name);
+            std::fs::write(&path, bytes).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempJournal {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn undo_restores_every_file_and_drops_the_entry() {
+        let temp = TempJournal::new("undo");
+        let a = temp.file("a.txt", b"new a");
+        let b = temp.file("b.txt", b"new b");
+        let entry = record_in(&temp.dir(), "replace", "test", &[
+            FileChange { path: &a, original: b"old a", written: b"new a" },
+            FileChange { path: &b, original: b"\xFF\xFEo\0l\0d\0", written: b"new b" },
+        ])
+        .unwrap();
+        assert_eq!(read_entries(&temp.dir()).len(), 1);
+
+        let report = undo_in(&temp.dir(), &entry.id).unwrap();
+        assert_eq!(report.restored.len(), 2);
+        assert_eq!(std::fs::read(&a).unwrap(), b"old a");
+        assert_eq!(std::fs::read(&b).unwrap(), b"\xFF\xFEo\0l\0d\0");
+        assert!(read_entries(&temp.dir()).is_empty());
+        assert_eq!(std::fs::read_dir(temp.dir()).unwrap().count(), 0); // backups removed
+    }
+
+    #[test]
+    fn undo_refuses_when_a_file_changed_since() {
+        let temp = TempJournal::new("changed");
+        let a = temp.file("a.txt", b"new a");
+        let b = temp.file("b.txt", b"new b");
+        let entry = record_in(&temp.dir(), "replace", "test", &[
+            FileChange { path: &a, original: b"old a", written: b"new a" },
+            FileChange { path: &b, original: b"old b", written: b"new b" },
+        ])
+        .unwrap();
+        std::fs::write(&b, "edited elsewhere").unwrap();
+
+        let err = undo_in(&temp.dir(), &entry.id).unwrap_err();
+        assert!(err.contains("has changed"), "{}", err);
+        assert_eq!(std::fs::read(&a).unwrap(), b"new a"); // nothing restored
+        assert_eq!(read_entries(&temp.dir()).len(), 1); // still undoable once fixed
+    }
+
+    #[test]
+    fn entry_ids_cannot_leave_the_journal() {
+        let temp = TempJournal::new("ids");
+        assert!(undo_in(&temp.dir(), "../files/a").unwrap_err().starts_with("Invalid journal entry ID"));
+    }
+}
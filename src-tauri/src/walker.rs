@@ -0,0 +1,430 @@
+// walker.rs
+// Walks a search root the way ripgrep would (hidden files, .gitignore/.ignore/.rgignore,
+// --glob filters, excluded directories) using the same `ignore` crate ripgrep is built on.
+// The walk is parallel, like ripgrep's; search.rs hands the files it yields to ripgrep in
+// batches. Walking ourselves means we know what was skipped and why, which ripgrep does
+// not report.
+//
+// Skipped files are counted by reason. Entries the ignore rules, hidden flag and globs
+// leave out are never seen by the walk, so they are not counted.
+
+use ignore::overrides::OverrideBuilder;
+use ignore::{DirEntry, WalkBuilder, WalkState};
+use serde::Serialize;
+use std::fs::Metadata;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+
+use crate::binary_view;
+use crate::metadata_filter::{Exclusion, MetadataMatcher};
+use crate::search::{SearchWarning, WarningKind};
+use crate::settings::Settings;
+
+// Files the walker threads may get ahead of the caller by
+const FILE_QUEUE: usize = 1024;
+
+pub struct WalkOptions {
+    pub root: PathBuf,
+    pub include_hidden: bool,
+    pub respect_ignore: bool,
+    pub file_filter: Option<String>,
+    pub excluded_dirs: Vec<String>,
+    pub max_file_size: u64, // bytes, 0 = unlimited
+    pub skip_binary: bool,
+    pub metadata: Option<MetadataMatcher>, // modified time, size and owner filters
+}
+
+impl WalkOptions {
+    /// The walk a search of `root` does with these settings and `--glob` filter
+    pub fn new(root: &Path, file_filter: Option<&str>, settings: &Settings) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            include_hidden: settings.search.include_hidden,
+            respect_ignore: settings.search.respect_ignore,
+            file_filter: file_filter.map(|f| f.trim().to_string()).filter(|f| !f.is_empty()),
+            excluded_dirs: settings.excluded_dirs.clone(),
+            max_file_size: settings.limits.max_file_size_mb * 1024 * 1024,
+            skip_binary: !settings.search.search_binary,
+            metadata: None,
+        }
+    }
+}
+
+/// Files left out of a search, by reason
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct SkippedFiles {
+    pub binary: usize,
+    pub symlinks_or_special: usize, // symlinks (not followed) and sockets, pipes, devices
+    pub too_large: usize,           // over limits.max_file_size_mb
+    pub error: usize,               // could not be read (see the search warnings)
+    pub modified_range: usize,      // outside the metadata filter's modified-time range
+    pub size_range: usize,          // outside its size range
+    pub owner: usize,               // owned by someone else
+}
+
+impl SkippedFiles {
+    pub fn total(&self) -> usize {
+        self.binary + self.symlinks_or_special + self.too_large + self.error + self.modified_range + self.size_range + self.owner
+    }
+}
+
+#[derive(Default)]
+pub struct WalkSummary {
+    pub files: usize, // yielded to the caller
+    pub dirs: usize,
+    pub skipped: SkippedFiles,
+    pub warnings: Vec<SearchWarning>,
+}
+
+/// Where a walk has got to, readable from other threads while it runs
+#[derive(Default)]
+pub struct WalkProgress {
+    pub files: AtomicUsize,          // files looked at, whether searched or skipped
+    pub current_dir: Mutex<PathBuf>, // "./relative/dir"
+}
+
+/// Walk `options.root`, calling `on_file` with each file to search as "./relative/path"
+/// and its metadata. Directories are read and files checked on the `ignore` crate's
+/// parallel walker; `on_file` runs on the calling thread, in no particular order.
+/// Stops early when `on_file` returns false.
+pub fn walk(
+    options: &WalkOptions,
+    progress: &WalkProgress,
+    mut on_file: impl FnMut(&Path, &Metadata) -> bool,
+) -> Result<WalkSummary, String> {
+    let walker = build_walker(options)?.build_parallel();
+    let summary = Mutex::new(WalkSummary::default());
+    let mut files = 0;
+    let (file_tx, file_rx) = mpsc::sync_channel::<(PathBuf, Metadata)>(FILE_QUEUE);
+
+    std::thread::scope(|scope| {
+        let summary = &summary;
+        scope.spawn(move || {
+            walker.run(|| {
+                let file_tx = file_tx.clone();
+                Box::new(move |result| {
+                    let Some(file) = visit(options, progress, summary, result) else {
+                        return WalkState::Continue;
+                    };
+                    // Fails once the caller stopped reading
+                    if file_tx.send(file).is_err() {
+                        return WalkState::Quit;
+                    }
+                    WalkState::Continue
+                })
+            });
+        });
+
+        for (path, meta) in &file_rx {
+            files += 1;
+            if !on_file(&path, &meta) {
+                break;
+            }
+        }
+        drop(file_rx); // unblocks the walker if we stopped early
+    });
+
+    let mut summary = summary.into_inner().unwrap();
+    summary.files = files;
+    Ok(summary)
+}
+
+// Look at one entry of the walk, returning the file if it is to be searched.
+// Anything skipped is counted in `summary`.
+fn visit(
+    options: &WalkOptions,
+    progress: &WalkProgress,
+    summary: &Mutex<WalkSummary>,
+    result: Result<DirEntry, ignore::Error>,
+) -> Option<(PathBuf, Metadata)> {
+    let entry = match result {
+        Ok(entry) => entry,
+        Err(err) => {
+            let mut summary = summary.lock().unwrap();
+            summary.skipped.error += 1;
+            summary.warnings.push(walk_warning(&options.root, &err));
+            return None;
+        }
+    };
+    let file_type = entry.file_type()?;
+
+    if file_type.is_dir() {
+        summary.lock().unwrap().dirs += 1;
+        *progress.current_dir.lock().unwrap() = relative_path(&options.root, entry.path());
+        return None;
+    }
+    if !file_type.is_file() {
+        // Symlinks are not followed (like ripgrep without -L); sockets, pipes...
+        summary.lock().unwrap().skipped.symlinks_or_special += 1;
+        return None;
+    }
+    progress.files.fetch_add(1, Ordering::Relaxed);
+
+    let meta = match entry.metadata() {
+        Ok(meta) => meta,
+        Err(err) => {
+            let mut summary = summary.lock().unwrap();
+            summary.skipped.error += 1;
+            summary.warnings.push(walk_warning(&options.root, &err));
+            return None;
+        }
+    };
+    if let Some(reason) = options.metadata.as_ref().and_then(|m| m.excludes(&meta)) {
+        let skipped = &mut summary.lock().unwrap().skipped;
+        match reason {
+            Exclusion::Modified => skipped.modified_range += 1,
+            Exclusion::Size => skipped.size_range += 1,
+            Exclusion::Owner => skipped.owner += 1,
+        }
+        return None;
+    }
+    if options.max_file_size > 0 && meta.len() > options.max_file_size {
+        summary.lock().unwrap().skipped.too_large += 1;
+        return None;
+    }
+    if options.skip_binary && looks_binary(entry.path()) {
+        summary.lock().unwrap().skipped.binary += 1;
+        return None;
+    }
+    Some((relative_path(&options.root, entry.path()), meta))
+}
+
+/// Count the files a walk would look at, without reading them. Used to estimate the
+/// total for progress reports; stops early (returning None) once `keep_going` is false.
+pub fn count_files(options: &WalkOptions, keep_going: impl Fn() -> bool + Sync) -> Option<usize> {
+    let count = AtomicUsize::new(0);
+    let gave_up = AtomicBool::new(false);
+    build_walker(options).ok()?.build_parallel().run(|| {
+        let (count, gave_up, keep_going) = (&count, &gave_up, &keep_going);
+        Box::new(move |result| {
+            if !keep_going() {
+                gave_up.store(true, Ordering::Relaxed);
+                return WalkState::Quit;
+            }
+            let Ok(entry) = result else {
+                return WalkState::Continue;
+            };
+            if entry.file_type().is_some_and(|t| t.is_file()) {
+                let excluded = options.metadata.as_ref().is_some_and(|m| entry.metadata().map_or(true, |meta| m.excludes(&meta).is_some()));
+                if !excluded {
+                    count.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            WalkState::Continue
+        })
+    });
+    (!gave_up.into_inner()).then(|| count.into_inner())
+}
+
+fn build_walker(options: &WalkOptions) -> Result<WalkBuilder, String> {
+    let mut overrides = OverrideBuilder::new(&options.root);
+    if let Some(filter) = &options.file_filter {
+        overrides.add(filter).map_err(|e| format!("Invalid file filter '{}': {}", filter, e))?;
+    }
+    // Excluded directories, matched by name at any depth
+    for dir in &options.excluded_dirs {
+        let name = dir.trim().trim_end_matches(['/', '\\']);
+        overrides
+            .add(&format!("!{}", name))
+            .map_err(|e| format!("Invalid excluded directory '{}': {}", dir, e))?;
+    }
+    let overrides = overrides.build().map_err(|e| format!("Invalid file filter: {}", e))?;
+
+    let mut builder = WalkBuilder::new(&options.root);
+    builder
+        .hidden(!options.include_hidden)
+        .ignore(options.respect_ignore)
+        .git_ignore(options.respect_ignore)
+        .git_global(options.respect_ignore)
+        .git_exclude(options.respect_ignore)
+        .parents(options.respect_ignore)
+        .follow_links(false)
+        .overrides(overrides);
+    if options.respect_ignore {
+        builder.add_custom_ignore_filename(".rgignore");
+    }
+    Ok(builder)
+}
+
+// "./src/main.rs", the form ripgrep prints when searching "."
+pub fn relative_path(root: &Path, path: &Path) -> PathBuf {
+    Path::new(".").join(path.strip_prefix(root).unwrap_or(path))
+}
+
+// The viewer's check on the start of the file, so UTF-16 text is searched, not skipped
+fn looks_binary(path: &Path) -> bool {
+    let mut buf = [0u8; binary_view::SNIFF_LEN];
+    let Ok(mut file) = std::fs::File::open(path) else {
+        return false; // let ripgrep report the error
+    };
+    let mut len = 0;
+    while len < buf.len() {
+        match file.read(&mut buf[len..]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => len += n,
+        }
+    }
+    binary_view::looks_binary(&buf[..len])
+}
+
+fn walk_warning(root: &Path, err: &ignore::Error) -> SearchWarning {
+    let (path, kind) = describe_error(err);
+    SearchWarning {
+        path: path.map(|p| relative_path(root, p).to_string_lossy().replace('\\', "/")),
+        kind,
+        message: err.to_string(),
+    }
+}
+
+fn describe_error(err: &ignore::Error) -> (Option<&Path>, WarningKind) {
+    match err {
+        ignore::Error::WithPath { path, err } => (Some(path.as_path()), describe_error(err).1),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => describe_error(err),
+        ignore::Error::Partial(errs) => errs.first().map(describe_error).unwrap_or((None, WarningKind::Other)),
+        ignore::Error::Loop { child, .. } => (Some(child.as_path()), WarningKind::SymlinkLoop),
+        ignore::Error::Io(io) => (None, match io.kind() {
+            std::io::ErrorKind::PermissionDenied => WarningKind::PermissionDenied,
+            std::io::ErrorKind::NotFound => WarningKind::NotFound,
+            _ => WarningKind::Io,
+        }),
+        _ => (None, WarningKind::Other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata_filter::MetadataFilter;
+
+    // A small tree under the system temp dir, removed on drop
+    struct TempTree(PathBuf);
+
+    impl TempTree {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("search-tool-walker-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+            for (path, bytes) in [
+                ("a.txt", &b"hello"[..]),
+                ("big.txt", &[b'x'; 100][..]),
+                ("data.bin", &b"\x7fELF\0\0\0\x01"[..]),
+                ("src/main.rs", &b"fn main() {}"[..]),
+                ("node_modules/dep/index.js", &b"x"[..]),
+                ("src/target/out.txt", &b"x"[..]),
+            ] {
+                let path = root.join(path);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, bytes).unwrap();
+            }
+            Self(root)
+        }
+
+        fn options(&self) -> WalkOptions {
+            WalkOptions {
+                root: self.0.clone(),
+                include_hidden: false,
+                respect_ignore: true,
+                file_filter: None,
+                excluded_dirs: Vec::new(),
+                max_file_size: 0,
+                skip_binary: false,
+                metadata: None,
+            }
+        }
+    }
+
+    impl Drop for TempTree {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn walk_all(options: &WalkOptions) -> (Vec<String>, WalkSummary) {
+        let mut files = Vec::new();
+        let summary = walk(options, &WalkProgress::default(), |path, _| {
+            files.push(path.to_string_lossy().replace('\\', "/"));
+            true
+        })
+        .unwrap();
+        files.sort();
+        (files, summary)
+    }
+
+    #[test]
+    fn excluded_dirs_are_left_out_at_any_depth() {
+        let tree = TempTree::new("excluded");
+        let (files, _) = walk_all(&tree.options());
+        assert_eq!(files.len(), 6);
+
+        let mut options = tree.options();
+        options.excluded_dirs = vec!["node_modules".to_string(), " target/ ".to_string()];
+        let (files, summary) = walk_all(&options);
+        assert_eq!(files, ["./a.txt", "./big.txt", "./data.bin", "./src/main.rs"]);
+        assert_eq!(summary.files, 4);
+        assert_eq!(summary.skipped.total(), 0); // never seen, so not counted
+    }
+
+    #[test]
+    fn large_and_binary_files_are_skipped_and_counted() {
+        let tree = TempTree::new("skipped");
+        let mut options = tree.options();
+        options.max_file_size = 50;
+        options.skip_binary = true;
+        let (files, summary) = walk_all(&options);
+        assert!(!files.iter().any(|f| f == "./big.txt" || f == "./data.bin"), "{:?}", files);
+        assert_eq!(summary.skipped.too_large, 1);
+        assert_eq!(summary.skipped.binary, 1);
+        assert_eq!(summary.skipped.total(), 2);
+    }
+
+    #[test]
+    fn metadata_exclusions_are_counted_by_reason() {
+        let tree = TempTree::new("metadata");
+        let matcher = |filter: MetadataFilter| filter.compile().unwrap();
+
+        let mut options = tree.options();
+        options.metadata = matcher(MetadataFilter { min_size: Some(10), ..Default::default() });
+        let (files, summary) = walk_all(&options);
+        assert_eq!(files, ["./big.txt", "./src/main.rs"]);
+        assert_eq!(summary.skipped.size_range, 4);
+
+        options.metadata = matcher(MetadataFilter { modified_before: Some("2000-01-01".to_string()), ..Default::default() });
+        let (files, summary) = walk_all(&options);
+        assert!(files.is_empty());
+        assert_eq!(summary.skipped.modified_range, 6);
+        assert_eq!(count_files(&options, || true), Some(0));
+
+        #[cfg(unix)]
+        {
+            // Numeric uids need no user database; nobody here owns files as 54321
+            options.metadata = matcher(MetadataFilter { owner: Some("54321".to_string()), ..Default::default() });
+            let (files, summary) = walk_all(&options);
+            assert!(files.is_empty());
+            assert_eq!(summary.skipped.owner, 6);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_not_followed() {
+        let tree = TempTree::new("symlinks");
+        std::os::unix::fs::symlink(tree.0.join("a.txt"), tree.0.join("link.txt")).unwrap();
+        let (files, summary) = walk_all(&tree.options());
+        assert!(!files.iter().any(|f| f == "./link.txt"));
+        assert_eq!(summary.skipped.symlinks_or_special, 1);
+    }
+
+    #[test]
+    fn the_walk_stops_when_the_caller_does() {
+        let tree = TempTree::new("stop");
+        let mut seen = 0;
+        let summary = walk(&tree.options(), &WalkProgress::default(), |_, _| {
+            seen += 1;
+            false
+        })
+        .unwrap();
+        assert_eq!((seen, summary.files), (1, 1));
+        assert_eq!(count_files(&tree.options(), || false), None);
+    }
+}
@@ -1,4 +1,5 @@
 import type { Action } from 'svelte/action';
+import type { TokenSpan } from '../types/search';
 
 // Function to highlight text with the selected color
 function highlightText(text: string, query: string, color: string): string {
@@ -26,4 +27,57 @@ export const highlightTextAction: Action<HTMLElement, { text: string; query: str
       node.innerHTML = highlightText(text, query, color);
     }
   };
-}; 
\ No newline at end of file
+}; 
+// Escape text before it goes into innerHTML next to our own spans
+function escapeHtml(text: string): string {
+  return text.replace(/&/g, '&amp;').replace(/</g, '&lt;').replace(/>/g, '&gt;').replace(/"/g, '&quot;');
+}
+
+// Syntax tokens and query matches together: the line is cut wherever either starts or
+// ends, so a match that crosses token boundaries is still highlighted in full
+function highlightTokens(text: string, tokens: TokenSpan[], query: string, color: string): string {
+  const matches: [number, number][] = [];
+  if (query) {
+    const regex = new RegExp(query.replace(/[.*+?^${}()|[\]\\]/g, '\\$&'), 'g');
+    for (const m of text.matchAll(regex)) {
+      matches.push([m.index ?? 0, (m.index ?? 0) + m[0].length]);
+    }
+  }
+
+  const cuts = new Set<number>([0, text.length]);
+  for (const t of tokens) {
+    cuts.add(Math.min(t.start, text.length));
+    cuts.add(Math.min(t.end, text.length));
+  }
+  for (const [start, end] of matches) {
+    cuts.add(start);
+    cuts.add(end);
+  }
+  const points = [...cuts].sort((a, b) => a - b);
+
+  let html = '';
+  for (let i = 0; i + 1 < points.length; i++) {
+    const [from, to] = [points[i], points[i + 1]];
+    let piece = escapeHtml(text.slice(from, to));
+    const token = tokens.find((t) => t.start <= from && to <= t.end);
+    if (token) {
+      piece = `<span class="tok-${token.kind}">${piece}</span>`;
+    }
+    if (matches.some(([start, end]) => start <= from && to <= end)) {
+      piece = `<span style="background-color: ${color}">${piece}</span>`;
+    }
+    html += piece;
+  }
+  return html;
+}
+
+// Action for viewer lines with syntax tokens (plain lines have no tokens)
+export const highlightTokensAction: Action<HTMLElement, { text: string; tokens: TokenSpan[]; query: string; color: string }> = (node, params) => {
+  node.innerHTML = highlightTokens(params.text, params.tokens, params.query, params.color);
+
+  return {
+    update: ({ text, tokens, query, color }) => {
+      node.innerHTML = highlightTokens(text, tokens, query, color);
+    }
+  };
+};
@@ -1,11 +1,18 @@
 export interface SearchLine {
     num: string;
     content: string;
+    offset?: number;  // byte offset of a match in a binary file
+    timestamp?: number;  // log mode: unix milliseconds
+    level?: Severity;
 }
 
 export interface SearchFile {
     name: string;
     lines: SearchLine[];
+    lineCount?: number;        // text search: matched lines, of which `lines` are loaded
+    firstTimestamp?: number;   // log mode: earliest match
+    size?: number;             // file-name search hits only
+    modified?: string | null;
 }
 
 export interface SearchParams {
@@ -29,8 +36,165 @@ export interface SearchStats {
     total_matches: number;
     matched_lines: number;
     files_searched: number;
+    files_with_matches: number;
+    bytes_searched: number;
+    bytes_printed: number;
     search_time_ms: number;
     total_time_ms: number;
+    skipped?: SkippedFiles;
+    files_excluded_by_index?: number;  // ruled out by the root's content index
+    lines_excluded_by_log_filter?: number;  // outside the log time window or levels
+    index_stale_files?: number;
+    by_extension?: StatsBucket[];
+    by_directory?: StatsBucket[];
+}
+
+export interface SkippedFiles {
+    binary: number;
+    symlinks_or_special: number;
+    too_large: number;
+    error: number;
+    modified_range: number;  // left out by the metadata filters
+    size_range: number;
+    owner: number;
+}
+
+// Times are relative ("2h", "3d") or absolute ("2025-01-31 14:00"); sizes in bytes
+export interface MetadataFilter {
+    modifiedAfter?: string;
+    modifiedBefore?: string;
+    minSize?: number;
+    maxSize?: number;
+    owner?: string;
+}
+
+export type Severity = 'trace' | 'debug' | 'info' | 'warn' | 'error' | 'fatal';
+
+// Sending a LogFilter turns log mode on; times take the same forms as MetadataFilter
+export interface LogFilter {
+    from?: string;
+    to?: string;
+    levels?: Severity[];  // empty or missing = any level
+}
+
+export interface StatsBucket {
+    key: string;
+    files_searched: number;
+    files_matched: number;
+    matches: number;
+}
+
+export interface SearchProgress {
+    search_id: string;
+    matches: number;
+    files_matched: number;
+    files_visited: number;
+    bytes_scanned: number;
+    current_dir: string;
+    estimated_files: number | null;  // only when settings.search.estimate_total is on
+    elapsed_ms: number;
+}
+
+export interface SearchWarning {
+    path: string | null;
+    kind: 'permission_denied' | 'not_found' | 'symlink_loop' | 'io' | 'other';
+    message: string;
+}
+
+// A chunk of lines for the viewer (read_file_mmap_chunk)
+export interface ViewerChunk {
+    lines: string[];
+    offset: number;   // where the next chunk starts
+    has_more: boolean;
+    binary: boolean;  // no lines; read the file with read_file_hex_chunk
+    language: string | null;
+    tokens: TokenSpan[][];  // one list per line, empty when not highlighted
+}
+
+// Offsets are UTF-16 code units into the line, like JavaScript string indexes
+export interface TokenSpan {
+    start: number;
+    end: number;
+    kind: 'comment' | 'string' | 'number' | 'constant' | 'keyword' | 'operator' | 'type' | 'function' | 'variable' | 'tag' | 'attribute';
+}
+
+// Viewer rows for binary files (read_file_hex_chunk)
+export interface HexRow {
+    offset: number;
+    hex: string;
+    ascii: string;
+}
+
+export interface HexChunk {
+    rows: HexRow[];
+    start: number;   // offset of the first row
+    offset: number;  // where the next chunk starts
+    total_bytes: number;
+    has_more: boolean;
+}
+
+// Sent when a search started with start_search ends
+export interface SearchFinished {
+    search_id: string;
+    status: 'completed' | 'cancelled' | 'failed';
+    stats: SearchStats | null;
+    warnings: SearchWarning[];
+    truncated: boolean;
+    error: string | null;
+}
+
+// One row of get_results_page; paths are relative to the search root
+export interface ResultGroup {
+    key: string;
+    kind: 'file' | 'directory';
+    file_count: number;
+    match_count: number;
+    line_count: number;
+    size: number | null;
+    modified: number | null;
+    first_timestamp: number | null;
+}
+
+export interface ResultsPage {
+    total_groups: number;
+    offset: number;
+    groups: ResultGroup[];
+}
+
+// The matches of one file (get_file_matches)
+export interface MatchesPage {
+    path: string;
+    total: number;
+    offset: number;
+    matches: StoredMatch[];
+}
+
+// A match as the backend stores it (get_file_matches, get_timeline)
+export interface StoredMatch {
+    path: string;
+    line: number;
+    text: string;
+    ranges: { start: number; end: number }[];
+    binary_offset: number | null;
+    timestamp: number | null;  // log mode only: unix milliseconds
+    level: Severity | null;
+}
+
+// Matches of all files, oldest first
+export interface TimelinePage {
+    total: number;
+    offset: number;
+    sources: number;  // files the matches come from
+    matches: StoredMatch[];
+}
+
+// Lines around a timeline entry (read_context_lines)
+export interface ContextLines {
+    first_line: number;  // 1-based number of lines[0]
+    lines: string[];
+    fields: { timestamp: number | null; level: Severity | null }[];  // parallel to lines
+    has_before: boolean;
+    has_after: boolean;
 }
 
 export interface FileMatch {
@@ -38,3 +202,31 @@ export interface FileMatch {
     line: number;
     content: string;
 }
+
+export interface IndexStatus {
+    root: string;
+    state: 'missing' | 'building' | 'ready' | 'failed';
+    files: number;
+    size_bytes: number;
+    built: string | null;
+    files_processed: number;  // while a build runs
+    error: string | null;
+}
+
+export type NameMode = '' | 'fuzzy' | 'glob' | 'regex';
+
+export interface FileHit {
+    path: string;
+    size: number;
+    modified: string | null;
+    score: number | null;  // fuzzy mode only; higher is better
+}
+
+export interface FileSearchFinished {
+    search_id: string;
+    status: 'completed' | 'cancelled' | 'failed';
+    stats: { files_visited: number; files_matched: number; skipped: SkippedFiles; total_time_ms: number } | null;
+    warnings: SearchWarning[];
+    truncated: boolean;
+    error: string | null;
+}